@@ -1,26 +1,84 @@
 use std::{
-    collections::VecDeque,
     future::{Future, Pending},
-    sync::{Arc, Mutex},
+    pin::Pin,
+    task::Poll,
 };
-const DEFAULT_EXECUTE_DURATION: std::time::Duration = std::time::Duration::from_millis(100);
+
+use async_channel::{Receiver, Sender, TrySendError};
+use futures_core::Stream;
+const DEFAULT_EXECUTE_DURATION: std::time::Duration = std::time::Duration::from_secs(60);
+const DEFAULT_CHANNEL_CAPACITY: usize = 1024;
 use crate::{
-    Scheduler, Task, TaskUid, handle_manager::HandleManager, prelude::AsyncRuntime,
+    AddOutcome, Dtu, RemovedTask, Scheduler, Task, TaskRun, TaskUid,
+    handle_manager::{HandleManager, RunHandle, TrackHandles},
+    history::RunRecord,
+    prelude::AsyncRuntime,
     runtime::Runtime,
 };
 #[cfg(feature = "async-std")]
 mod async_std;
+mod reply;
+mod ticker;
+use reply::ReplySender;
+pub use reply::{Canceled, Reply};
+pub use ticker::*;
 #[cfg(feature = "tokio")]
 mod tokio;
 
 #[derive(Debug)]
 enum Event<R: Runtime> {
-    AddTask(TaskUid, Task<R>),
-    RemoveTask(TaskUid),
+    AddTask(TaskUid, Task<R>, ReplySender<AddOutcome>),
+    RemoveTask(TaskUid, ReplySender<Option<RemovedTask>>),
+    NextRun(TaskUid, ReplySender<Option<Dtu>>),
+    List(ReplySender<Vec<TaskRun>>),
+    History(TaskUid, ReplySender<Vec<RunRecord>>),
+    TriggerNow(TaskUid, ReplySender<Option<TaskRun>>),
+}
+
+/// Error of [`AsyncSchedulerClient::try_add_task`], it gives the task back.
+pub enum TryAddTaskError<R: Runtime> {
+    /// the channel to the runner is full
+    Full(Task<R>),
+    /// the runner has been dropped
+    Closed(Task<R>),
+}
+
+impl<R: Runtime> TryAddTaskError<R> {
+    pub fn into_task(self) -> Task<R> {
+        match self {
+            Self::Full(task) | Self::Closed(task) => task,
+        }
+    }
 }
 
+impl<R: Runtime> std::fmt::Debug for TryAddTaskError<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Full(_) => f.write_str("Full(..)"),
+            Self::Closed(_) => f.write_str("Closed(..)"),
+        }
+    }
+}
+
+impl<R: Runtime> std::fmt::Display for TryAddTaskError<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Full(_) => f.write_str("the scheduler runner channel is full"),
+            Self::Closed(_) => f.write_str("the scheduler runner was dropped"),
+        }
+    }
+}
+
+impl<R: Runtime> std::error::Error for TryAddTaskError<R> {}
+
 /// A implementation of async scheduler runner
 ///
+/// The runner sleeps until the next task is due, or until a client sends an event, with a
+/// single reusable timer of the runtime.
+///
+/// The clients send their events through a bounded channel, of 1024 events by default, see
+/// [`AsyncSchedulerRunner::with_capacity`].
+///
 /// ```
 /// # use tsuki_scheduler::prelude::*;
 /// // create runner
@@ -35,35 +93,62 @@ enum Event<R: Runtime> {
 pub struct AsyncSchedulerRunner<R: AsyncRuntime, H = ()> {
     /// inner scheduler
     pub scheduler: Scheduler<R, H>,
-    /// execute duration
+    /// The longest time to sleep without reading the clock, 60 seconds by default.
+    ///
+    /// The runner reads the clock again at least this often, e.g. to follow the changes of a
+    /// [`ManualClock`](crate::clock::ManualClock).
     pub execute_duration: std::time::Duration,
-    event_queue: Arc<Mutex<VecDeque<Event<R>>>>,
+    sender: Sender<Event<R>>,
+    receiver: Pin<Box<Receiver<Event<R>>>>,
 }
 
 impl<R, H> Default for AsyncSchedulerRunner<R, H>
 where
     R: AsyncRuntime + Default,
-    H: Default,
+    H: HandleManager<R::Handle> + Default,
 {
     fn default() -> Self {
-        Self {
-            scheduler: Scheduler::default(),
-            execute_duration: DEFAULT_EXECUTE_DURATION,
-            event_queue: Default::default(),
-        }
+        Self::new(Scheduler::default())
+    }
+}
+
+impl<R: AsyncRuntime, H> Drop for AsyncSchedulerRunner<R, H> {
+    fn drop(&mut self) {
+        // cancel the pending requests, even if some clients are still alive
+        self.receiver.close();
+        while self.receiver.try_recv().is_ok() {}
     }
 }
 
 impl<R: AsyncRuntime, H: HandleManager<R::Handle>> AsyncSchedulerRunner<R, H> {
     /// create a new async scheduler runner
     pub fn new(scheduler: Scheduler<R, H>) -> Self {
+        Self::with_capacity(scheduler, DEFAULT_CHANNEL_CAPACITY)
+    }
+    /// Create a new async scheduler runner, the clients may queue up to `capacity` events.
+    ///
+    /// # Panics
+    /// if `capacity` is zero
+    pub fn with_capacity(scheduler: Scheduler<R, H>, capacity: usize) -> Self {
+        let (sender, receiver) = async_channel::bounded(capacity);
         Self {
             scheduler,
             execute_duration: DEFAULT_EXECUTE_DURATION,
-            event_queue: Default::default(),
+            sender,
+            receiver: Box::pin(receiver),
         }
     }
-    /// set execute duration
+    /// set the clock of the inner scheduler, see [`Scheduler::with_clock`]
+    pub fn with_clock<C: crate::clock::Clock + 'static>(mut self, clock: C) -> Self {
+        self.scheduler.clock = std::sync::Arc::new(clock);
+        self
+    }
+    /// share a state with the task functions, see [`Scheduler::with_state`]
+    pub fn with_state<T: Send + Sync + 'static>(mut self, state: T) -> Self {
+        self.scheduler.insert_state(state);
+        self
+    }
+    /// set the longest time to sleep without reading the clock
     pub fn with_execute_duration(mut self, duration: std::time::Duration) -> Self {
         self.execute_duration = duration;
         self
@@ -71,7 +156,7 @@ impl<R: AsyncRuntime, H: HandleManager<R::Handle>> AsyncSchedulerRunner<R, H> {
     /// get runner client
     pub fn client(&self) -> AsyncSchedulerClient<R> {
         AsyncSchedulerClient {
-            event_queue: self.event_queue.clone(),
+            sender: self.sender.clone(),
         }
     }
     /// start running
@@ -79,117 +164,313 @@ impl<R: AsyncRuntime, H: HandleManager<R::Handle>> AsyncSchedulerRunner<R, H> {
         self.run_with_shutdown_signal(std::future::pending())
     }
 
-    /// start running with shutdown signal
+    /// Start running until the shutdown signal resolves.
+    ///
+    /// The signal may be any future, its output is handed back with the runner, e.g. a
+    /// shutdown reason.
+    /// ```
+    /// # use tsuki_scheduler::prelude::*;
+    /// # use std::time::Duration;
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let runner = AsyncSchedulerRunner::<Tokio>::default();
+    /// let (runner, reason) = runner
+    ///     .run_with_shutdown_signal(async {
+    ///         tokio::time::sleep(Duration::from_millis(10)).await;
+    ///         "timeout"
+    ///     })
+    ///     .await;
+    /// assert_eq!(reason, "timeout");
+    /// # }
+    /// ```
     pub fn run_with_shutdown_signal<S>(self, shutdown_signal: S) -> AsyncSchedulerRunning<R, H, S>
     where
-        S: Future<Output = ()> + Send,
+        S: Future + Send,
     {
         AsyncSchedulerRunning {
             runner: Some(self),
-            event_queue: VecDeque::new(),
+            sleep: None,
             shutdown_signal,
         }
     }
 }
 
+impl<R> AsyncSchedulerRunner<R, TrackHandles<R::Handle>>
+where
+    R: AsyncRuntime,
+    R::Handle: RunHandle,
+{
+    /// Run until the shutdown signal resolves, then shut down gracefully, see
+    /// [`drain`](AsyncSchedulerRunner::drain).
+    ///
+    /// Returns the runner, the output of the signal and the report of the runs in flight.
+    ///
+    /// ```
+    /// # use tsuki_scheduler::prelude::*;
+    /// # use std::time::Duration;
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let runner = AsyncSchedulerRunner::<Tokio, TrackHandles<_>>::default();
+    /// let signal = tokio::time::sleep(Duration::from_millis(10));
+    /// let (runner, (), report) = runner
+    ///     .run_with_graceful_shutdown(signal, Duration::from_secs(5))
+    ///     .await;
+    /// assert!(report.is_clean());
+    /// # }
+    /// ```
+    pub async fn run_with_graceful_shutdown<S>(
+        self,
+        shutdown_signal: S,
+        grace: std::time::Duration,
+    ) -> (Self, S::Output, ShutdownReport)
+    where
+        S: Future + Send,
+    {
+        let (mut runner, output) = self.run_with_shutdown_signal(shutdown_signal).await;
+        let report = runner.drain(grace).await;
+        (runner, output, report)
+    }
+    /// Wait for the runs in flight, for `grace` at most, then abort the runs left where the
+    /// runtime can.
+    ///
+    /// No new run is fired while draining, the runner can be run again afterwards.
+    pub async fn drain(&mut self, grace: std::time::Duration) -> ShutdownReport {
+        let mut in_flight = self.scheduler.handle_manager.take();
+        let mut report = ShutdownReport::default();
+        let mut deadline = self.scheduler.runtime.sleep(grace);
+        std::future::poll_fn(|cx| {
+            in_flight.retain_mut(|(run, handle)| {
+                let finished = handle.poll_finished(cx).is_ready();
+                if finished {
+                    report.completed.push(run.clone());
+                }
+                !finished
+            });
+            if in_flight.is_empty() {
+                return Poll::Ready(());
+            }
+            Pin::new(&mut deadline).poll(cx)
+        })
+        .await;
+        for (run, mut handle) in in_flight {
+            if handle.abort() {
+                report.aborted.push(run);
+            } else {
+                report.running.push(run);
+            }
+        }
+        report
+    }
+}
+
+/// The outcome of the runs in flight at a graceful shutdown, see
+/// [`AsyncSchedulerRunner::drain`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// the runs finished before the deadline
+    pub completed: Vec<TaskRun>,
+    /// the runs aborted at the deadline
+    pub aborted: Vec<TaskRun>,
+    /// the runs still running after the deadline, their runtime can't abort them
+    pub running: Vec<TaskRun>,
+}
+
+impl ShutdownReport {
+    /// every run in flight finished before the deadline
+    pub fn is_clean(&self) -> bool {
+        self.aborted.is_empty() && self.running.is_empty()
+    }
+}
+
 /// Client for [`AsyncSchedulerRunner`]
 ///
 /// created by [`AsyncSchedulerRunner::client`].
 ///
 /// # Clone
 /// this client is cheap to clone.
+///
+/// # Replies and backpressure
+/// The async methods wait for room in the channel, then for the reply of the runner. They
+/// fail with [`Canceled`] if the runner is dropped.
+///
+/// [`try_add_task`](AsyncSchedulerClient::try_add_task) never waits, it fails if the channel
+/// is full, and returns the [`Reply`] to await, or to drop to fire and forget.
+/// ```
+/// # use tsuki_scheduler::prelude::*;
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let runner = AsyncSchedulerRunner::<Tokio>::default();
+/// let client = runner.client();
+/// tokio::spawn(runner.run());
+/// let id = TaskUid::new(0);
+/// let outcome = client
+///     .add_task(id, Task::new_async(now() + chrono::TimeDelta::hours(1), || async {}))
+///     .await
+///     .expect("runner is running");
+/// assert!(matches!(outcome, AddOutcome::Added { .. }));
+/// assert_eq!(client.list().await.expect("runner is running").len(), 1);
+/// # }
+/// ```
 #[derive(Debug)]
 pub struct AsyncSchedulerClient<R: AsyncRuntime> {
-    event_queue: Arc<Mutex<VecDeque<Event<R>>>>,
+    sender: Sender<Event<R>>,
 }
 
 impl<R: AsyncRuntime> Clone for AsyncSchedulerClient<R> {
     fn clone(&self) -> Self {
         Self {
-            event_queue: self.event_queue.clone(),
+            sender: self.sender.clone(),
         }
     }
 }
 
 impl<R: AsyncRuntime> AsyncSchedulerClient<R> {
-    /// add a new task
-    pub fn add_task(&self, key: TaskUid, task: Task<R>) {
-        let mut queue = self.event_queue.lock().expect("lock event queue failed");
-        queue.push_back(Event::AddTask(key, task));
+    /// add a new task, see [`Scheduler::add_task`]
+    pub async fn add_task(&self, key: TaskUid, task: Task<R>) -> Result<AddOutcome, Canceled> {
+        self.request(|reply| Event::AddTask(key, task, reply)).await
+    }
+    /// Add a new task without waiting for room in the channel.
+    ///
+    /// The task is queued when this returns `Ok`, the [`Reply`] may be dropped.
+    pub fn try_add_task(
+        &self,
+        key: TaskUid,
+        task: Task<R>,
+    ) -> Result<Reply<AddOutcome>, TryAddTaskError<R>> {
+        let (sender, reply) = reply::channel();
+        match self.sender.try_send(Event::AddTask(key, task, sender)) {
+            Ok(()) => Ok(reply),
+            Err(TrySendError::Full(Event::AddTask(_, task, _))) => Err(TryAddTaskError::Full(task)),
+            Err(TrySendError::Closed(Event::AddTask(_, task, _))) => {
+                Err(TryAddTaskError::Closed(task))
+            }
+            Err(_) => unreachable!("an add task event is sent"),
+        }
+    }
+    /// remove a task by id, replies none if there was no such task
+    pub async fn remove_task(&self, key: TaskUid) -> Result<Option<RemovedTask>, Canceled> {
+        self.request(|reply| Event::RemoveTask(key, reply)).await
     }
-    /// remove a task by id
-    pub fn remove_task(&self, key: TaskUid) {
-        let mut queue = self.event_queue.lock().expect("lock event queue failed");
-        queue.push_back(Event::RemoveTask(key));
+    /// the next run of a task, see [`Scheduler::next_run`]
+    pub async fn next_run(&self, key: TaskUid) -> Result<Option<Dtu>, Canceled> {
+        self.request(|reply| Event::NextRun(key, reply)).await
+    }
+    /// the next run of every task, see [`Scheduler::list`]
+    pub async fn list(&self) -> Result<Vec<TaskRun>, Canceled> {
+        self.request(Event::List).await
+    }
+    /// the recorded runs of a task, see [`Scheduler::history`]
+    pub async fn history(&self, key: TaskUid) -> Result<Vec<RunRecord>, Canceled> {
+        self.request(|reply| Event::History(key, reply)).await
+    }
+    /// run a task now, see [`Scheduler::trigger_now`]
+    pub async fn trigger_now(&self, key: TaskUid) -> Result<Option<TaskRun>, Canceled> {
+        self.request(|reply| Event::TriggerNow(key, reply)).await
+    }
+    /// send an event once there is room in the channel, and wait for the reply
+    async fn request<T>(
+        &self,
+        event: impl FnOnce(ReplySender<T>) -> Event<R>,
+    ) -> Result<T, Canceled> {
+        let (sender, reply) = reply::channel();
+        self.sender
+            .send(event(sender))
+            .await
+            .map_err(|_| Canceled)?;
+        reply.await
     }
 }
 
-pub struct AsyncSchedulerRunning<R, H, S>
-where
-    R: AsyncRuntime + Send,
-    H: HandleManager<R::Handle>,
-    S: Future<Output = ()> + Send,
-{
-    runner: Option<AsyncSchedulerRunner<R, H>>,
-    event_queue: VecDeque<Event<R>>,
-    shutdown_signal: S,
+pin_project_lite::pin_project! {
+    /// The future of a running [`AsyncSchedulerRunner`], created by
+    /// [`run_with_shutdown_signal`](AsyncSchedulerRunner::run_with_shutdown_signal).
+    ///
+    /// It resolves to the runner and the output of the shutdown signal once the signal resolves.
+    pub struct AsyncSchedulerRunning<R, H, S>
+    where
+        R: AsyncRuntime,
+    {
+        runner: Option<AsyncSchedulerRunner<R, H>>,
+        sleep: Option<R::Sleep>,
+        #[pin]
+        shutdown_signal: S,
+    }
 }
 
-impl<R, H, S> Unpin for AsyncSchedulerRunning<R, H, S>
-where
-    R: AsyncRuntime,
-    H: HandleManager<R::Handle>,
-    S: Future<Output = ()> + Unpin + Send,
-{
-}
 impl<R, H, S> Future for AsyncSchedulerRunning<R, H, S>
 where
     R: AsyncRuntime,
     H: HandleManager<R::Handle>,
-    S: Future<Output = ()> + Unpin + Send,
+    S: Future,
 {
-    type Output = AsyncSchedulerRunner<R, H>;
+    type Output = (AsyncSchedulerRunner<R, H>, S::Output);
     fn poll(
         self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Self::Output> {
-        let this = self.get_mut();
-        let shutdown_signal = std::pin::pin!(&mut this.shutdown_signal);
-        match shutdown_signal.poll(cx) {
-            std::task::Poll::Ready(_) => {
-                let runner = this.runner.take().expect("missing runner");
-                {
-                    let mut add_task_queue =
-                        runner.event_queue.lock().expect("lock event queue failed");
-                    while let Some(event) = this.event_queue.pop_back() {
-                        add_task_queue.push_front(event)
-                    }
-                }
-                std::task::Poll::Ready(runner)
+        let this = self.project();
+        match this.shutdown_signal.poll(cx) {
+            std::task::Poll::Ready(output) => {
+                // the events left stay in the channel for the next run
+                let runner = this.runner.take().expect("polled after completion");
+                std::task::Poll::Ready((runner, output))
             }
             std::task::Poll::Pending => {
                 let runner = this.runner.as_mut().expect("missing runner");
-                {
-                    let mut add_task_queue =
-                        runner.event_queue.lock().expect("lock event queue failed");
-                    std::mem::swap(&mut this.event_queue, &mut add_task_queue);
-                }
-                while let Some(evt) = this.event_queue.pop_front() {
-                    match evt {
-                        Event::AddTask(key, task) => {
-                            runner.scheduler.add_task(key, task);
+                // the tasks wake the runner when changing the scheduler
+                runner.scheduler.commands.register(cx.waker());
+                loop {
+                    // drain the channel, then wait for the next event
+                    while let std::task::Poll::Ready(Some(evt)) =
+                        runner.receiver.as_mut().poll_next(cx)
+                    {
+                        let scheduler = &mut runner.scheduler;
+                        match evt {
+                            Event::AddTask(key, task, reply) => {
+                                reply.send(scheduler.add_task(key, task));
+                            }
+                            Event::RemoveTask(key, reply) => {
+                                let next_run = scheduler.next_run(key);
+                                let removed = scheduler
+                                    .delete_task(key)
+                                    .map(|_| RemovedTask { key, next_run });
+                                reply.send(removed);
+                            }
+                            Event::NextRun(key, reply) => {
+                                reply.send(scheduler.next_run(key));
+                            }
+                            Event::List(reply) => {
+                                reply.send(scheduler.list());
+                            }
+                            Event::History(key, reply) => {
+                                reply.send(scheduler.history(key));
+                            }
+                            Event::TriggerNow(key, reply) => {
+                                reply.send(scheduler.trigger_now(key));
+                            }
                         }
-                        Event::RemoveTask(key) => {
-                            runner.scheduler.delete_task(key);
+                    }
+                    runner.scheduler.execute_by_now();
+                    // sleep until the next task is due
+                    let duration = match runner.scheduler.next_deadline() {
+                        Some(deadline) => (deadline - runner.scheduler.now())
+                            .to_std()
+                            .unwrap_or_default()
+                            .min(runner.execute_duration),
+                        None => runner.execute_duration,
+                    };
+                    let runtime = &runner.scheduler.runtime;
+                    let sleep = match this.sleep {
+                        Some(sleep) => {
+                            runtime.reset(sleep, duration);
+                            sleep
                         }
+                        None => this.sleep.insert(runtime.sleep(duration)),
+                    };
+                    if std::pin::Pin::new(sleep).poll(cx).is_pending() {
+                        return std::task::Poll::Pending;
                     }
                 }
-                runner.scheduler.execute_by_now();
-                runner
-                    .scheduler
-                    .runtime
-                    .wake_after(runner.execute_duration, cx);
-                std::task::Poll::Pending
             }
         }
     }
@@ -1,12 +1,11 @@
+use std::{future::Future, pin::Pin};
+
 use crate::{AsyncSchedulerRunner, prelude::AsyncRuntime, runtime::AsyncStd};
 
 impl AsyncRuntime for AsyncStd {
-    fn wake_after(&self, duration: std::time::Duration, ctx: &mut std::task::Context<'_>) {
-        let waker = ctx.waker().clone();
-        async_std::task::spawn(async move {
-            async_std::task::sleep(duration).await;
-            waker.wake()
-        });
+    type Sleep = Pin<Box<dyn Future<Output = ()> + Send>>;
+    fn sleep(&self, duration: std::time::Duration) -> Self::Sleep {
+        Box::pin(async_std::task::sleep(duration))
     }
     fn spawn<F>(task: F) -> Self::Handle
     where
@@ -0,0 +1,89 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    task::{Context, Poll, Waker},
+};
+
+#[derive(Debug)]
+struct Slot<T> {
+    value: Option<T>,
+    waker: Option<Waker>,
+    closed: bool,
+}
+
+/// lock a slot, it is left consistent by a panic of the other half
+fn lock<T>(slot: &Mutex<Slot<T>>) -> MutexGuard<'_, Slot<T>> {
+    slot.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Create a one-shot reply channel, it doesn't depend on any runtime.
+pub(crate) fn channel<T>() -> (ReplySender<T>, Reply<T>) {
+    let slot = Arc::new(Mutex::new(Slot {
+        value: None,
+        waker: None,
+        closed: false,
+    }));
+    (ReplySender { slot: slot.clone() }, Reply { slot })
+}
+
+/// The sending half of a [`Reply`], kept by the runner.
+#[derive(Debug)]
+pub(crate) struct ReplySender<T> {
+    slot: Arc<Mutex<Slot<T>>>,
+}
+
+impl<T> ReplySender<T> {
+    pub(crate) fn send(self, value: T) {
+        lock(&self.slot).value = Some(value);
+    }
+}
+
+impl<T> Drop for ReplySender<T> {
+    fn drop(&mut self) {
+        let waker = {
+            let mut slot = lock(&self.slot);
+            slot.closed = true;
+            slot.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// The reply of the runner to a request of an [`AsyncSchedulerClient`](super::AsyncSchedulerClient).
+///
+/// The request is sent when the client method is called, await the reply to get its result,
+/// or drop it to ignore the result.
+#[derive(Debug)]
+pub struct Reply<T> {
+    slot: Arc<Mutex<Slot<T>>>,
+}
+
+/// The runner was dropped before replying.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Canceled;
+
+impl std::fmt::Display for Canceled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("the scheduler runner was dropped before replying")
+    }
+}
+
+impl std::error::Error for Canceled {}
+
+impl<T> Future for Reply<T> {
+    type Output = Result<T, Canceled>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut slot = lock(&self.slot);
+        if let Some(value) = slot.value.take() {
+            Poll::Ready(Ok(value))
+        } else if slot.closed {
+            Poll::Ready(Err(Canceled))
+        } else {
+            slot.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
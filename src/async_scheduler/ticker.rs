@@ -0,0 +1,181 @@
+use std::{
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use chrono::TimeDelta;
+use futures_core::Stream;
+
+use crate::{
+    Dtu,
+    clock::{Clock, SystemClock},
+    runtime::AsyncRuntime,
+    schedule::{IntoSchedule, Schedule},
+};
+
+/// What a [`Ticker`] does with the time points missed while it was not polled, e.g. when the
+/// loop body took longer than the interval.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum MissedTickBehavior {
+    /// yield every missed time point right away
+    #[default]
+    Burst,
+    /// yield the first missed time point right away, skip the others
+    Skip,
+    /// yield the first missed time point right away, and put off the following ones by how late
+    /// it was, so the next tick is one interval after now, the ticks are the put off time points
+    Delay,
+}
+
+/// The time points of a schedule as a stream, waiting for each one with the timer of an
+/// [`AsyncRuntime`], without a scheduler.
+///
+/// # Example
+/// ```
+/// # #[cfg(not(feature = "tokio"))] fn main() {}
+/// # #[cfg(feature = "tokio")] #[tokio::main(flavor = "current_thread")] async fn main() {
+/// # use tsuki_scheduler::prelude::*;
+/// use chrono::TimeDelta;
+/// let schedule = Period::new(TimeDelta::milliseconds(10), now()).take(3);
+/// let mut ticks = Ticker::tokio(schedule).with_missed_tick_behavior(MissedTickBehavior::Skip);
+/// while let Some(time) = ticks.tick().await {
+///     println!("tick at {time}");
+/// }
+/// # }
+/// ```
+pub struct Ticker<R: AsyncRuntime, S> {
+    runtime: R,
+    schedule: S,
+    clock: Arc<dyn Clock>,
+    missed_tick_behavior: MissedTickBehavior,
+    /// how far the time points are put off, see [`MissedTickBehavior::Delay`]
+    delay: TimeDelta,
+    /// the timer, and the time point it waits for
+    sleep: Option<(Dtu, R::Sleep)>,
+}
+
+impl<R: AsyncRuntime + std::fmt::Debug, S: std::fmt::Debug> std::fmt::Debug for Ticker<R, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Ticker")
+            .field("runtime", &self.runtime)
+            .field("schedule", &self.schedule)
+            .field("missed_tick_behavior", &self.missed_tick_behavior)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Nothing is pinned in place, the timer of an [`AsyncRuntime`] is `Unpin`.
+impl<R: AsyncRuntime, S> Unpin for Ticker<R, S> {}
+
+impl<R: AsyncRuntime, S: Schedule> Ticker<R, S> {
+    pub fn new<I: IntoSchedule<Output = S>>(runtime: R, schedule: I) -> Self {
+        Self {
+            runtime,
+            schedule: schedule.into_schedule(),
+            clock: Arc::new(SystemClock),
+            missed_tick_behavior: MissedTickBehavior::default(),
+            delay: TimeDelta::zero(),
+            sleep: None,
+        }
+    }
+    /// set the clock, the time points are compared to its current time
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+    pub fn with_missed_tick_behavior(mut self, behavior: MissedTickBehavior) -> Self {
+        self.missed_tick_behavior = behavior;
+        self
+    }
+    pub fn schedule(&self) -> &S {
+        &self.schedule
+    }
+    pub fn into_schedule(self) -> S {
+        self.schedule
+    }
+    /// wait for the next time point, none once the schedule has no time point left
+    pub async fn tick(&mut self) -> Option<Dtu> {
+        std::future::poll_fn(|cx| self.poll_tick(cx)).await
+    }
+    /// Poll for the next time point, the waker of `cx` is woken when it is due.
+    pub fn poll_tick(&mut self, cx: &mut Context<'_>) -> Poll<Option<Dtu>> {
+        loop {
+            let Some(next) = self
+                .schedule
+                .peek_next()
+                .and_then(|next| next.checked_add_signed(self.delay))
+            else {
+                self.sleep = None;
+                return Poll::Ready(None);
+            };
+            let now = self.clock.now();
+            if next <= now {
+                self.sleep = None;
+                self.schedule.next();
+                match self.missed_tick_behavior {
+                    MissedTickBehavior::Burst => {}
+                    MissedTickBehavior::Skip => self.schedule.forward_to(now - self.delay),
+                    MissedTickBehavior::Delay => self.delay += now - next,
+                }
+                return Poll::Ready(Some(next));
+            }
+            let duration = (next - now).to_std().unwrap_or_default();
+            match &mut self.sleep {
+                Some((deadline, sleep)) if *deadline != next => {
+                    self.runtime.reset(sleep, duration);
+                    *deadline = next;
+                }
+                Some(_) => {}
+                None => self.sleep = Some((next, self.runtime.sleep(duration))),
+            }
+            let (_, sleep) = self.sleep.as_mut().expect("the timer is set");
+            if Pin::new(sleep).poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+            // the timer may complete a bit before the clock reaches the time point
+            self.sleep = None;
+        }
+    }
+}
+
+/// Wait for the time points of a schedule with the tokio timer, see [`Ticker`].
+///
+/// # Example
+/// ```
+/// # #[cfg(not(feature = "tokio"))] fn main() {}
+/// # #[cfg(feature = "tokio")] #[tokio::main(flavor = "current_thread")] async fn main() {
+/// # use tsuki_scheduler::prelude::*;
+/// use chrono::TimeDelta;
+/// let mut ticks = ticker(Period::new(TimeDelta::milliseconds(10), now()).take(3))
+///     .with_missed_tick_behavior(MissedTickBehavior::Delay);
+/// while let Some(time) = ticks.tick().await {
+///     println!("tick at {time}");
+/// }
+/// # }
+/// ```
+#[cfg(feature = "tokio")]
+pub fn ticker<I: IntoSchedule>(schedule: I) -> Ticker<crate::runtime::Tokio, I::Output> {
+    Ticker::tokio(schedule)
+}
+
+#[cfg(feature = "tokio")]
+impl<S: Schedule> Ticker<crate::runtime::Tokio, S> {
+    pub fn tokio<I: IntoSchedule<Output = S>>(schedule: I) -> Self {
+        Self::new(crate::runtime::Tokio, schedule)
+    }
+}
+
+#[cfg(feature = "async-std")]
+impl<S: Schedule> Ticker<crate::runtime::AsyncStd, S> {
+    pub fn async_std<I: IntoSchedule<Output = S>>(schedule: I) -> Self {
+        Self::new(crate::runtime::AsyncStd, schedule)
+    }
+}
+
+impl<R: AsyncRuntime, S: Schedule> Stream for Ticker<R, S> {
+    type Item = Dtu;
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_tick(cx)
+    }
+}
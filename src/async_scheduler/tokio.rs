@@ -1,14 +1,16 @@
+use std::pin::Pin;
+
 use crate::{AsyncSchedulerRunner, runtime::Tokio};
 
 use crate::runtime::AsyncRuntime;
 
 impl AsyncRuntime for Tokio {
-    fn wake_after(&self, duration: std::time::Duration, ctx: &mut std::task::Context<'_>) {
-        let waker = ctx.waker().clone();
-        tokio::task::spawn(async move {
-            tokio::time::sleep(duration).await;
-            waker.wake()
-        });
+    type Sleep = Pin<Box<tokio::time::Sleep>>;
+    fn sleep(&self, duration: std::time::Duration) -> Self::Sleep {
+        Box::pin(tokio::time::sleep(duration))
+    }
+    fn reset(&self, sleep: &mut Self::Sleep, duration: std::time::Duration) {
+        sleep.as_mut().reset(tokio::time::Instant::now() + duration);
     }
     fn spawn<F>(task: F) -> Self::Handle
     where
@@ -0,0 +1,315 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Condvar, Mutex, MutexGuard, mpsc},
+    task::{Wake, Waker},
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use crate::{
+    AddOutcome, Dtu, RemovedTask, Scheduler, Task, TaskRun, TaskUid, handle_manager::HandleManager,
+    history::RunRecord, runtime::Runtime,
+};
+
+const DEFAULT_EXECUTE_DURATION: Duration = Duration::from_secs(60);
+
+type ReplySender<T> = mpsc::SyncSender<T>;
+
+#[derive(Debug)]
+enum Command<R: Runtime> {
+    AddTask(TaskUid, Task<R>, ReplySender<AddOutcome>),
+    RemoveTask(TaskUid, ReplySender<Option<RemovedTask>>),
+    NextRun(TaskUid, ReplySender<Option<Dtu>>),
+    List(ReplySender<Vec<TaskRun>>),
+    History(TaskUid, ReplySender<Vec<RunRecord>>),
+    TriggerNow(TaskUid, ReplySender<Option<TaskRun>>),
+}
+
+#[derive(Debug)]
+struct State<R: Runtime> {
+    commands: VecDeque<Command<R>>,
+    execute_duration: Duration,
+    shutdown: bool,
+    /// a task changed the scheduler, see [`SchedulerCtx`](crate::ctx::SchedulerCtx)
+    woken: bool,
+}
+
+/// The state shared by the runner thread and the clients.
+#[derive(Debug)]
+struct Shared<R: Runtime> {
+    state: Mutex<State<R>>,
+    condvar: Condvar,
+}
+
+impl<R: Runtime> Shared<R> {
+    /// the state, also when poisoned: the runner and the clients only queue commands and set
+    /// flags in it, a panic can't leave one half done
+    fn lock(&self) -> MutexGuard<'_, State<R>> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// The runner thread is stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Stopped;
+
+impl std::fmt::Display for Stopped {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("the scheduler runner thread is stopped")
+    }
+}
+
+impl std::error::Error for Stopped {}
+
+/// A scheduler running on a dedicated thread, for the runtimes without an event loop, like
+/// [`Thread`](crate::runtime::Thread) and [`Local`](crate::runtime::Local).
+///
+/// The thread sleeps until the next task is due, or until a client sends a command.
+///
+/// ```
+/// # use tsuki_scheduler::prelude::*;
+/// use chrono::TimeDelta;
+/// let runner = BlockingSchedulerRunner::new(Scheduler::new(Local::new()));
+/// let client = runner.client();
+/// let id = TaskUid::new(0);
+/// let outcome = client
+///     .add_task(id, Task::local(now() + TimeDelta::hours(1), || println!("hello")))
+///     .expect("runner is running");
+/// assert!(matches!(outcome, AddOutcome::Added { .. }));
+/// // stop the thread and get the scheduler back
+/// let scheduler = runner.shutdown();
+/// assert!(scheduler.next_run(id).is_some());
+/// ```
+///
+/// # Local runtime
+/// With [`Local`](crate::runtime::Local), the tasks run on the runner thread: they must not
+/// wait for a client of their own runner, it would never reply.
+#[derive(Debug)]
+pub struct BlockingSchedulerRunner<R: Runtime, H = ()> {
+    shared: Arc<Shared<R>>,
+    thread: Option<JoinHandle<Scheduler<R, H>>>,
+}
+
+impl<R, H> BlockingSchedulerRunner<R, H>
+where
+    R: Runtime + Send + 'static,
+    H: HandleManager<R::Handle> + Send + 'static,
+{
+    /// start running a scheduler on a new thread
+    ///
+    /// # Panics
+    /// if the thread can't be created
+    pub fn new(scheduler: Scheduler<R, H>) -> Self {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                commands: VecDeque::new(),
+                execute_duration: DEFAULT_EXECUTE_DURATION,
+                shutdown: false,
+                woken: false,
+            }),
+            condvar: Condvar::new(),
+        });
+        let thread = std::thread::Builder::new()
+            .name(env!("CARGO_CRATE_NAME").to_string())
+            .spawn({
+                let shared = shared.clone();
+                move || run(scheduler, shared)
+            })
+            .expect("failed to spawn the scheduler thread");
+        Self {
+            shared,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl<R: Runtime, H> BlockingSchedulerRunner<R, H> {
+    /// Set the longest time to sleep without reading the clock, 60 seconds by default.
+    pub fn with_execute_duration(self, duration: Duration) -> Self {
+        self.shared.lock().execute_duration = duration;
+        self.shared.condvar.notify_one();
+        self
+    }
+    /// get runner client
+    pub fn client(&self) -> BlockingSchedulerClient<R> {
+        BlockingSchedulerClient {
+            shared: self.shared.clone(),
+        }
+    }
+    /// Stop the thread and return the scheduler, the commands left are dropped and their
+    /// clients get [`Stopped`].
+    ///
+    /// # Panics
+    /// if the thread panicked, e.g. in a task of the [`Local`](crate::runtime::Local) runtime
+    pub fn shutdown(mut self) -> Scheduler<R, H> {
+        self.stop();
+        let thread = self.thread.take().expect("the thread is joined once");
+        thread
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    }
+    fn stop(&self) {
+        self.shared.lock().shutdown = true;
+        self.shared.condvar.notify_one();
+    }
+}
+
+/// The thread stops when the runner is dropped, without waiting for it.
+impl<R: Runtime, H> Drop for BlockingSchedulerRunner<R, H> {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Wakes the runner thread.
+struct ThreadWaker<R: Runtime>(Arc<Shared<R>>);
+
+impl<R: Runtime + Send> Wake for ThreadWaker<R> {
+    fn wake(self: Arc<Self>) {
+        self.0.lock().woken = true;
+        self.0.condvar.notify_one();
+    }
+}
+
+/// Stops the runner when its thread exits, even by a panic of a task: the clients waiting for a
+/// reply get [`Stopped`], and the next ones fail right away.
+struct StopOnExit<R: Runtime>(Arc<Shared<R>>);
+
+impl<R: Runtime> Drop for StopOnExit<R> {
+    fn drop(&mut self) {
+        let commands = {
+            let mut state = self.0.lock();
+            state.shutdown = true;
+            std::mem::take(&mut state.commands)
+        };
+        drop(commands);
+    }
+}
+
+fn run<R: Runtime + Send, H: HandleManager<R::Handle>>(
+    mut scheduler: Scheduler<R, H>,
+    shared: Arc<Shared<R>>,
+) -> Scheduler<R, H> {
+    let _stop = StopOnExit(shared.clone());
+    let waker = Waker::from(Arc::new(ThreadWaker(shared.clone())));
+    loop {
+        // the tasks wake the thread when changing the scheduler
+        scheduler.commands.register(&waker);
+        let (commands, execute_duration) = {
+            let mut state = shared.lock();
+            if state.shutdown {
+                return scheduler;
+            }
+            state.woken = false;
+            (std::mem::take(&mut state.commands), state.execute_duration)
+        };
+        for command in commands {
+            // a client which stopped waiting is not an error
+            match command {
+                Command::AddTask(key, task, reply) => {
+                    let _ = reply.send(scheduler.add_task(key, task));
+                }
+                Command::RemoveTask(key, reply) => {
+                    let next_run = scheduler.next_run(key);
+                    let removed = scheduler
+                        .delete_task(key)
+                        .map(|_| RemovedTask { key, next_run });
+                    let _ = reply.send(removed);
+                }
+                Command::NextRun(key, reply) => {
+                    let _ = reply.send(scheduler.next_run(key));
+                }
+                Command::List(reply) => {
+                    let _ = reply.send(scheduler.list());
+                }
+                Command::History(key, reply) => {
+                    let _ = reply.send(scheduler.history(key));
+                }
+                Command::TriggerNow(key, reply) => {
+                    let _ = reply.send(scheduler.trigger_now(key));
+                }
+            }
+        }
+        scheduler.execute_by_now();
+        // sleep until the next task is due
+        let timeout = match scheduler.next_deadline() {
+            Some(deadline) => (deadline - scheduler.now())
+                .to_std()
+                .unwrap_or_default()
+                .min(execute_duration),
+            None => execute_duration,
+        };
+        let state = shared.lock();
+        if state.commands.is_empty() && !state.shutdown && !state.woken && !timeout.is_zero() {
+            let _state = shared
+                .condvar
+                .wait_timeout(state, timeout)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+    }
+}
+
+/// Client for [`BlockingSchedulerRunner`]
+///
+/// created by [`BlockingSchedulerRunner::client`].
+///
+/// # Clone
+/// this client is cheap to clone.
+///
+/// # Blocking
+/// Every method waits for the reply of the runner thread, and fails with [`Stopped`] once the
+/// runner is shut down.
+#[derive(Debug)]
+pub struct BlockingSchedulerClient<R: Runtime> {
+    shared: Arc<Shared<R>>,
+}
+
+impl<R: Runtime> Clone for BlockingSchedulerClient<R> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<R: Runtime> BlockingSchedulerClient<R> {
+    /// add a new task, see [`Scheduler::add_task`]
+    pub fn add_task(&self, key: TaskUid, task: Task<R>) -> Result<AddOutcome, Stopped> {
+        self.request(|reply| Command::AddTask(key, task, reply))
+    }
+    /// remove a task by id, replies none if there was no such task
+    pub fn remove_task(&self, key: TaskUid) -> Result<Option<RemovedTask>, Stopped> {
+        self.request(|reply| Command::RemoveTask(key, reply))
+    }
+    /// the next run of a task, see [`Scheduler::next_run`]
+    pub fn next_run(&self, key: TaskUid) -> Result<Option<Dtu>, Stopped> {
+        self.request(|reply| Command::NextRun(key, reply))
+    }
+    /// the next run of every task, see [`Scheduler::list`]
+    pub fn list(&self) -> Result<Vec<TaskRun>, Stopped> {
+        self.request(Command::List)
+    }
+    /// the recorded runs of a task, see [`Scheduler::history`]
+    pub fn history(&self, key: TaskUid) -> Result<Vec<RunRecord>, Stopped> {
+        self.request(|reply| Command::History(key, reply))
+    }
+    /// run a task now, see [`Scheduler::trigger_now`]
+    pub fn trigger_now(&self, key: TaskUid) -> Result<Option<TaskRun>, Stopped> {
+        self.request(|reply| Command::TriggerNow(key, reply))
+    }
+    /// queue a command, wake the runner thread and wait for the reply
+    fn request<T>(&self, command: impl FnOnce(ReplySender<T>) -> Command<R>) -> Result<T, Stopped> {
+        let (sender, reply) = mpsc::sync_channel(1);
+        {
+            let mut state = self.shared.lock();
+            if state.shutdown {
+                return Err(Stopped);
+            }
+            state.commands.push_back(command(sender));
+        }
+        self.shared.condvar.notify_one();
+        reply.recv().map_err(|_| Stopped)
+    }
+}
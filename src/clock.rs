@@ -0,0 +1,120 @@
+use std::sync::{Arc, Mutex};
+
+use chrono::TimeDelta;
+
+use crate::Dtu;
+
+/// A source of the current time.
+///
+/// [`Scheduler`](crate::Scheduler) reads the time from a clock in
+/// [`execute_by_now`](crate::Scheduler::execute_by_now), and the schedules depending on the
+/// current time can be created against a clock with
+/// [`IntoSchedule::into_schedule_with_clock`](crate::schedule::IntoSchedule::into_schedule_with_clock).
+///
+/// The default one is [`SystemClock`], use a [`ManualClock`] to run against virtual time.
+pub trait Clock: std::fmt::Debug + Send + Sync {
+    /// the current time
+    fn now(&self) -> Dtu;
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn now(&self) -> Dtu {
+        C::now(self)
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> Dtu {
+        C::now(self)
+    }
+}
+
+/// The system clock, [`chrono::Utc::now()`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Dtu {
+        chrono::Utc::now()
+    }
+}
+
+/// A clock that only moves when told to.
+///
+/// The clones share the same time, keep one to drive the time of a scheduler.
+///
+/// # Example
+/// ```
+/// # use tsuki_scheduler::prelude::*;
+/// use chrono::{TimeDelta, TimeZone, Utc};
+/// let start = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+/// let clock = ManualClock::new(start);
+/// let mut scheduler = Scheduler::new(Local::new()).with_clock(clock.clone());
+/// let schedule = TimeDelta::minutes(10).into_schedule_with_clock(&clock);
+/// scheduler.add_task(TaskUid::new(0), Task::local(schedule, || println!("tick")));
+/// // runs at 00:00, 00:10 and 00:20
+/// clock.advance(TimeDelta::minutes(25));
+/// scheduler.execute_by_now();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    now: Arc<Mutex<Dtu>>,
+}
+
+impl ManualClock {
+    pub fn new(start: Dtu) -> Self {
+        Self {
+            now: Arc::new(Mutex::new(start)),
+        }
+    }
+    /// set the current time, it may go backward
+    pub fn set(&self, now: Dtu) {
+        *self.now.lock().expect("lock manual clock failed") = now;
+    }
+    /// move the current time forward by `delta`
+    pub fn advance(&self, delta: TimeDelta) {
+        *self.now.lock().expect("lock manual clock failed") += delta;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Dtu {
+        *self.now.lock().expect("lock manual clock failed")
+    }
+}
+
+/// A clock shifted from another one by a fixed offset.
+///
+/// # Example
+/// ```
+/// # use tsuki_scheduler::prelude::*;
+/// use chrono::TimeDelta;
+/// // pretend to be one hour later
+/// let clock = OffsetClock::new(TimeDelta::hours(1));
+/// assert!(clock.now() > now() + TimeDelta::minutes(59));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct OffsetClock<C = SystemClock> {
+    pub inner: C,
+    pub offset: TimeDelta,
+}
+
+impl OffsetClock {
+    /// shift the [`SystemClock`]
+    pub fn new(offset: TimeDelta) -> Self {
+        Self::with_inner(SystemClock, offset)
+    }
+}
+
+impl<C: Clock> OffsetClock<C> {
+    /// shift another clock
+    pub fn with_inner(inner: C, offset: TimeDelta) -> Self {
+        Self { inner, offset }
+    }
+}
+
+impl<C: Clock> Clock for OffsetClock<C> {
+    fn now(&self) -> Dtu {
+        self.inner.now() + self.offset
+    }
+}
@@ -0,0 +1,326 @@
+use std::{
+    cmp::Reverse,
+    sync::{Arc, Mutex, MutexGuard},
+    task::Waker,
+};
+
+use crate::{
+    Dtu, NextUp, Scheduler, Task, TaskRun, TaskUid,
+    clock::Clock,
+    handle_manager::HandleManager,
+    runtime::{Runtime, TaskRunArg},
+    schedule::{IntoSchedule, Schedule},
+};
+
+/// A change of the scheduler requested by a running task.
+enum Command<R: Runtime> {
+    AddTask(TaskUid, Task<R>),
+    DeleteTask(TaskUid),
+    SetSchedule(TaskUid, Box<dyn Schedule + Send>),
+    /// the schedule has a new next time point, e.g. a [`Feedback`](crate::schedule::Feedback)
+    Refresh(TaskUid),
+    /// a time point was pushed into the schedule, see [`ScheduleWaker`]
+    Wake(TaskUid),
+}
+
+struct Queue<R: Runtime> {
+    commands: Vec<Command<R>>,
+    /// wakes the runner of the scheduler, if any
+    waker: Option<Waker>,
+}
+
+/// The commands of the running tasks, applied by the scheduler after each execution.
+pub(crate) struct CommandQueue<R: Runtime> {
+    queue: Mutex<Queue<R>>,
+}
+
+impl<R: Runtime> Default for CommandQueue<R> {
+    fn default() -> Self {
+        Self {
+            queue: Mutex::new(Queue {
+                commands: Vec::new(),
+                waker: None,
+            }),
+        }
+    }
+}
+
+impl<R: Runtime> std::fmt::Debug for CommandQueue<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CommandQueue").finish_non_exhaustive()
+    }
+}
+
+impl<R: Runtime> CommandQueue<R> {
+    /// the queue, also when poisoned: a task pushes its commands whole, a panicking one can't
+    /// leave a command half written
+    fn lock(&self) -> MutexGuard<'_, Queue<R>> {
+        self.queue
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+    fn push(&self, command: Command<R>) {
+        let waker = {
+            let mut queue = self.lock();
+            queue.commands.push(command);
+            queue.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+    fn take(&self) -> Vec<Command<R>> {
+        std::mem::take(&mut self.lock().commands)
+    }
+    /// wake `waker` at the next command
+    #[cfg(any(feature = "async-scheduler", feature = "blocking-scheduler"))]
+    pub(crate) fn register(&self, waker: &Waker) {
+        let mut queue = self.lock();
+        match &mut queue.waker {
+            Some(registered) => registered.clone_from(waker),
+            None => queue.waker = Some(waker.clone()),
+        }
+    }
+}
+
+/// The queue of a scheduler, for the schedules which don't know its runtime.
+trait WakeQueue: Send + Sync {
+    fn wake(&self, key: TaskUid);
+}
+
+impl<R: Runtime> WakeQueue for CommandQueue<R> {
+    fn wake(&self, key: TaskUid) {
+        self.push(Command::Wake(key));
+    }
+}
+
+/// Tells the scheduler that a time point was pushed into the schedule of a task, given to the
+/// schedule by [`Schedule::register`].
+///
+/// The scheduler takes the pushed time point after its current execution, a runner is woken to
+/// do it. A time point later than the next run is taken after it, as usual.
+#[derive(Clone)]
+pub struct ScheduleWaker {
+    key: TaskUid,
+    queue: Arc<dyn WakeQueue>,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for ScheduleWaker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScheduleWaker")
+            .field("key", &self.key)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ScheduleWaker {
+    /// the uid of the task of the schedule
+    pub fn key(&self) -> TaskUid {
+        self.key
+    }
+    pub fn wake(&self) {
+        self.queue.wake(self.key);
+    }
+    /// the current time, by the clock of the scheduler
+    pub fn now(&self) -> Dtu {
+        self.clock.now()
+    }
+}
+
+/// Change the scheduler from a running task.
+///
+/// The commands are applied after the current execution of the scheduler, or at its next
+/// execution for a task still running. An
+/// [`AsyncSchedulerRunner`](crate::AsyncSchedulerRunner) is woken to apply them.
+///
+/// # Example
+/// ```
+/// # use tsuki_scheduler::prelude::*;
+/// use chrono::TimeDelta;
+/// let mut scheduler = Scheduler::new(Local::new());
+/// let (id, follow_up) = (TaskUid::new(0), TaskUid::new(1));
+/// scheduler.add_task(
+///     id,
+///     Task::new(Period::new(TimeDelta::hours(1), now()), |ctx: SchedulerCtx<Local>| {
+///         ctx.add_task(TaskUid::new(1), Task::local(now(), || println!("follow up")));
+///         ctx.delete_self();
+///     }),
+/// );
+/// scheduler.execute_by_now();
+/// assert!(scheduler.next_run(id).is_none());
+/// assert!(scheduler.next_run(follow_up).is_some());
+/// ```
+///
+/// # Panics
+/// The extraction panics if the run is not dispatched by a [`Scheduler`] of the runtime `R`.
+pub struct SchedulerCtx<R: Runtime> {
+    key: TaskUid,
+    queue: Arc<CommandQueue<R>>,
+}
+
+impl<R: Runtime> Clone for SchedulerCtx<R> {
+    fn clone(&self) -> Self {
+        Self {
+            key: self.key,
+            queue: self.queue.clone(),
+        }
+    }
+}
+
+impl<R: Runtime> std::fmt::Debug for SchedulerCtx<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SchedulerCtx")
+            .field("key", &self.key)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<R: Runtime> SchedulerCtx<R> {
+    /// the uid of the running task
+    pub fn key(&self) -> TaskUid {
+        self.key
+    }
+    /// add a task, see [`Scheduler::add_task`]
+    pub fn add_task(&self, key: TaskUid, task: Task<R>) {
+        self.queue.push(Command::AddTask(key, task));
+    }
+    /// delete a task, see [`Scheduler::delete_task`]
+    pub fn delete_task(&self, key: TaskUid) {
+        self.queue.push(Command::DeleteTask(key));
+    }
+    /// delete the running task
+    pub fn delete_self(&self) {
+        self.delete_task(self.key);
+    }
+    /// replace the schedule of the running task, see [`Scheduler::set_schedule`]
+    pub fn reschedule_self<S: IntoSchedule>(&self, schedule: S) {
+        self.queue.push(Command::SetSchedule(
+            self.key,
+            Box::new(schedule.into_schedule()),
+        ));
+    }
+}
+
+impl<R: Runtime> SchedulerCtx<R> {
+    /// the context of a run dispatched by a scheduler of `R`
+    pub(crate) fn of_run(task_run: &TaskRun) -> Option<Self> {
+        let queue = task_run.context.commands.clone()?.downcast().ok()?;
+        Some(SchedulerCtx {
+            key: task_run.key,
+            queue,
+        })
+    }
+    /// take the next time point of the schedule of the running task again
+    pub(crate) fn refresh_self(&self) {
+        self.queue.push(Command::Refresh(self.key));
+    }
+}
+
+impl<R: Runtime> TaskRunArg for SchedulerCtx<R> {
+    fn extract(task_run: &TaskRun) -> Self {
+        match Self::of_run(task_run) {
+            Some(ctx) => ctx,
+            None => panic!(
+                "the run of task {} was not dispatched by a scheduler of `{}`",
+                task_run.key,
+                std::any::type_name::<R>()
+            ),
+        }
+    }
+}
+
+impl<R: Runtime, H> Scheduler<R, H> {
+    pub(crate) fn schedule_waker(&self, key: TaskUid) -> ScheduleWaker {
+        ScheduleWaker {
+            key,
+            queue: self.commands.clone(),
+            clock: self.clock.clone(),
+        }
+    }
+    /// Replace the schedule of a task, keeping its run function.
+    ///
+    /// Returns the next run, the task is removed if the schedule has no time point left, unless it
+    /// is open, see [`Schedule::is_open`].
+    pub fn set_schedule<S: IntoSchedule>(&mut self, key: TaskUid, schedule: S) -> Option<Dtu> {
+        self.replace_schedule(key, Box::new(schedule.into_schedule()))
+    }
+    fn replace_schedule(
+        &mut self,
+        key: TaskUid,
+        schedule: Box<dyn Schedule + Send>,
+    ) -> Option<Dtu> {
+        let waker = self.schedule_waker(key);
+        let entry = self.task_map.get_mut(&key)?;
+        entry.task.schedule = schedule;
+        entry.task.schedule.register(&waker);
+        entry.deferred.clear();
+        self.refresh(key)
+    }
+    /// replace the next run of a task by the next time point of its schedule
+    fn refresh(&mut self, key: TaskUid) -> Option<Dtu> {
+        let entry = self.task_map.get_mut(&key)?;
+        let next = entry.take_next();
+        self.plan(key, next)
+    }
+    /// take the time point pushed into the schedule of a task, if it comes before the next run
+    fn wake(&mut self, key: TaskUid) {
+        let Some(entry) = self.task_map.get_mut(&key) else {
+            return;
+        };
+        let Some(pushed) = entry.task.schedule.peek_next() else {
+            return;
+        };
+        if entry.next.is_some_and(|next| next <= pushed) {
+            return;
+        }
+        let pushed = entry.task.schedule.next();
+        if let Some(planned) = entry.next {
+            entry.deferred.push(Reverse(planned));
+        }
+        self.plan(key, pushed);
+    }
+    /// set the next run of a task, a task without one is removed unless its schedule is open
+    pub(crate) fn plan(&mut self, key: TaskUid, next: Option<Dtu>) -> Option<Dtu> {
+        let entry = self.task_map.get_mut(&key)?;
+        if next.is_none() && !entry.task.schedule.is_open() {
+            self.task_map.remove(&key);
+            return None;
+        }
+        self.generation += 1;
+        entry.next = next;
+        entry.generation = self.generation;
+        if let Some(next) = next {
+            self.next_up_heap.push(NextUp {
+                run: TaskRun::new(key, next),
+                generation: self.generation,
+            });
+        }
+        next
+    }
+}
+
+impl<R: Runtime, H: HandleManager<R::Handle>> Scheduler<R, H> {
+    /// apply the commands of the running tasks
+    pub(crate) fn apply_commands(&mut self) {
+        for command in self.commands.take() {
+            match command {
+                Command::AddTask(key, task) => {
+                    self.add_task(key, task);
+                }
+                Command::DeleteTask(key) => {
+                    self.delete_task(key);
+                }
+                Command::SetSchedule(key, schedule) => {
+                    self.replace_schedule(key, schedule);
+                }
+                Command::Refresh(key) => {
+                    self.refresh(key);
+                }
+                Command::Wake(key) => {
+                    self.wake(key);
+                }
+            }
+        }
+    }
+}
@@ -1,8 +1,27 @@
-use crate::TaskRun;
+use std::{
+    collections::HashMap,
+    task::{Context, Poll, Waker},
+};
+
+use crate::{Dtu, TaskRun, TaskUid, history::RunOutcome};
+
+#[cfg(feature = "tokio")]
+mod tokio;
+#[cfg(feature = "tokio")]
+pub use tokio::*;
 
 /// A trait for processing handles produced by the runtime
 pub trait HandleManager<H> {
     fn manage(&mut self, task_run: &TaskRun, handle: H);
+    /// Called by [`Scheduler::delete_task`](crate::Scheduler::delete_task), even if the task
+    /// was already removed, e.g. to abort its runs. The default one does nothing.
+    fn on_delete(&mut self, _key: TaskUid) {}
+    /// Report the runs finished since the last call, with the time they were seen finished, for
+    /// the [history](crate::Scheduler::with_history) of the scheduler. It is called before each
+    /// execution, the default one reports nothing.
+    ///
+    /// The time is read by [`TaskRun::now`], from the clock of the scheduler.
+    fn report_finished(&mut self, _finished: &mut dyn FnMut(&TaskRun, RunOutcome, Dtu)) {}
 }
 
 impl<H> HandleManager<H> for Vec<H> {
@@ -15,3 +34,249 @@ impl<H> HandleManager<H> for Vec<H> {
 impl<T> HandleManager<T> for () {
     fn manage(&mut self, _: &TaskRun, _: T) {}
 }
+
+/// A handle of a run which can be polled for completion, see [`TrackHandles`].
+pub trait RunHandle {
+    /// Poll whether the run is finished, the handle is not polled again once finished.
+    fn poll_finished(&mut self, cx: &mut Context<'_>) -> Poll<()>;
+    /// Abort the run, returns `false` if the runtime can't abort it.
+    fn abort(&mut self) -> bool {
+        false
+    }
+}
+
+/// A run without handle, like a [`Local`](crate::runtime::Local) one, is finished.
+impl RunHandle for () {
+    fn poll_finished(&mut self, _: &mut Context<'_>) -> Poll<()> {
+        Poll::Ready(())
+    }
+}
+
+/// A handle manager keeping the handles of the runs in flight.
+///
+/// The finished runs are dropped whenever a new handle is managed. An
+/// [`AsyncSchedulerRunner`](crate::AsyncSchedulerRunner) using it can wait for the runs in
+/// flight when shutting down.
+#[derive(Debug)]
+pub struct TrackHandles<H> {
+    in_flight: Vec<(TaskRun, H)>,
+}
+
+impl<H> Default for TrackHandles<H> {
+    fn default() -> Self {
+        Self {
+            in_flight: Vec::new(),
+        }
+    }
+}
+
+impl<H> TrackHandles<H> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// the number of runs tracked, some may have finished since the last managed handle
+    pub fn len(&self) -> usize {
+        self.in_flight.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.in_flight.is_empty()
+    }
+    /// the runs tracked
+    pub fn runs(&self) -> impl Iterator<Item = &TaskRun> {
+        self.in_flight.iter().map(|(run, _)| run)
+    }
+    /// take all the runs tracked with their handles
+    pub fn take(&mut self) -> Vec<(TaskRun, H)> {
+        std::mem::take(&mut self.in_flight)
+    }
+}
+
+impl<H: RunHandle> HandleManager<H> for TrackHandles<H> {
+    fn manage(&mut self, task_run: &TaskRun, handle: H) {
+        self.in_flight
+            .retain_mut(|(_, handle)| !is_finished(handle));
+        self.in_flight.push((task_run.clone(), handle));
+    }
+}
+
+/// poll a handle without waiting for it
+fn is_finished<H: RunHandle>(handle: &mut H) -> bool {
+    handle
+        .poll_finished(&mut Context::from_waker(Waker::noop()))
+        .is_ready()
+}
+
+/// What [`KeyedHandles`] does with the runs of a deleted task.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum OnDelete {
+    /// keep tracking the runs until they finish, they can still be joined
+    #[default]
+    Keep,
+    /// abort the runs, those the runtime can't abort are kept
+    Abort,
+    /// drop the handles and let the runs go on unobserved
+    Detach,
+}
+
+/// A handle manager indexing the handles of the runs in flight by task.
+///
+/// The finished runs are dropped whenever a new handle is managed, or by
+/// [`prune`](KeyedHandles::prune). When a task is deleted from the scheduler, its runs are
+/// handled according to the [`OnDelete`] policy.
+///
+/// ```
+/// # use tsuki_scheduler::prelude::*;
+/// # #[cfg(not(feature = "tokio"))]
+/// # fn main() {}
+/// # #[cfg(feature = "tokio")]
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let handles = KeyedHandles::new().with_on_delete(OnDelete::Abort);
+/// let mut scheduler = Scheduler::new(Tokio).with_handle_manager(handles);
+/// let id = TaskUid::new(0);
+/// scheduler.add_task(id, Task::new_async([now()], || std::future::pending::<()>()));
+/// scheduler.execute_by_now();
+/// assert_eq!(scheduler.handle_manager.count(id), 1);
+/// // the pending run is aborted
+/// scheduler.delete_task(id);
+/// scheduler.handle_manager.join(id).await;
+/// assert_eq!(scheduler.handle_manager.count(id), 0);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct KeyedHandles<H> {
+    handles: HashMap<TaskUid, Vec<(TaskRun, H)>>,
+    on_delete: OnDelete,
+    /// the runs seen finished, until reported
+    finished: Vec<(TaskRun, RunOutcome, Dtu)>,
+}
+
+impl<H> Default for KeyedHandles<H> {
+    fn default() -> Self {
+        Self {
+            handles: HashMap::new(),
+            on_delete: OnDelete::default(),
+            finished: Vec::new(),
+        }
+    }
+}
+
+impl<H> KeyedHandles<H> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// set what to do with the runs of a deleted task
+    pub fn with_on_delete(mut self, on_delete: OnDelete) -> Self {
+        self.on_delete = on_delete;
+        self
+    }
+    /// the number of runs tracked for a task, some may have finished since the last prune
+    pub fn count(&self, key: TaskUid) -> usize {
+        self.handles.get(&key).map_or(0, Vec::len)
+    }
+    /// the tasks with runs tracked
+    pub fn keys(&self) -> impl Iterator<Item = TaskUid> + '_ {
+        self.handles.keys().copied()
+    }
+    /// the runs tracked for a task
+    pub fn runs(&self, key: TaskUid) -> impl Iterator<Item = &TaskRun> {
+        self.handles
+            .get(&key)
+            .into_iter()
+            .flatten()
+            .map(|(run, _)| run)
+    }
+    /// take the runs tracked for a task with their handles
+    pub fn take(&mut self, key: TaskUid) -> Vec<(TaskRun, H)> {
+        self.handles.remove(&key).unwrap_or_default()
+    }
+}
+
+impl<H: RunHandle> KeyedHandles<H> {
+    /// drop the handles of the finished runs
+    pub fn prune(&mut self) {
+        let finished = &mut self.finished;
+        self.handles.retain(|_, handles| {
+            handles.retain_mut(|(run, handle)| {
+                let done = is_finished(handle);
+                if done {
+                    finished.push((run.clone(), RunOutcome::Finished, run.now()));
+                }
+                !done
+            });
+            !handles.is_empty()
+        });
+    }
+    /// Abort the runs of a task, those the runtime can't abort are kept.
+    pub fn abort(&mut self, key: TaskUid) {
+        if let Some(handles) = self.handles.get_mut(&key) {
+            handles.retain_mut(|(run, handle)| {
+                let aborted = handle.abort();
+                if aborted {
+                    self.finished
+                        .push((run.clone(), RunOutcome::Cancelled, run.now()));
+                }
+                !aborted
+            });
+            if handles.is_empty() {
+                self.handles.remove(&key);
+            }
+        }
+    }
+    /// Wait for the runs of a task to finish.
+    ///
+    /// A [`Thread`](crate::runtime::Thread) run wakes the waiting task when it finishes if it
+    /// was spawned by [`Thread::spawn`](crate::runtime::Thread::spawn), the other ones are
+    /// polled again and again while they run.
+    pub async fn join(&mut self, key: TaskUid) {
+        let Some(mut handles) = self.handles.remove(&key) else {
+            return;
+        };
+        std::future::poll_fn(|cx| {
+            handles.retain_mut(|(run, handle)| {
+                let pending = handle.poll_finished(cx).is_pending();
+                if !pending {
+                    self.finished
+                        .push((run.clone(), RunOutcome::Finished, run.now()));
+                }
+                pending
+            });
+            if handles.is_empty() {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await;
+    }
+}
+
+impl<H: RunHandle> HandleManager<H> for KeyedHandles<H> {
+    fn manage(&mut self, task_run: &TaskRun, mut handle: H) {
+        self.prune();
+        if is_finished(&mut handle) {
+            self.finished
+                .push((task_run.clone(), RunOutcome::Finished, task_run.now()));
+            return;
+        }
+        self.handles
+            .entry(task_run.key)
+            .or_default()
+            .push((task_run.clone(), handle));
+    }
+    fn on_delete(&mut self, key: TaskUid) {
+        match self.on_delete {
+            OnDelete::Keep => {}
+            OnDelete::Abort => self.abort(key),
+            OnDelete::Detach => {
+                self.handles.remove(&key);
+            }
+        }
+    }
+    fn report_finished(&mut self, finished: &mut dyn FnMut(&TaskRun, RunOutcome, Dtu)) {
+        self.prune();
+        for (run, outcome, ended) in self.finished.drain(..) {
+            finished(&run, outcome, ended);
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+
+use tokio::task::{AbortHandle, JoinError, JoinHandle, JoinSet};
+
+use super::HandleManager;
+use crate::{Dtu, TaskRun, TaskUid, history::RunOutcome};
+
+/// The outcomes of the finished runs of a task, see [`JoinSetManager::stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct RunStats {
+    pub succeeded: u64,
+    pub panicked: u64,
+    pub cancelled: u64,
+}
+
+impl RunStats {
+    /// the number of finished runs
+    pub fn finished(&self) -> u64 {
+        self.succeeded + self.panicked + self.cancelled
+    }
+}
+
+/// A handle manager of [`Tokio`](crate::runtime::Tokio) runs built on a [`JoinSet`].
+///
+/// The finished runs are reaped whenever a new run is managed, or by
+/// [`reap`](JoinSetManager::reap), and counted per task. The runs of a task are aborted when
+/// it is deleted from the scheduler, and its stats and panics are dropped.
+///
+/// ```
+/// # use tsuki_scheduler::prelude::*;
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let mut scheduler = Scheduler::new(Tokio).with_handle_manager(JoinSetManager::new());
+/// let id = TaskUid::new(0);
+/// scheduler.add_task(id, Task::new_async([now()], || async { panic!("oops") }));
+/// scheduler.execute_by_now();
+/// tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+/// scheduler.handle_manager.reap();
+/// assert_eq!(scheduler.handle_manager.stats(id).panicked, 1);
+/// let (run, _panic) = scheduler.handle_manager.take_panics().remove(0);
+/// assert_eq!(run.key(), id);
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct JoinSetManager {
+    /// the watching tasks, which yield the run with the time it ended
+    join_set: JoinSet<(TaskRun, u64, Result<(), JoinError>, Dtu)>,
+    running: HashMap<TaskUid, HashMap<u64, AbortHandle>>,
+    stats: HashMap<TaskUid, RunStats>,
+    panics: Vec<(TaskRun, JoinError)>,
+    /// the runs reaped, until reported
+    finished: Vec<(TaskRun, RunOutcome, Dtu)>,
+    next_id: u64,
+}
+
+impl JoinSetManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Count the finished runs and drop their handles.
+    pub fn reap(&mut self) {
+        while let Some(joined) = self.join_set.try_join_next() {
+            // the watching task only fails when the whole set is aborted
+            let Ok((run, id, result, ended)) = joined else {
+                continue;
+            };
+            let Some(running) = self.running.get_mut(&run.key) else {
+                continue;
+            };
+            // a run of a deleted task
+            if running.remove(&id).is_none() {
+                continue;
+            }
+            if running.is_empty() {
+                self.running.remove(&run.key);
+            }
+            let stats = self.stats.entry(run.key).or_default();
+            let outcome = match &result {
+                Ok(()) => {
+                    stats.succeeded += 1;
+                    RunOutcome::Succeeded
+                }
+                Err(error) if error.is_panic() => {
+                    stats.panicked += 1;
+                    RunOutcome::Panicked
+                }
+                Err(_) => {
+                    stats.cancelled += 1;
+                    RunOutcome::Cancelled
+                }
+            };
+            self.finished.push((run.clone(), outcome, ended));
+            if let Err(error) = result
+                && error.is_panic()
+            {
+                self.panics.push((run, error));
+            }
+        }
+    }
+    /// the outcomes of the runs of a task reaped so far
+    pub fn stats(&self, key: TaskUid) -> RunStats {
+        self.stats.get(&key).copied().unwrap_or_default()
+    }
+    /// the number of runs of a task still running
+    pub fn running(&self, key: TaskUid) -> usize {
+        self.running.get(&key).map_or(0, |running| {
+            running
+                .values()
+                .filter(|handle| !handle.is_finished())
+                .count()
+        })
+    }
+    /// Take the panicked runs reaped so far, the payloads are kept until taken.
+    pub fn take_panics(&mut self) -> Vec<(TaskRun, JoinError)> {
+        std::mem::take(&mut self.panics)
+    }
+    /// abort all the runs of a task
+    pub fn abort(&mut self, key: TaskUid) {
+        for handle in self.running.get(&key).into_iter().flat_map(HashMap::values) {
+            handle.abort();
+        }
+    }
+}
+
+impl HandleManager<JoinHandle<()>> for JoinSetManager {
+    fn manage(&mut self, task_run: &TaskRun, handle: JoinHandle<()>) {
+        self.reap();
+        let id = self.next_id;
+        self.next_id += 1;
+        self.running
+            .entry(task_run.key)
+            .or_default()
+            .insert(id, handle.abort_handle());
+        let run = task_run.clone();
+        self.join_set.spawn(async move {
+            let result = handle.await;
+            let ended = run.now();
+            (run, id, result, ended)
+        });
+    }
+    fn on_delete(&mut self, key: TaskUid) {
+        self.abort(key);
+        self.running.remove(&key);
+        self.stats.remove(&key);
+        self.panics.retain(|(run, _)| run.key != key);
+    }
+    fn report_finished(&mut self, finished: &mut dyn FnMut(&TaskRun, RunOutcome, Dtu)) {
+        self.reap();
+        for (run, outcome, ended) in self.finished.drain(..) {
+            finished(&run, outcome, ended);
+        }
+    }
+}
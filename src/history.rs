@@ -0,0 +1,146 @@
+use std::collections::{HashMap, VecDeque};
+
+use chrono::TimeDelta;
+
+use crate::{Dtu, Scheduler, TaskRun, TaskUid, handle_manager::HandleManager, runtime::Runtime};
+
+/// How a run ended, as reported by the handle manager.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RunOutcome {
+    /// not reported finished yet
+    Running,
+    /// finished, the handle manager can't tell how
+    Finished,
+    Succeeded,
+    Panicked,
+    Cancelled,
+}
+
+/// A run in the history of a task, see [`Scheduler::with_history`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunRecord {
+    /// the task and the time it was scheduled at
+    pub run: TaskRun,
+    /// when the run was started, by the clock of the scheduler
+    pub started: Dtu,
+    /// when the handle manager saw the run finished, by the clock of the scheduler
+    pub ended: Option<Dtu>,
+    pub outcome: RunOutcome,
+}
+
+impl RunRecord {
+    /// how long the run took, as far as the scheduler could see
+    pub fn duration(&self) -> Option<TimeDelta> {
+        self.ended.map(|ended| ended - self.started)
+    }
+    /// how late the run was started
+    pub fn lateness(&self) -> TimeDelta {
+        self.started - self.run.time
+    }
+}
+
+/// The last runs of each task, in ring buffers of the same capacity.
+#[derive(Debug, Clone)]
+pub(crate) struct History {
+    capacity: usize,
+    records: HashMap<TaskUid, VecDeque<RunRecord>>,
+}
+
+impl History {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            records: HashMap::new(),
+        }
+    }
+    pub(crate) fn start(&mut self, run: &TaskRun, started: Dtu) {
+        if self.capacity == 0 {
+            return;
+        }
+        let records = self.records.entry(run.key).or_default();
+        if records.len() == self.capacity {
+            records.pop_front();
+        }
+        records.push_back(RunRecord {
+            run: run.clone(),
+            started,
+            ended: None,
+            outcome: RunOutcome::Running,
+        });
+    }
+    pub(crate) fn finish(&mut self, run: &TaskRun, ended: Dtu, outcome: RunOutcome) {
+        let record = self.records.get_mut(&run.key).and_then(|records| {
+            records
+                .iter_mut()
+                .rev()
+                .find(|record| record.run.time == run.time && record.ended.is_none())
+        });
+        if let Some(record) = record {
+            record.ended = Some(ended);
+            record.outcome = outcome;
+        }
+    }
+    pub(crate) fn remove(&mut self, key: TaskUid) {
+        self.records.remove(&key);
+    }
+    pub(crate) fn of(&self, key: TaskUid) -> Vec<RunRecord> {
+        self.records
+            .get(&key)
+            .map(|records| records.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+impl<R: Runtime, H> Scheduler<R, H> {
+    /// Keep the last `capacity` runs of each task, see [`history`](Scheduler::history).
+    ///
+    /// The start of a run is recorded when it is executed, its end and outcome when the handle
+    /// manager reports it finished, see [`HandleManager::report_finished`]. The history of a
+    /// task is removed by [`delete_task`](Scheduler::delete_task), it is kept when its schedule
+    /// ends.
+    ///
+    /// # Example
+    /// ```
+    /// # use tsuki_scheduler::prelude::*;
+    /// let mut scheduler = Scheduler::new(Local::new())
+    ///     .with_handle_manager(KeyedHandles::new())
+    ///     .with_history(10);
+    /// let id = TaskUid::new(0);
+    /// scheduler.add_task(id, Task::local([now(), now()], || {}));
+    /// scheduler.execute_by_now();
+    /// // the finished runs are reported at the next execution
+    /// scheduler.reap();
+    /// let history = scheduler.history(id);
+    /// assert_eq!(history.len(), 2);
+    /// assert_eq!(history[0].outcome, RunOutcome::Finished);
+    /// ```
+    pub fn with_history(mut self, capacity: usize) -> Self {
+        self.history = Some(History::new(capacity));
+        self
+    }
+    /// the recorded runs of a task, oldest first
+    pub fn history(&self, key: TaskUid) -> Vec<RunRecord> {
+        self.history
+            .as_ref()
+            .map(|history| history.of(key))
+            .unwrap_or_default()
+    }
+    /// the last recorded run of a task
+    pub fn last_run(&self, key: TaskUid) -> Option<RunRecord> {
+        self.history.as_ref()?.records.get(&key)?.back().cloned()
+    }
+}
+
+impl<R: Runtime, H: HandleManager<R::Handle>> Scheduler<R, H> {
+    /// Record the runs the handle manager reports finished, it is done at each execution.
+    pub fn reap(&mut self) {
+        let history = &mut self.history;
+        self.handle_manager.report_finished(
+            &mut |run: &TaskRun, outcome: RunOutcome, ended: Dtu| {
+                if let Some(history) = history {
+                    history.finish(run, ended, outcome);
+                }
+            },
+        );
+    }
+}
@@ -0,0 +1,642 @@
+//! Turn the events of an iCalendar document (RFC 5545) into schedules, tasks and time sets, and
+//! write schedules and time sets back as a document with [`Export`].
+//!
+//! # Import
+//! Each `VEVENT` gives:
+//! - a recurrence set ([`RRule`]) from its `DTSTART`, `RRULE`, `RDATE` and `EXDATE`, to run a task
+//!   at the start of every occurrence,
+//! - a time set of its occurrences' `DTSTART..DTEND` windows, e.g. to keep tasks out of
+//!   maintenance windows with [`Filtered`](crate::schedule::Filtered),
+//! - a stable [`TaskUid`] derived from its `UID`.
+//!
+//! # Example
+//! ```
+//! # use tsuki_scheduler::prelude::*;
+//! use tsuki_scheduler::ical::Calendar;
+//! let calendar: Calendar = "\
+//! BEGIN:VCALENDAR
+//! BEGIN:VEVENT
+//! UID:db-maintenance@example.com
+//! SUMMARY:Database maintenance
+//! DTSTART;TZID=Europe/Paris:20250105T020000
+//! DTEND;TZID=Europe/Paris:20250105T040000
+//! RRULE:FREQ=WEEKLY;BYDAY=SU
+//! END:VEVENT
+//! END:VCALENDAR"
+//!     .parse()
+//!     .unwrap();
+//! let event = &calendar.events()[0];
+//! assert_eq!(event.summary(), Some("Database maintenance"));
+//! // run a task at the start of every occurrence
+//! let task = event.task::<Local, _, _>(|| println!("maintenance started"));
+//! // or keep another schedule out of the windows
+//! let outside = Period::new(chrono::TimeDelta::minutes(10), now())
+//!     .filtered_in(timeset::Universal.difference(calendar.windows()));
+//! ```
+//!
+//! # Export
+//! ```
+//! # use tsuki_scheduler::prelude::*;
+//! use chrono::{TimeDelta, Utc};
+//! use tsuki_scheduler::ical::Export;
+//! let start = now() + TimeDelta::minutes(1);
+//! let mut export = Export::new();
+//! // an exact `RRULE:FREQ=HOURLY;INTERVAL=2`
+//! export.push_schedule("backup", "Backup", Period::new(TimeDelta::hours(2), start), 10);
+//! // any other schedule is written as its next 10 time points
+//! let throttled = Period::new(TimeDelta::minutes(20), start).throttling(TimeDelta::hours(1));
+//! export.push_schedule("report", "Report", throttled, 10);
+//! // the intervals of a time set, as busy blocks
+//! let freeze = timeset::Range::between(start, start + TimeDelta::days(2)).unwrap();
+//! export
+//!     .push_busy("freeze", "Release freeze", &freeze, start, start + TimeDelta::days(7))
+//!     .unwrap();
+//! let ics = export.to_string();
+//! assert!(ics.contains("RRULE:FREQ=HOURLY;INTERVAL=2"));
+//! ```
+//!
+//! # Limitations
+//! - `TZID` must be an IANA time zone name, `VTIMEZONE` components are ignored.
+//! - An overridden occurrence (an event with a `RECURRENCE-ID`) is moved to its new `DTSTART`,
+//!   the other overridden properties (e.g. a different duration) are ignored.
+//! - Only `VEVENT` is read, `VTODO`, `VJOURNAL` and `VALARM` are skipped.
+use std::{fmt, path::Path, str::FromStr};
+
+use chrono::{DateTime, TimeDelta};
+use chrono_tz::Tz;
+
+use crate::{
+    Dtu, Task, TaskUid,
+    runtime::{IntoRunTaskFn, Runtime},
+    schedule::{
+        IntoSchedule, RRule, RRuleError, Schedule, Until,
+        rrule::{DateValue, Params, invalid, parse_dates, parse_dtstart, split_line, unfold},
+    },
+    timeset::{Interval, LowerBound, Range, TimeSet, TimeSetExt, Union, succ_dtu},
+};
+
+#[derive(Debug)]
+pub enum IcalError {
+    Io(std::io::Error),
+    RRule(RRuleError),
+    /// a `VEVENT` without a required property (`UID` or `DTSTART`)
+    MissingProperty(&'static str),
+    /// a `BEGIN` without its `END`, or the other way round
+    Unbalanced(String),
+    /// a time set that can't list its intervals, see [`TimeSet::intervals`]
+    NoIntervals,
+}
+
+impl fmt::Display for IcalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to read calendar: {e}"),
+            Self::RRule(e) => write!(f, "{e}"),
+            Self::MissingProperty(name) => write!(f, "VEVENT is missing {name}"),
+            Self::Unbalanced(component) => write!(f, "unbalanced component {component}"),
+            Self::NoIntervals => write!(f, "the time set can't list its intervals"),
+        }
+    }
+}
+
+impl std::error::Error for IcalError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::RRule(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for IcalError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<RRuleError> for IcalError {
+    fn from(e: RRuleError) -> Self {
+        Self::RRule(e)
+    }
+}
+
+/// A `VEVENT`.
+#[derive(Debug, Clone)]
+pub struct Event {
+    uid: String,
+    summary: Option<String>,
+    recurrence: RRule,
+    duration: Option<TimeDelta>,
+}
+
+impl Event {
+    /// the `UID` property
+    pub fn uid(&self) -> &str {
+        &self.uid
+    }
+    /// the `SUMMARY` property, unescaped
+    pub fn summary(&self) -> Option<&str> {
+        self.summary.as_deref()
+    }
+    /// the recurrence set, overridden occurrences included
+    pub fn recurrence(&self) -> &RRule {
+        &self.recurrence
+    }
+    /// The length of every occurrence, from `DTEND` or `DURATION`.
+    ///
+    /// An all-day event without either lasts one day, other events without either are
+    /// instants and have no duration.
+    pub fn duration(&self) -> Option<TimeDelta> {
+        self.duration
+    }
+    /// A task uid derived from the event's `UID`, stable across imports.
+    pub fn task_uid(&self) -> TaskUid {
+        TaskUid::from_name(&self.uid)
+    }
+    /// A schedule at the start of every occurrence.
+    pub fn schedule(&self) -> RRule {
+        self.recurrence.clone()
+    }
+    /// The window `[DTSTART, DTEND)` of the first occurrence.
+    pub fn window(&self) -> Option<Range> {
+        let start = self.recurrence.dtstart().to_utc();
+        Range::between(start, start.checked_add_signed(self.duration?)?).ok()
+    }
+    /// The windows `[start, start + duration)` of every occurrence.
+    pub fn windows(&self) -> Windows {
+        Windows {
+            starts: self.recurrence.clone(),
+            duration: self.duration.unwrap_or_default(),
+        }
+    }
+    /// A task running at the start of every occurrence, see [`Task::new`].
+    pub fn task<R, F, A>(&self, run: F) -> Task<R>
+    where
+        R: Runtime,
+        F: IntoRunTaskFn<R, A>,
+    {
+        Task::new(self.schedule(), run)
+    }
+}
+
+/// The windows of the occurrences of an [`Event`], see [`Event::windows`].
+#[derive(Debug, Clone)]
+pub struct Windows {
+    starts: RRule,
+    duration: TimeDelta,
+}
+
+impl TimeSet for Windows {
+    fn contains(&self, dtu: Dtu) -> bool {
+        if self.duration <= TimeDelta::zero() {
+            return false;
+        }
+        // an occurrence started in `(dtu - duration, dtu]`
+        let Some(earliest) = dtu.checked_sub_signed(self.duration).and_then(succ_dtu) else {
+            return self.starts.lower_bound(dtu) != LowerBound::Never;
+        };
+        self.starts
+            .lower_bound(earliest)
+            .at()
+            .is_some_and(|start| start <= dtu)
+    }
+    fn lower_bound(&self, from: Dtu) -> LowerBound {
+        if self.duration <= TimeDelta::zero() {
+            LowerBound::Never
+        } else if self.contains(from) {
+            LowerBound::At(from)
+        } else {
+            self.starts.lower_bound(from)
+        }
+    }
+    fn intervals(&self, from: Dtu, to: Dtu) -> Option<Vec<Interval>> {
+        let mut intervals = vec![];
+        if self.duration <= TimeDelta::zero() {
+            return Some(intervals);
+        }
+        // the occurrences started in `(from - duration, to)`
+        let mut cursor = from
+            .checked_sub_signed(self.duration)
+            .and_then(succ_dtu)
+            .unwrap_or(from);
+        while let LowerBound::At(start) = self.starts.lower_bound(cursor)
+            && start < to
+        {
+            let end = start
+                .checked_add_signed(self.duration)
+                .unwrap_or(Dtu::MAX_UTC);
+            intervals.push(start.max(from)..end.min(to));
+            cursor = succ_dtu(start)?;
+        }
+        Some(crate::timeset::normalize_intervals(intervals))
+    }
+}
+
+/// The events of an iCalendar document.
+#[derive(Debug, Clone, Default)]
+pub struct Calendar {
+    events: Vec<Event>,
+}
+
+impl Calendar {
+    /// Read and parse a `.ics` file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, IcalError> {
+        std::fs::read_to_string(path)?.parse()
+    }
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+    pub fn into_events(self) -> Vec<Event> {
+        self.events
+    }
+    /// One task per event, keyed by [`Event::task_uid`], all running `run`.
+    pub fn tasks<R, F, A>(&self, run: F) -> Vec<(TaskUid, Task<R>)>
+    where
+        R: Runtime,
+        F: IntoRunTaskFn<R, A> + Clone,
+    {
+        self.events
+            .iter()
+            .map(|event| (event.task_uid(), event.task(run.clone())))
+            .collect()
+    }
+    /// The windows of every event.
+    pub fn windows(&self) -> Union {
+        Union::new(
+            self.events
+                .iter()
+                .map(|event| event.windows().dyn_box())
+                .collect(),
+        )
+    }
+}
+
+/// The properties of a `VEVENT` as they are read.
+#[derive(Default)]
+struct RawEvent {
+    uid: Option<String>,
+    summary: Option<String>,
+    dtstart: Option<(DateTime<Tz>, bool)>,
+    duration: Option<TimeDelta>,
+    rules: Vec<String>,
+    /// the date properties, resolved once `DTSTART` is known
+    dates: Vec<(String, Params, String)>,
+}
+
+impl RawEvent {
+    fn read(&mut self, line: &str) -> Result<(), IcalError> {
+        let (name, params, value) = split_line(line)?;
+        match name.as_str() {
+            "UID" => self.uid = Some(unescape(value)),
+            "SUMMARY" => self.summary = Some(unescape(value)),
+            "DTSTART" => {
+                let is_date = matches!(DateValue::parse(value), Some(DateValue::Date(_)));
+                self.dtstart = Some((parse_dtstart(&params, value)?, is_date));
+            }
+            "DURATION" => {
+                self.duration = Some(parse_duration(value).ok_or_else(|| invalid(&name, value))?)
+            }
+            "RRULE" => self.rules.push(value.to_string()),
+            "DTEND" | "RDATE" | "EXDATE" | "RECURRENCE-ID" => {
+                self.dates.push((name, params, value.to_string()))
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn build(self) -> Result<(Event, Option<Dtu>), IcalError> {
+        let uid = self.uid.ok_or(IcalError::MissingProperty("UID"))?;
+        let (dtstart, is_date) = self.dtstart.ok_or(IcalError::MissingProperty("DTSTART"))?;
+        let tz = dtstart.timezone();
+        let mut recurrence = RRule::from_dtstart(dtstart);
+        for rule in self.rules {
+            recurrence = recurrence.with_rule(rule.parse()?);
+        }
+        let mut dtend = None;
+        let mut recurrence_id = None;
+        for (name, params, value) in self.dates {
+            let mut dates = parse_dates(&name, &params, &value, &tz)?;
+            match name.as_str() {
+                "RDATE" => recurrence = recurrence.with_rdates(dates),
+                "EXDATE" => recurrence = recurrence.with_exdates(dates),
+                "DTEND" => dtend = dates.pop(),
+                _ => recurrence_id = dates.pop(),
+            }
+        }
+        let duration = match (dtend, self.duration) {
+            (Some(dtend), _) => Some(dtend - dtstart.to_utc()),
+            (None, Some(duration)) => Some(duration),
+            (None, None) if is_date => Some(TimeDelta::days(1)),
+            (None, None) => None,
+        };
+        let event = Event {
+            uid,
+            summary: self.summary,
+            recurrence,
+            duration: duration.filter(|duration| *duration > TimeDelta::zero()),
+        };
+        Ok((event, recurrence_id))
+    }
+}
+
+/// Unescape a `TEXT` value.
+fn unescape(value: &str) -> String {
+    let mut text = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(char) = chars.next() {
+        if char != '\\' {
+            text.push(char);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => text.push('\n'),
+            Some(escaped) => text.push(escaped),
+            None => text.push('\\'),
+        }
+    }
+    text
+}
+
+/// Parse a `DURATION` value, e.g. `PT1H30M`, `P1W` or `-P1D`.
+pub(crate) fn parse_duration(value: &str) -> Option<TimeDelta> {
+    let value = value.trim();
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let mut total = TimeDelta::zero();
+    let mut number = String::new();
+    let mut in_time = false;
+    let mut empty = true;
+    for char in value.strip_prefix('P')?.chars() {
+        if char.is_ascii_digit() {
+            number.push(char);
+            continue;
+        }
+        if char == 'T' && !in_time && number.is_empty() {
+            in_time = true;
+            continue;
+        }
+        let count: i64 = number.parse().ok()?;
+        number.clear();
+        empty = false;
+        total += match (in_time, char) {
+            (false, 'W') => TimeDelta::try_weeks(count)?,
+            (false, 'D') => TimeDelta::try_days(count)?,
+            (true, 'H') => TimeDelta::try_hours(count)?,
+            (true, 'M') => TimeDelta::try_minutes(count)?,
+            (true, 'S') => TimeDelta::try_seconds(count)?,
+            _ => return None,
+        };
+    }
+    if empty || !number.is_empty() {
+        return None;
+    }
+    Some(if negative { -total } else { total })
+}
+
+impl FromStr for Calendar {
+    type Err = IcalError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut stack: Vec<String> = vec![];
+        let mut current: Option<RawEvent> = None;
+        let mut events = vec![];
+        for line in unfold(s) {
+            let (name, _, value) = split_line(&line)?;
+            let component = value.trim().to_ascii_uppercase();
+            match name.as_str() {
+                "BEGIN" => {
+                    if component == "VEVENT" && current.is_none() {
+                        current = Some(RawEvent::default());
+                    }
+                    stack.push(component);
+                }
+                "END" => {
+                    if stack.pop().as_ref() != Some(&component) {
+                        return Err(IcalError::Unbalanced(component));
+                    }
+                    if component == "VEVENT"
+                        && !stack.iter().any(|open| open == "VEVENT")
+                        && let Some(event) = current.take()
+                    {
+                        events.push(event.build()?);
+                    }
+                }
+                // properties of the event itself, not of a nested VALARM
+                _ if stack.last().is_some_and(|open| open == "VEVENT") => {
+                    if let Some(event) = current.as_mut() {
+                        event.read(&line)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+        if let Some(open) = stack.pop() {
+            return Err(IcalError::Unbalanced(open));
+        }
+        // move the overridden occurrences of the recurring events
+        let (overrides, masters): (Vec<_>, Vec<_>) =
+            events.into_iter().partition(|(_, id)| id.is_some());
+        let mut events: Vec<Event> = masters.into_iter().map(|(event, _)| event).collect();
+        for (event, recurrence_id) in overrides {
+            match events.iter_mut().find(|master| master.uid == event.uid) {
+                Some(master) => {
+                    master.recurrence = master
+                        .recurrence
+                        .clone()
+                        .with_exdates(recurrence_id)
+                        .with_rdates([event.recurrence.dtstart().to_utc()]);
+                }
+                None => events.push(event),
+            }
+        }
+        Ok(Self { events })
+    }
+}
+
+/// Write schedules and time sets as an iCalendar document, e.g. to subscribe to what a scheduler
+/// will do from a calendar app.
+///
+/// Each pushed item becomes one or several `VEVENT`s, the document is written by
+/// [`Display`](fmt::Display).
+#[derive(Debug, Clone)]
+pub struct Export {
+    prodid: String,
+    dtstamp: Dtu,
+    events: Vec<Vec<String>>,
+}
+
+impl Default for Export {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Export {
+    pub fn new() -> Self {
+        Self {
+            prodid: format!(
+                "-//{}//{}//EN",
+                env!("CARGO_PKG_NAME"),
+                env!("CARGO_PKG_VERSION")
+            ),
+            dtstamp: crate::now(),
+            events: vec![],
+        }
+    }
+    /// set the `PRODID` of the document
+    pub fn with_prodid(mut self, prodid: impl Into<String>) -> Self {
+        self.prodid = prodid.into();
+        self
+    }
+    /// set the `DTSTAMP` of the events, the creation time of the export by default
+    pub fn with_dtstamp(mut self, dtstamp: Dtu) -> Self {
+        self.dtstamp = dtstamp;
+        self
+    }
+    /// Push a schedule as one event.
+    ///
+    /// If the schedule can be described by a recurrence rule (see [`Schedule::to_rrule`]), the
+    /// rule is written as is. Otherwise its next `limit` time points are written as `RDATE`s.
+    pub fn push_schedule<S: IntoSchedule>(
+        &mut self,
+        uid: &str,
+        summary: &str,
+        schedule: S,
+        limit: usize,
+    ) -> &mut Self {
+        let mut schedule = schedule.into_schedule();
+        if let Some(rrule) = schedule.to_rrule() {
+            return self.push_rrule(uid, summary, &rrule);
+        }
+        let mut times = (0..limit).map_while(|_| schedule.next());
+        let Some(first) = times.next() else {
+            return self;
+        };
+        let rrule = RRule::from_dtstart(first.with_timezone(&Tz::UTC)).with_rdates(times);
+        self.push_rrule(uid, summary, &rrule)
+    }
+    /// Push a recurrence set as one event.
+    pub fn push_rrule(&mut self, uid: &str, summary: &str, rrule: &RRule) -> &mut Self {
+        self.push_lines(uid, summary, rrule.to_string().lines().map(str::to_string))
+    }
+    /// Push an imported event back, with its duration.
+    pub fn push_event(&mut self, event: &Event) -> &mut Self {
+        let mut lines: Vec<String> = event
+            .recurrence
+            .to_string()
+            .lines()
+            .map(str::to_string)
+            .collect();
+        if let Some(duration) = event.duration {
+            lines.push(format!("DURATION:{}", format_duration(duration)));
+        }
+        self.push_lines(&event.uid, event.summary().unwrap_or_default(), lines)
+    }
+    /// Push the intervals of a time set within `[from, to)` as busy events, one per interval.
+    ///
+    /// Fails with [`IcalError::NoIntervals`] if the time set can't list its intervals, e.g. a
+    /// set of time points.
+    pub fn push_busy<T: TimeSet>(
+        &mut self,
+        uid: &str,
+        summary: &str,
+        set: &T,
+        from: Dtu,
+        to: Dtu,
+    ) -> Result<&mut Self, IcalError> {
+        let intervals = set.intervals(from, to).ok_or(IcalError::NoIntervals)?;
+        for interval in intervals {
+            let start = Until::Utc(interval.start);
+            let lines = [
+                format!("DTSTART:{start}"),
+                format!("DTEND:{}", Until::Utc(interval.end)),
+                "TRANSP:OPAQUE".to_string(),
+            ];
+            self.push_lines(&format!("{uid}-{start}"), summary, lines);
+        }
+        Ok(self)
+    }
+    fn push_lines(
+        &mut self,
+        uid: &str,
+        summary: &str,
+        lines: impl IntoIterator<Item = String>,
+    ) -> &mut Self {
+        let mut event = vec![
+            format!("UID:{}", escape(uid)),
+            format!("DTSTAMP:{}", Until::Utc(self.dtstamp)),
+        ];
+        if !summary.is_empty() {
+            event.push(format!("SUMMARY:{}", escape(summary)));
+        }
+        event.extend(lines);
+        self.events.push(event);
+        self
+    }
+}
+
+/// Write the document with `CRLF` line endings, lines longer than 75 octets are folded.
+impl fmt::Display for Export {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_folded(f, "BEGIN:VCALENDAR")?;
+        write_folded(f, "VERSION:2.0")?;
+        write_folded(f, &format!("PRODID:{}", self.prodid))?;
+        for event in &self.events {
+            write_folded(f, "BEGIN:VEVENT")?;
+            for line in event {
+                write_folded(f, line)?;
+            }
+            write_folded(f, "END:VEVENT")?;
+        }
+        write_folded(f, "END:VCALENDAR")
+    }
+}
+
+fn write_folded(f: &mut fmt::Formatter<'_>, line: &str) -> fmt::Result {
+    const MAX_OCTETS: usize = 75;
+    let mut rest = line;
+    let mut limit = MAX_OCTETS;
+    while rest.len() > limit {
+        let mut split = limit;
+        while !rest.is_char_boundary(split) {
+            split -= 1;
+        }
+        f.write_str(&rest[..split])?;
+        f.write_str("\r\n ")?;
+        rest = &rest[split..];
+        // the leading space counts
+        limit = MAX_OCTETS - 1;
+    }
+    f.write_str(rest)?;
+    f.write_str("\r\n")
+}
+
+/// Escape a `TEXT` value.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for char in text.chars() {
+        match char {
+            '\\' | ';' | ',' => {
+                escaped.push('\\');
+                escaped.push(char);
+            }
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            char => escaped.push(char),
+        }
+    }
+    escaped
+}
+
+/// Write a non-negative `DURATION` value.
+fn format_duration(duration: TimeDelta) -> String {
+    let seconds = duration.num_seconds();
+    if seconds % (24 * 3600) == 0 {
+        format!("P{}D", seconds / (24 * 3600))
+    } else {
+        format!("PT{seconds}S")
+    }
+}
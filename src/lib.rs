@@ -39,24 +39,49 @@
 mod async_scheduler;
 #[cfg(feature = "async-scheduler")]
 pub use async_scheduler::*;
+#[cfg(feature = "blocking-scheduler")]
+mod blocking_scheduler;
+#[cfg(feature = "blocking-scheduler")]
+pub use blocking_scheduler::*;
 /// prelude for tsuki_scheduler
 pub mod prelude;
 use std::{
+    any::Any,
+    cmp::Reverse,
     collections::{BinaryHeap, HashMap},
     hash::Hash,
+    sync::Arc,
 };
 
+use chrono::TimeDelta;
+use clock::{Clock, SystemClock};
+use ctx::CommandQueue;
 use handle_manager::HandleManager;
+use history::History;
 use runtime::Runtime;
 use schedule::Schedule;
+use state::StateMap;
 /// alias for [`chrono::DateTime`] in [`chrono::Utc`] timezone
 pub type Dtu = chrono::DateTime<chrono::Utc>;
+/// Clocks telling the current time
+pub mod clock;
+/// Change the scheduler from the running tasks
+pub mod ctx;
 /// Process the handlers of the tasks
 pub mod handle_manager;
+/// The last runs of each task
+pub mod history;
+/// Import and export iCalendar (`.ics`) documents
+#[cfg(feature = "ical")]
+pub mod ical;
 /// Runtime to run the tasks
 pub mod runtime;
 /// Schedules and combinators
 pub mod schedule;
+/// Replay the schedules over virtual time
+pub mod simulation;
+/// Shared state for the task functions
+pub mod state;
 /// Time set for schedule filters
 pub mod timeset;
 
@@ -77,6 +102,17 @@ impl TaskUid {
     pub fn new(inner: u128) -> Self {
         Self(inner)
     }
+    /// Derive a task uid from a name, e.g. the `UID` of a calendar event.
+    ///
+    /// The same name always gives the same uid, across runs and platforms (128-bit FNV-1a).
+    pub fn from_name(name: impl AsRef<[u8]>) -> Self {
+        const OFFSET_BASIS: u128 = 0x6c62272e07bb014262b821756295c58d;
+        const PRIME: u128 = 0x0000000001000000000000000000013b;
+        let hash = name.as_ref().iter().fold(OFFSET_BASIS, |hash, byte| {
+            (hash ^ *byte as u128).wrapping_mul(PRIME)
+        });
+        Self(hash)
+    }
 }
 
 impl std::fmt::Display for TaskUid {
@@ -128,11 +164,20 @@ impl<R: Runtime> std::fmt::Debug for Task<R> {
 /// The default one is `()`, which does nothing.
 ///
 /// And you can implement your own handle manager to manage the handles, see [HandleManager](`crate::handle_manager::HandleManager`).
+///
+/// # Clock
+/// The current time of [`execute_by_now`](Scheduler::execute_by_now) is read from a
+/// [`Clock`], the default one is the [`SystemClock`], see [`Scheduler::with_clock`].
 #[derive(Debug)]
 pub struct Scheduler<R: Runtime, H = ()> {
-    pub(crate) next_up_heap: BinaryHeap<TaskRun>,
-    pub(crate) task_map: HashMap<TaskUid, Task<R>>,
+    pub(crate) next_up_heap: BinaryHeap<NextUp>,
+    pub(crate) task_map: HashMap<TaskUid, TaskEntry<R>>,
+    pub(crate) generation: u64,
     pub(crate) runtime: R,
+    pub(crate) clock: Arc<dyn Clock>,
+    pub(crate) history: Option<History>,
+    pub(crate) state: Arc<StateMap>,
+    pub(crate) commands: Arc<CommandQueue<R>>,
     pub handle_manager: H,
 }
 
@@ -145,16 +190,127 @@ where
         Self {
             next_up_heap: BinaryHeap::new(),
             task_map: HashMap::new(),
+            generation: 0,
             runtime: R::default(),
+            clock: Arc::new(SystemClock),
+            history: None,
+            state: Arc::default(),
+            commands: Arc::default(),
             handle_manager: H::default(),
         }
     }
 }
+
+/// A task in the scheduler, with its next run.
+#[derive(Debug)]
+pub(crate) struct TaskEntry<R: Runtime> {
+    pub(crate) task: Task<R>,
+    /// none while an open schedule waits for a time point, see [`Schedule::is_open`]
+    pub(crate) next: Option<Dtu>,
+    /// the time points taken from the schedule, put aside for an earlier pushed one
+    pub(crate) deferred: BinaryHeap<Reverse<Dtu>>,
+    /// tells the heap entries of this task from the ones of a deleted or replaced task
+    pub(crate) generation: u64,
+    /// the number of runs dispatched
+    pub(crate) count: u64,
+    /// the scheduled time of the last run dispatched
+    pub(crate) previous: Option<Dtu>,
+}
+
+impl<R: Runtime> TaskEntry<R> {
+    /// take the time point after the next run
+    pub(crate) fn take_next(&mut self) -> Option<Dtu> {
+        if let Some(&Reverse(deferred)) = self.deferred.peek()
+            && self
+                .task
+                .schedule
+                .peek_next()
+                .is_none_or(|next| deferred <= next)
+        {
+            self.deferred.pop();
+            return Some(deferred);
+        }
+        self.task.schedule.next()
+    }
+    /// the time point after the next run
+    pub(crate) fn peek_next(&mut self) -> Option<Dtu> {
+        let deferred = self.deferred.peek().map(|&Reverse(deferred)| deferred);
+        match (deferred, self.task.schedule.peek_next()) {
+            (Some(deferred), Some(next)) => Some(deferred.min(next)),
+            (deferred, next) => deferred.or(next),
+        }
+    }
+}
+
+/// An entry of the heap, stale if its task has been deleted or replaced since.
+#[derive(Debug)]
+pub(crate) struct NextUp {
+    pub(crate) run: TaskRun,
+    pub(crate) generation: u64,
+}
+
+impl PartialEq for NextUp {
+    fn eq(&self, other: &Self) -> bool {
+        self.run.eq(&other.run)
+    }
+}
+
+impl Eq for NextUp {}
+
+impl PartialOrd for NextUp {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for NextUp {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.run.cmp(&other.run)
+    }
+}
+
+/// The result of [`Scheduler::add_task`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AddOutcome {
+    /// the task is added, and will run next at `next_run`
+    Added { next_run: Dtu },
+    /// the task replaced another one with the same uid, and will run next at `next_run`
+    Replaced { next_run: Dtu },
+    /// the task is added, or replaced another one, and its open schedule waits for a time point,
+    /// see [`Schedule::is_open`]
+    Waiting { replaced: bool },
+    /// the schedule has no time point left, nothing changed
+    Exhausted,
+}
+
+/// A task removed through the client of a runner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RemovedTask {
+    pub key: TaskUid,
+    /// the next run it had, none for a task waiting for a time point
+    pub next_run: Option<Dtu>,
+}
 /// A single task running schedule
 #[derive(Debug, Clone)]
 pub struct TaskRun {
     key: TaskUid,
     time: chrono::DateTime<chrono::Utc>,
+    context: RunContext,
+}
+
+/// What [`Scheduler::execute`] knows about a run when dispatching it.
+#[derive(Debug, Clone, Default)]
+struct RunContext {
+    count: u64,
+    previous: Option<Dtu>,
+    dispatched: Option<Dtu>,
+    next: Option<Dtu>,
+    /// run by [`Scheduler::trigger_now`]
+    manual: bool,
+    state: Option<Arc<StateMap>>,
+    /// the [`CommandQueue`] of the scheduler
+    commands: Option<Arc<dyn Any + Send + Sync>>,
+    clock: Option<Arc<dyn Clock>>,
 }
 
 impl std::fmt::Display for TaskRun {
@@ -190,12 +346,52 @@ impl Ord for TaskRun {
 }
 
 impl TaskRun {
+    pub(crate) fn new(key: TaskUid, time: Dtu) -> Self {
+        Self {
+            key,
+            time,
+            context: RunContext::default(),
+        }
+    }
     pub fn key(&self) -> TaskUid {
         self.key
     }
     pub fn time(&self) -> Dtu {
         self.time
     }
+    /// the number of runs of this task so far, this one included, `0` if not dispatched
+    pub fn run_count(&self) -> u64 {
+        self.context.count
+    }
+    /// the scheduled time of the previous run of this task
+    pub fn previous_time(&self) -> Option<Dtu> {
+        self.context.previous
+    }
+    /// when the scheduler dispatched this run, by its clock
+    pub fn dispatched(&self) -> Option<Dtu> {
+        self.context.dispatched
+    }
+    /// how late this run was dispatched, compared to its scheduled time
+    pub fn lateness(&self) -> Option<TimeDelta> {
+        self.context
+            .dispatched
+            .map(|dispatched| dispatched - self.time)
+    }
+    /// the scheduled time of the next run of this task, when this one was dispatched
+    pub fn next_time(&self) -> Option<Dtu> {
+        self.context.next
+    }
+    /// run by [`Scheduler::trigger_now`], not by the schedule
+    pub fn is_manual(&self) -> bool {
+        self.context.manual
+    }
+    /// the current time, by the clock of the scheduler which dispatched this run
+    pub fn now(&self) -> Dtu {
+        self.context
+            .clock
+            .as_ref()
+            .map_or_else(now, |clock| clock.now())
+    }
 }
 
 impl<R: Runtime> Scheduler<R, ()> {
@@ -203,7 +399,12 @@ impl<R: Runtime> Scheduler<R, ()> {
         Self {
             next_up_heap: BinaryHeap::new(),
             task_map: HashMap::new(),
+            generation: 0,
             runtime,
+            clock: Arc::new(SystemClock),
+            history: None,
+            state: Arc::default(),
+            commands: Arc::default(),
             handle_manager: (),
         }
     }
@@ -213,6 +414,27 @@ impl<R: Runtime, H> Scheduler<R, H> {
     pub fn runtime(&self) -> &R {
         &self.runtime
     }
+    /// the clock of this scheduler
+    pub fn clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
+    /// the current time, by the clock of this scheduler
+    pub fn now(&self) -> Dtu {
+        self.clock.now()
+    }
+    /// set the clock
+    /// # Example
+    /// ```
+    /// # use tsuki_scheduler::prelude::*;
+    /// use chrono::{TimeZone, Utc};
+    /// let clock = ManualClock::new(Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap());
+    /// let scheduler = Scheduler::new(Local::new()).with_clock(clock.clone());
+    /// assert_eq!(scheduler.now(), clock.now());
+    /// ```
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
     /// set handle manager
     /// # Example
     #[cfg_attr(feature = "thread", doc = "```")]
@@ -226,52 +448,201 @@ impl<R: Runtime, H> Scheduler<R, H> {
         Scheduler {
             next_up_heap: self.next_up_heap,
             task_map: self.task_map,
+            generation: self.generation,
             runtime: self.runtime,
+            clock: self.clock,
+            history: self.history,
+            state: self.state,
+            commands: self.commands,
             handle_manager,
         }
     }
+    /// the next run of a task, none for a task waiting for a time point
+    pub fn next_run(&self, key: TaskUid) -> Option<Dtu> {
+        self.task_map.get(&key).and_then(|entry| entry.next)
+    }
+    /// the next run of every task, ordered by time, then by task uid
+    ///
+    /// The tasks waiting for a time point are left out.
+    pub fn list(&self) -> Vec<TaskRun> {
+        let mut runs: Vec<_> = self
+            .task_map
+            .iter()
+            .filter_map(|(key, entry)| Some(TaskRun::new(*key, entry.next?)))
+            .collect();
+        runs.sort_by_key(|run| (run.time, run.key));
+        runs
+    }
+    /// The time of the earliest next run, e.g. to sleep until then in a custom runner.
+    pub fn next_deadline(&mut self) -> Option<Dtu> {
+        while let Some(peek) = self.next_up_heap.peek() {
+            if self.is_current(peek) {
+                return Some(peek.run.time);
+            }
+            self.next_up_heap.pop();
+        }
+        None
+    }
+    /// pop the next run by `until`, skipping the stale heap entries
+    pub(crate) fn pop_due(&mut self, until: Dtu) -> Option<TaskRun> {
+        while let Some(peek) = self.next_up_heap.peek() {
+            if peek.run.time > until {
+                return None;
+            }
+            let next_up = self.next_up_heap.pop().expect("should has peek");
+            if self.is_current(&next_up) {
+                return Some(next_up.run);
+            }
+        }
+        None
+    }
+    /// Move the task of `run` to its next time point, or remove it if there is none.
+    ///
+    /// A task with an open schedule waits for a time point instead of being removed.
+    pub(crate) fn reschedule(&mut self, mut run: TaskRun) {
+        let Some(entry) = self.task_map.get_mut(&run.key) else {
+            return;
+        };
+        entry.next = entry.take_next();
+        if let Some(next_call) = entry.next {
+            run.time = next_call;
+            self.next_up_heap.push(NextUp {
+                run,
+                generation: entry.generation,
+            });
+        } else if !entry.task.schedule.is_open() {
+            self.task_map.remove(&run.key);
+        }
+    }
+    fn is_current(&self, next_up: &NextUp) -> bool {
+        self.task_map
+            .get(&next_up.run.key)
+            .is_some_and(|entry| entry.generation == next_up.generation)
+    }
 }
 
 impl<R: Runtime, H: HandleManager<R::Handle>> Scheduler<R, H> {
-    /// add a new task
-    pub fn add_task(&mut self, key: TaskUid, mut task: Task<R>) {
-        if let Some(next) = task.schedule.next() {
-            let next_up = TaskRun { key, time: next };
-            self.task_map.insert(key, task);
-            self.next_up_heap.push(next_up);
+    /// Add a new task, it replaces the task with the same uid.
+    ///
+    /// A task whose schedule has no time point left is not added.
+    ///
+    /// A task whose open schedule has no time point yet is added and waits for one, see
+    /// [`Schedule::is_open`].
+    pub fn add_task(&mut self, key: TaskUid, mut task: Task<R>) -> AddOutcome {
+        task.schedule.register(&self.schedule_waker(key));
+        let next = task.schedule.next();
+        if next.is_none() && !task.schedule.is_open() {
+            return AddOutcome::Exhausted;
+        }
+        self.generation += 1;
+        let generation = self.generation;
+        if let Some(next) = next {
+            self.next_up_heap.push(NextUp {
+                run: TaskRun::new(key, next),
+                generation,
+            });
+        }
+        let entry = TaskEntry {
+            task,
+            next,
+            deferred: BinaryHeap::new(),
+            generation,
+            count: 0,
+            previous: None,
+        };
+        let replaced = self.task_map.insert(key, entry).is_some();
+        match next {
+            Some(next_run) if replaced => AddOutcome::Replaced { next_run },
+            Some(next_run) => AddOutcome::Added { next_run },
+            None => AddOutcome::Waiting { replaced },
         }
     }
-    /// delete a task by id
+    /// delete a task by id, the handle manager is told by [`HandleManager::on_delete`]
     pub fn delete_task(&mut self, key: TaskUid) -> Option<Task<R>> {
-        self.task_map.remove(&key)
+        self.handle_manager.on_delete(key);
+        if let Some(history) = &mut self.history {
+            history.remove(key);
+        }
+        self.task_map.remove(&key).map(|entry| entry.task)
     }
-    /// execute all tasks by now
+    /// execute all tasks by now, by the clock of this scheduler
     #[inline]
     pub fn execute_by_now(&mut self) {
-        self.execute(chrono::Utc::now())
+        self.execute(self.clock.now())
     }
     /// execute all tasks by a specific time
     pub fn execute(&mut self, base_time: Dtu) {
-        let now = base_time;
-        while let Some(peek) = self.next_up_heap.peek() {
-            if peek.time > now {
-                break;
+        self.reap();
+        // the commands of the runs still running since the last execution
+        self.apply_commands();
+        while let Some(mut next_up) = self.pop_due(base_time) {
+            self.dispatch(&mut next_up);
+            self.reschedule(next_up);
+        }
+        self.apply_commands();
+    }
+    /// Run a task now, its schedule and next run are left untouched.
+    ///
+    /// The run is at the current time, marked [manual](TaskRun::is_manual), and counted by
+    /// [`run_count`](TaskRun::run_count). It is dispatched as is: nothing keeps it from
+    /// overlapping a run of the task still running, or one due at the same time. Returns none
+    /// if there is no such task.
+    ///
+    /// # Example
+    /// ```
+    /// # use tsuki_scheduler::prelude::*;
+    /// use chrono::TimeDelta;
+    /// let mut scheduler = Scheduler::new(Local::new());
+    /// let id = TaskUid::new(0);
+    /// let next = now() + TimeDelta::hours(1);
+    /// scheduler.add_task(id, Task::local(next, || println!("hello")));
+    /// let run = scheduler.trigger_now(id).expect("the task exists");
+    /// assert!(run.is_manual());
+    /// assert_eq!(scheduler.next_run(id), Some(next));
+    /// ```
+    pub fn trigger_now(&mut self, key: TaskUid) -> Option<TaskRun> {
+        if !self.task_map.contains_key(&key) {
+            return None;
+        }
+        let mut run = TaskRun::new(key, self.clock.now());
+        run.context.manual = true;
+        self.dispatch(&mut run);
+        self.apply_commands();
+        Some(run)
+    }
+    /// fill the context of a run of an existing task, record and run it
+    fn dispatch(&mut self, run: &mut TaskRun) {
+        let dispatched = self.clock.now();
+        let entry = self
+            .task_map
+            .get_mut(&run.key)
+            .expect("a dispatched run has a task");
+        entry.count += 1;
+        let manual = run.context.manual;
+        run.context = RunContext {
+            count: entry.count,
+            // a manual run is not in the schedule
+            previous: if manual {
+                entry.previous
             } else {
-                let mut next_up = self.next_up_heap.pop().expect("should has peek");
-                let Some(task) = self.task_map.get_mut(&next_up.key) else {
-                    // has been deleted
-                    continue;
-                };
-                let handle = (task.run)(&mut self.runtime, &next_up);
-                self.handle_manager.manage(&next_up, handle);
-                if let Some(next_call) = task.schedule.next() {
-                    next_up.time = next_call;
-                    self.next_up_heap.push(next_up);
-                } else {
-                    self.task_map.remove(&next_up.key);
-                }
-            }
+                entry.previous.replace(run.time)
+            },
+            dispatched: Some(dispatched),
+            next: if manual {
+                entry.next
+            } else {
+                entry.peek_next()
+            },
+            manual,
+            state: Some(self.state.clone()),
+            commands: Some(self.commands.clone()),
+            clock: Some(self.clock.clone()),
+        };
+        if let Some(history) = &mut self.history {
+            history.start(run, dispatched);
         }
+        let handle = (entry.task.run)(&mut self.runtime, run);
+        self.handle_manager.manage(run, handle);
     }
 }
 
@@ -1,8 +1,15 @@
-pub use crate::{Dtu, RunTaskFn, Scheduler, Task, TaskUid, now};
+pub use crate::{AddOutcome, Dtu, RemovedTask, RunTaskFn, Scheduler, Task, TaskUid, now};
 
 #[cfg(feature = "async-scheduler")]
 pub use crate::async_scheduler::*;
+#[cfg(feature = "blocking-scheduler")]
+pub use crate::blocking_scheduler::*;
+pub use crate::clock::{Clock, ManualClock, OffsetClock, SystemClock};
+pub use crate::ctx::SchedulerCtx;
 pub use crate::handle_manager::*;
+pub use crate::history::{RunOutcome, RunRecord};
 pub use crate::runtime::*;
 pub use crate::schedule::*;
+pub use crate::simulation::SimulationLog;
+pub use crate::state::State;
 pub use crate::timeset::{self, DynTimeSet, LowerBound, TimeSet, TimeSetExt};
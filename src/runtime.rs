@@ -20,17 +20,31 @@ pub use promise::*;
 mod local;
 pub use local::*;
 
+use chrono::TimeDelta;
+
 use crate::{Dtu, Task, TaskRun, TaskUid};
 
 use crate::schedule::IntoSchedule;
 
-pub trait Runtime {
+/// A runtime to run the tasks in.
+///
+/// It is `'static`, so that the runs can reach their scheduler, see
+/// [`SchedulerCtx`](crate::ctx::SchedulerCtx).
+pub trait Runtime: 'static {
     type Handle;
 }
 
 pub trait AsyncRuntime: Runtime + Send + Sync {
-    /// wake runner after a duration
-    fn wake_after(&self, duration: Duration, ctx: &mut std::task::Context<'_>);
+    /// a timer of this runtime
+    type Sleep: Future<Output = ()> + Send + Unpin;
+    /// create a timer completing after a duration
+    fn sleep(&self, duration: Duration) -> Self::Sleep;
+    /// Reset a timer to complete after a duration.
+    ///
+    /// The default one creates a new timer, override it if the timers of the runtime can be reused.
+    fn reset(&self, sleep: &mut Self::Sleep, duration: Duration) {
+        *sleep = self.sleep(duration);
+    }
     fn spawn<F>(task: F) -> Self::Handle
     where
         F: Future<Output = ()> + Send + 'static;
@@ -50,6 +64,16 @@ impl<R: Runtime> Task<R> {
     /// * [`TaskRun`] : information about this run.
     /// * [`TaskUid`] : the task unique identifier.
     /// * [`Dtu`] : the time when this task is scheduled to run.
+    /// * [`RunCount`] : the number of runs of this task so far, this one included.
+    /// * [`PreviousRun`] : the scheduled time of the previous run.
+    /// * [`Dispatched`] : when the scheduler dispatched this run.
+    /// * [`Lateness`] : how late this run was dispatched.
+    /// * [`NextPlanned`] : the scheduled time of the next run.
+    /// * [`Manual`] : the run was triggered manually.
+    /// * [`State`](crate::state::State) : a state shared by the scheduler, see
+    ///   [`Scheduler::with_state`](crate::Scheduler::with_state).
+    ///
+    /// Up to 12 arguments are supported.
     ///
     /// For example, you can call this function like this:
     ///
@@ -119,6 +143,68 @@ impl TaskRunArg for Dtu {
     }
 }
 
+/// The number of runs of the task so far, this one included, see [`TaskRun::run_count`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RunCount(pub u64);
+
+impl TaskRunArg for RunCount {
+    fn extract(task_run: &TaskRun) -> Self {
+        Self(task_run.run_count())
+    }
+}
+
+/// The scheduled time of the previous run of the task, see [`TaskRun::previous_time`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PreviousRun(pub Option<Dtu>);
+
+impl TaskRunArg for PreviousRun {
+    fn extract(task_run: &TaskRun) -> Self {
+        Self(task_run.previous_time())
+    }
+}
+
+/// When the scheduler dispatched the run, by its clock, see [`TaskRun::dispatched`].
+///
+/// It is the scheduled time if the run was not dispatched by a scheduler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Dispatched(pub Dtu);
+
+impl TaskRunArg for Dispatched {
+    fn extract(task_run: &TaskRun) -> Self {
+        Self(task_run.dispatched().unwrap_or(task_run.time))
+    }
+}
+
+/// How late the run was dispatched, compared to its scheduled time, see [`TaskRun::lateness`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Lateness(pub TimeDelta);
+
+impl TaskRunArg for Lateness {
+    fn extract(task_run: &TaskRun) -> Self {
+        Self(task_run.lateness().unwrap_or_default())
+    }
+}
+
+/// The scheduled time of the next run of the task, see [`TaskRun::next_time`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NextPlanned(pub Option<Dtu>);
+
+impl TaskRunArg for NextPlanned {
+    fn extract(task_run: &TaskRun) -> Self {
+        Self(task_run.next_time())
+    }
+}
+
+/// The run was triggered manually, see [`TaskRun::is_manual`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Manual(pub bool);
+
+impl TaskRunArg for Manual {
+    fn extract(task_run: &TaskRun) -> Self {
+        Self(task_run.is_manual())
+    }
+}
+
 struct WithRuntime<A>(A);
 struct Async<A, F>(A, F);
 
@@ -178,5 +264,5 @@ macro_rules! impl_for {
 }
 
 impl_for!(
-    T0 T1 T2 T3
+    T0 T1 T2 T3 T4 T5 T6 T7 T8 T9 T10 T11
 );
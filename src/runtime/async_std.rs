@@ -1,5 +1,8 @@
 use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
 
+use crate::handle_manager::RunHandle;
 use crate::schedule::IntoSchedule;
 use crate::{Runtime, Task};
 
@@ -18,6 +21,13 @@ impl Runtime for AsyncStd {
     type Handle = JoinHandle<()>;
 }
 
+/// The runs can't be aborted, [`JoinHandle::cancel`] has to be awaited.
+impl RunHandle for JoinHandle<()> {
+    fn poll_finished(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        Pin::new(self).poll(cx)
+    }
+}
+
 impl AsyncStd {
     pub fn new() -> Self {
         Self
@@ -1,5 +1,9 @@
-use std::thread::JoinHandle;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+use std::thread::{JoinHandle, ThreadId};
 
+use crate::handle_manager::RunHandle;
 use crate::schedule::IntoSchedule;
 use crate::{Runtime, Task};
 
@@ -19,6 +23,88 @@ impl Runtime for Thread {
     type Handle = std::io::Result<JoinHandle<()>>;
 }
 
+/// What the poller of a thread spawned by [`Thread::spawn`] knows of it, until it finishes.
+enum Watch {
+    Running,
+    /// a poller waits for the thread
+    Polled(Waker),
+    /// the thread finished before its spawner registered it
+    Finished,
+}
+
+/// The threads spawned by [`Thread::spawn`], an entry is removed when its thread finishes.
+static WATCHED: LazyLock<Mutex<HashMap<ThreadId, Watch>>> = LazyLock::new(Mutex::default);
+
+fn watched() -> MutexGuard<'static, HashMap<ThreadId, Watch>> {
+    WATCHED
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Wakes the poller of the current thread when dropped, even if the run panics.
+struct WakeOnExit;
+
+impl Drop for WakeOnExit {
+    fn drop(&mut self) {
+        let id = std::thread::current().id();
+        let mut watched = watched();
+        match watched.remove(&id) {
+            Some(Watch::Polled(waker)) => {
+                drop(watched);
+                waker.wake();
+            }
+            Some(_) => {}
+            None => {
+                watched.insert(id, Watch::Finished);
+            }
+        }
+    }
+}
+
+impl Thread {
+    /// Spawn the thread of a run, named `name`, which wakes the poller of its handle when it
+    /// finishes, see [`RunHandle::poll_finished`].
+    pub fn spawn<F>(name: String, run: F) -> std::io::Result<JoinHandle<()>>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let handle = std::thread::Builder::new().name(name).spawn(move || {
+            let _wake = WakeOnExit;
+            run();
+        })?;
+        let id = handle.thread().id();
+        let mut watched = watched();
+        if let Some(Watch::Finished) = watched.get(&id) {
+            watched.remove(&id);
+        } else {
+            watched.insert(id, Watch::Running);
+        }
+        Ok(handle)
+    }
+}
+
+/// A thread spawned by [`Thread::spawn`] wakes the poller when it finishes. Any other thread,
+/// or one in the middle of finishing, can't, so the poller is woken right away to poll it
+/// again. A thread which failed to spawn is finished.
+impl RunHandle for std::io::Result<JoinHandle<()>> {
+    fn poll_finished(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        let handle = match self {
+            Ok(handle) if !handle.is_finished() => handle,
+            _ => return Poll::Ready(()),
+        };
+        let mut watched = watched();
+        match watched.get_mut(&handle.thread().id()) {
+            Some(watch @ (Watch::Running | Watch::Polled(_))) => {
+                if !cx.waker().will_wake(Waker::noop()) {
+                    *watch = Watch::Polled(cx.waker().clone());
+                }
+            }
+            _ => cx.waker().wake_by_ref(),
+        }
+        Poll::Pending
+    }
+}
+
 impl Task<Thread> {
     pub fn thread<S, F>(schedule: S, task: F) -> Self
     where
@@ -29,9 +115,7 @@ impl Task<Thread> {
         Task {
             schedule: Box::new(schedule.into_schedule()),
             run: Box::new(move |_: _, task_run: _| {
-                std::thread::Builder::new()
-                    .name(task_run.to_string())
-                    .spawn(task.clone())
+                Thread::spawn(task_run.to_string(), task.clone())
             }),
         }
     }
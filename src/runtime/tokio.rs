@@ -1,5 +1,8 @@
 use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
 
+use crate::handle_manager::RunHandle;
 use crate::schedule::IntoSchedule;
 use crate::{Runtime, Task};
 
@@ -16,6 +19,16 @@ impl Runtime for Tokio {
     type Handle = tokio::task::JoinHandle<()>;
 }
 
+impl RunHandle for tokio::task::JoinHandle<()> {
+    fn poll_finished(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        Pin::new(self).poll(cx).map(|_| ())
+    }
+    fn abort(&mut self) -> bool {
+        tokio::task::JoinHandle::abort(self);
+        true
+    }
+}
+
 impl Tokio {
     pub fn new() -> Self {
         Self
@@ -16,18 +16,60 @@ mod once;
 pub use once::*;
 mod period;
 pub use period::*;
+mod backoff;
+pub use backoff::*;
+mod calendar_period;
+pub use calendar_period::*;
+#[cfg(feature = "rrule")]
+pub(crate) mod rrule;
+#[cfg(feature = "rrule")]
+pub use rrule::*;
+#[cfg(feature = "systemd")]
+mod systemd;
+#[cfg(feature = "systemd")]
+pub use systemd::*;
+#[cfg(feature = "iso8601")]
+mod iso8601;
+#[cfg(feature = "iso8601")]
+pub use iso8601::*;
+mod take;
+pub use take::*;
+mod feedback;
+pub use feedback::*;
 mod throttling;
 pub use throttling::*;
 mod never;
 pub use never::*;
 mod filtered;
 pub use filtered::*;
+mod trigger;
+pub use trigger::*;
 
 /// Schedule is a series of time points, and user can forward the schedule to a specific time point.
 pub trait Schedule: Send + 'static {
     fn peek_next(&mut self) -> Option<Dtu>;
     fn next(&mut self) -> Option<Dtu>;
     fn forward_to(&mut self, dtu: Dtu);
+    /// Called when the schedule is added to a scheduler, a schedule whose time points are pushed
+    /// from outside, like a [`Trigger`], keeps the `waker` to tell the scheduler.
+    fn register(&mut self, waker: &crate::ctx::ScheduleWaker) {
+        let _ = waker;
+    }
+    /// Time points may still be pushed into the schedule, like a [`Trigger`].
+    ///
+    /// The task of an open schedule with no time point left waits for one instead of being
+    /// removed.
+    fn is_open(&mut self) -> bool {
+        false
+    }
+    /// The remaining time points as an exact recurrence set, starting from the next one, if the
+    /// schedule can be described by one (e.g. a [`Period`] or a `Cron`).
+    ///
+    /// iCalendar has a one second resolution, the sub-second part of the start is dropped.
+    #[cfg(feature = "rrule")]
+    fn to_rrule(&mut self) -> Option<RRule> {
+        None
+    }
 }
 
 impl<T> Schedule for T
@@ -46,6 +88,19 @@ where
     fn forward_to(&mut self, dtu: Dtu) {
         self.as_mut().forward_to(dtu)
     }
+
+    fn register(&mut self, waker: &crate::ctx::ScheduleWaker) {
+        self.as_mut().register(waker)
+    }
+
+    fn is_open(&mut self) -> bool {
+        self.as_mut().is_open()
+    }
+
+    #[cfg(feature = "rrule")]
+    fn to_rrule(&mut self) -> Option<RRule> {
+        self.as_mut().to_rrule()
+    }
 }
 
 pub fn forward_to_default<S: Schedule>(schedule: &mut S, dtu: Dtu) {
@@ -60,6 +115,16 @@ pub fn forward_to_default<S: Schedule>(schedule: &mut S, dtu: Dtu) {
 pub trait IntoSchedule {
     type Output: Schedule;
     fn into_schedule(self) -> Self::Output;
+    /// Like [`into_schedule`](IntoSchedule::into_schedule), with the current time read from
+    /// `clock`, for the conversions depending on it, e.g. a [`TimeDelta`](chrono::TimeDelta)
+    /// runs from now.
+    fn into_schedule_with_clock(self, clock: &dyn crate::clock::Clock) -> Self::Output
+    where
+        Self: Sized,
+    {
+        let _ = clock;
+        self.into_schedule()
+    }
 }
 
 impl<S: Schedule> IntoSchedule for S {
@@ -90,6 +155,10 @@ pub trait ScheduleExt: Schedule + Sized {
     fn throttling(self, interval: chrono::TimeDelta) -> Throttling<Self> {
         Throttling::new(self, interval)
     }
+    /// only keep the first `count` time points
+    fn take(self, count: u64) -> Take<Self> {
+        Take::new(count, self)
+    }
     /// only keep the time points accepted by `filter`
     fn filtered(self, filter: Filter) -> Filtered<Self> {
         Filtered::new(self, filter)
@@ -142,6 +211,9 @@ impl ScheduleDynBuilder {
     pub fn throttling(self, interval: chrono::TimeDelta) -> ScheduleDynBuilder {
         self.map(|this| this.throttling(interval))
     }
+    pub fn take(self, count: u64) -> ScheduleDynBuilder {
+        self.map(|this| this.take(count))
+    }
     pub fn filtered(self, filter: Filter) -> ScheduleDynBuilder {
         self.map(|this| this.filtered(filter))
     }
@@ -30,4 +30,12 @@ impl<S: Schedule> Schedule for After<S> {
     fn forward_to(&mut self, dtu: Dtu) {
         self.inner.forward_to(dtu)
     }
+
+    fn register(&mut self, waker: &crate::ctx::ScheduleWaker) {
+        self.inner.register(waker)
+    }
+
+    fn is_open(&mut self) -> bool {
+        self.inner.is_open()
+    }
 }
@@ -0,0 +1,296 @@
+use std::{collections::hash_map::RandomState, hash::BuildHasher};
+
+use chrono::TimeDelta;
+
+use super::{Schedule, period::total_nanos};
+use crate::Dtu;
+
+/// How the interval of a [`Backoff`] grows after each time point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Growth {
+    /// multiplied by a factor
+    Exponential(f64),
+    /// the sum of the two last intervals
+    Fibonacci,
+}
+
+/// The random part of the intervals of a [`Backoff`].
+///
+/// See <https://aws.amazon.com/blogs/architecture/exponential-backoff-and-jitter/>.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Jitter {
+    /// no random part, the intervals are exact
+    #[default]
+    None,
+    /// a random interval between zero and the interval
+    Full,
+    /// half of the interval, plus a random interval up to the other half
+    Equal,
+    /// a random interval between the start interval and three times the last interval, the
+    /// growth of the backoff is ignored
+    Decorrelated,
+}
+
+/// A schedule with growing intervals, for retries.
+///
+/// The first time point is one start interval after `from`, then the interval grows until it
+/// reaches the cap, if any. The series ends after the max elapsed time since `from`, if any, or
+/// when the time points overflow.
+///
+/// The jitter is drawn from a xorshift generator with a random seed, so that the backoffs
+/// started together spread out. Seed it with [`with_seed`](Backoff::with_seed) to get
+/// reproducible time points.
+///
+/// # Example
+/// ```
+/// # use tsuki_scheduler::prelude::*;
+/// use chrono::TimeDelta;
+/// let from = now();
+/// let mut retry = Backoff::exponential(TimeDelta::seconds(1), 2.0, from)
+///     .with_cap(TimeDelta::seconds(5));
+/// assert_eq!(retry.next(), Some(from + TimeDelta::seconds(1)));
+/// assert_eq!(retry.next(), Some(from + TimeDelta::seconds(3)));
+/// assert_eq!(retry.next(), Some(from + TimeDelta::seconds(7)));
+/// // capped
+/// assert_eq!(retry.next(), Some(from + TimeDelta::seconds(12)));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backoff {
+    initial: TimeDelta,
+    growth: Growth,
+    cap: Option<TimeDelta>,
+    max_elapsed: Option<TimeDelta>,
+    jitter: Jitter,
+    /// the state of the xorshift generator
+    rng: u64,
+    from: Dtu,
+    next: Option<Dtu>,
+    /// the exact interval before `next`
+    interval: TimeDelta,
+    /// the exact interval before the previous time point, zero before the first one
+    previous: TimeDelta,
+    /// the interval before `next`, with its jitter
+    delay: TimeDelta,
+    attempt: u64,
+}
+
+impl Backoff {
+    /// Intervals multiplied by `multiplier` after each time point.
+    ///
+    /// # Panics
+    /// if `initial` is not positive, or `multiplier` is not a finite number at least 1
+    pub fn exponential(initial: TimeDelta, multiplier: f64, from: Dtu) -> Self {
+        assert!(
+            multiplier.is_finite() && multiplier >= 1.0,
+            "Backoff multiplier must be at least 1"
+        );
+        Self::new(initial, Growth::Exponential(multiplier), from)
+    }
+    /// Intervals growing like the Fibonacci sequence: 1, 1, 2, 3, 5, 8... times `initial`.
+    ///
+    /// # Panics
+    /// if `initial` is not positive
+    pub fn fibonacci(initial: TimeDelta, from: Dtu) -> Self {
+        Self::new(initial, Growth::Fibonacci, from)
+    }
+    fn new(initial: TimeDelta, growth: Growth, from: Dtu) -> Self {
+        assert!(
+            initial > TimeDelta::zero(),
+            "Backoff interval must be positive"
+        );
+        // every instance has its own keys
+        let seed = RandomState::new().hash_one(from);
+        let mut backoff = Self {
+            initial,
+            growth,
+            cap: None,
+            max_elapsed: None,
+            jitter: Jitter::None,
+            rng: 0,
+            from,
+            next: None,
+            interval: initial,
+            previous: TimeDelta::zero(),
+            delay: initial,
+            attempt: 0,
+        };
+        backoff.seed(seed);
+        backoff.restart(from);
+        backoff
+    }
+    /// the longest interval, before the jitter
+    pub fn with_cap(mut self, cap: TimeDelta) -> Self {
+        self.cap = Some(cap);
+        self.restart(self.from);
+        self
+    }
+    /// no time point later than `max_elapsed` after the start
+    pub fn with_max_elapsed(mut self, max_elapsed: TimeDelta) -> Self {
+        self.max_elapsed = Some(max_elapsed);
+        self.restart(self.from);
+        self
+    }
+    pub fn with_jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = jitter;
+        self.restart(self.from);
+        self
+    }
+    /// seed the jitter, the same seed gives the same time points
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed(seed);
+        self.restart(self.from);
+        self
+    }
+    /// Start again from the start interval, e.g. after a successful retry.
+    ///
+    /// The jitter goes on with the same generator.
+    pub fn restart(&mut self, from: Dtu) {
+        self.from = from;
+        self.attempt = 0;
+        self.previous = TimeDelta::zero();
+        self.interval = self.capped(self.initial);
+        self.delay = self.interval;
+        self.delay = self.jittered(self.interval);
+        self.next = self.point_after(from, self.delay);
+    }
+    /// the number of time points passed since the start, returned or skipped
+    pub fn attempt(&self) -> u64 {
+        self.attempt
+    }
+    fn seed(&mut self, seed: u64) {
+        // xorshift never leaves zero
+        self.rng = if seed == 0 {
+            0x9E37_79B9_7F4A_7C15
+        } else {
+            seed
+        };
+    }
+    /// a random number in `[0, 1)`, xorshift64*
+    fn random(&mut self) -> f64 {
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        let bits = self.rng.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 11;
+        bits as f64 / (1u64 << 53) as f64
+    }
+    fn capped(&self, interval: TimeDelta) -> TimeDelta {
+        self.cap.map_or(interval, |cap| interval.min(cap))
+    }
+    /// the exact interval after `interval`
+    fn grown(&self) -> TimeDelta {
+        let grown = match self.growth {
+            Growth::Exponential(multiplier) => {
+                from_nanos(total_nanos(self.interval) as f64 * multiplier)
+            }
+            Growth::Fibonacci => self
+                .interval
+                .checked_add(&self.previous)
+                .unwrap_or(TimeDelta::MAX),
+        };
+        self.capped(grown)
+    }
+    /// the interval doesn't grow anymore, it is capped or too short for the multiplier to add a
+    /// nanosecond
+    fn is_constant(&self) -> bool {
+        self.cap == Some(self.interval)
+            || matches!(self.growth, Growth::Exponential(_)) && self.grown() == self.interval
+    }
+    fn grow(&mut self) {
+        let grown = self.grown();
+        self.previous = std::mem::replace(&mut self.interval, grown);
+    }
+    fn jittered(&mut self, interval: TimeDelta) -> TimeDelta {
+        let nanos = total_nanos(interval) as f64;
+        match self.jitter {
+            Jitter::None => interval,
+            Jitter::Full => from_nanos(self.random() * nanos),
+            Jitter::Equal => from_nanos(nanos / 2.0 + self.random() * nanos / 2.0),
+            Jitter::Decorrelated => {
+                let low = total_nanos(self.initial) as f64;
+                let high = (total_nanos(self.delay) as f64 * 3.0).max(low);
+                let random = self.random();
+                self.capped(from_nanos(low + random * (high - low)))
+            }
+        }
+    }
+    fn point_after(&self, point: Dtu, delay: TimeDelta) -> Option<Dtu> {
+        let point = point.checked_add_signed(delay)?;
+        match self.max_elapsed {
+            Some(max_elapsed) if point - self.from > max_elapsed => None,
+            _ => Some(point),
+        }
+    }
+    /// take the time point after `point`
+    fn advance_from(&mut self, point: Dtu) {
+        self.grow();
+        self.delay = self.jittered(self.interval);
+        self.next = self.point_after(point, self.delay);
+    }
+    /// Skip the exact time points after `point` before `dtu`, returns the last one.
+    ///
+    /// The growing intervals are walked one by one, so that the points are the same as the ones
+    /// returned by [`next`](Schedule::next), which round each interval to the nanosecond. Skipping
+    /// a span with a multiplier `m` takes about `log_m(1 + span * (m - 1) / interval)` steps,
+    /// e.g. 80 thousand for a year of intervals from 1 second with `m = 1.0001`. The constant
+    /// intervals are skipped at once.
+    fn skip_exact(&mut self, mut point: Dtu, dtu: Dtu) -> Option<Dtu> {
+        loop {
+            if self.is_constant() {
+                let step = total_nanos(self.interval);
+                let count = (total_nanos(dtu - point) - 1) / step;
+                point = point.checked_add_signed(from_nanos_exact(count * step)?)?;
+                self.attempt += count as u64;
+                self.delay = self.interval;
+                return Some(point);
+            }
+            let grown = self.grown();
+            let following = point.checked_add_signed(grown)?;
+            if following >= dtu {
+                self.delay = self.interval;
+                return Some(point);
+            }
+            self.grow();
+            self.attempt += 1;
+            point = following;
+        }
+    }
+}
+
+/// a non-negative interval from nanoseconds, saturating
+fn from_nanos(nanos: f64) -> TimeDelta {
+    TimeDelta::nanoseconds(nanos as i64)
+}
+
+fn from_nanos_exact(nanos: i128) -> Option<TimeDelta> {
+    const NANOS_PER_SEC: i128 = 1_000_000_000;
+    TimeDelta::new(
+        i64::try_from(nanos / NANOS_PER_SEC).ok()?,
+        (nanos % NANOS_PER_SEC) as u32,
+    )
+}
+
+impl Schedule for Backoff {
+    fn peek_next(&mut self) -> Option<Dtu> {
+        self.next
+    }
+
+    fn next(&mut self) -> Option<Dtu> {
+        let next = self.next?;
+        self.attempt += 1;
+        self.advance_from(next);
+        Some(next)
+    }
+
+    /// The skipped time points are exact, the jitter is only drawn for the next one.
+    fn forward_to(&mut self, dtu: Dtu) {
+        while let Some(next) = self.next
+            && next < dtu
+        {
+            self.attempt += 1;
+            match self.skip_exact(next, dtu) {
+                Some(last) => self.advance_from(last),
+                None => self.next = None,
+            }
+        }
+    }
+}
@@ -0,0 +1,333 @@
+use chrono::{DateTime, Datelike, Days, NaiveDate, NaiveDateTime, TimeZone, Weekday};
+
+use super::Schedule;
+use crate::Dtu;
+
+/// The calendar unit a [`CalendarPeriod`] steps by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CalendarUnit {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+/// What to do when the wanted day doesn't exist in a month, e.g. the 31st of April.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum DayOverflow {
+    /// Use the last matching day of the month instead (the 30th of April).
+    #[default]
+    Clamp,
+    /// Skip this occurrence.
+    Skip,
+    /// Count the missing days into the next month (the 1st of May).
+    RollOver,
+}
+
+/// The day picked inside a month, for [`CalendarUnit::Month`] and [`CalendarUnit::Year`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MonthDay {
+    /// A day of the month, `1..=31`.
+    Day(u32),
+    /// The `nth` weekday of the month, `1..=5`, or `-1..=-5` to count from the end of the month.
+    NthWeekday(i8, Weekday),
+    /// The last day of the month.
+    Last,
+}
+
+/// A schedule stepping by calendar units (days, weeks, months or years) in a time zone.
+///
+/// Unlike [`Period`](super::Period), the distance between two runs is not fixed: "every month
+/// on the 31st" or "every year on the 29th of February" depend on the calendar, and a daily
+/// run at 09:00 stays at 09:00 across daylight saving time changes.
+///
+/// The `k`-th occurrence is computed directly from the start, so
+/// [`forward_to`](Schedule::forward_to) is `O(1)`, and a clamped day never drifts: "every month
+/// on the 31st" runs on the 30th of April and on the 31st of May again.
+///
+/// # Example
+/// ```
+/// # use tsuki_scheduler::prelude::*;
+/// use chrono::{TimeZone, Utc, Weekday};
+/// let start = Utc.with_ymd_and_hms(2025, 1, 31, 9, 0, 0).unwrap();
+/// // every month on the 31st, at 09:00, clamped to the end of shorter months
+/// let mut schedule = CalendarPeriod::months(1, start);
+/// assert_eq!(schedule.next(), Some(start));
+/// assert_eq!(schedule.next(), Some(Utc.with_ymd_and_hms(2025, 2, 28, 9, 0, 0).unwrap()));
+/// assert_eq!(schedule.next(), Some(Utc.with_ymd_and_hms(2025, 3, 31, 9, 0, 0).unwrap()));
+///
+/// // every quarter on the first monday
+/// let mut schedule = CalendarPeriod::months(3, start).on(MonthDay::NthWeekday(1, Weekday::Mon));
+/// assert_eq!(schedule.next(), Some(Utc.with_ymd_and_hms(2025, 4, 7, 9, 0, 0).unwrap()));
+/// ```
+///
+/// # Time zone transitions
+/// A local time falling into a gap (e.g. 02:30 on a spring-forward day) is moved forward by the
+/// length of the gap, an ambiguous local time (on a fall-back day) takes the earliest instant.
+#[derive(Debug, Clone)]
+pub struct CalendarPeriod<Z: TimeZone> {
+    unit: CalendarUnit,
+    step: u32,
+    overflow: DayOverflow,
+    day: MonthDay,
+    start: DateTime<Z>,
+    /// the index of the next candidate occurrence
+    index: i64,
+    next: Option<Dtu>,
+}
+
+/// Occurrences rejected in a row before the schedule is considered exhausted.
+///
+/// The gregorian calendar repeats every 400 years (4800 months), a rule that hasn't matched
+/// within that many candidates never will.
+const MAX_REJECTED: i64 = 4800;
+
+impl<Z: TimeZone> CalendarPeriod<Z> {
+    /// Create a schedule running every `step` `unit`s from `start`, `start` included.
+    ///
+    /// The day of the month and the time of the day are taken from `start`, in its time zone.
+    ///
+    /// # Panics
+    /// If `step` is zero.
+    pub fn new(unit: CalendarUnit, step: u32, start: DateTime<Z>) -> Self {
+        assert!(step > 0, "step must be positive");
+        let mut this = Self {
+            unit,
+            step,
+            overflow: DayOverflow::default(),
+            day: MonthDay::Day(start.day()),
+            start,
+            index: 0,
+            next: None,
+        };
+        this.seek(0);
+        this
+    }
+    /// every `step` days, at the same local time
+    pub fn days(step: u32, start: DateTime<Z>) -> Self {
+        Self::new(CalendarUnit::Day, step, start)
+    }
+    /// every `step` weeks, on the same weekday and at the same local time
+    pub fn weeks(step: u32, start: DateTime<Z>) -> Self {
+        Self::new(CalendarUnit::Week, step, start)
+    }
+    /// every `step` months, on the same day of the month and at the same local time
+    pub fn months(step: u32, start: DateTime<Z>) -> Self {
+        Self::new(CalendarUnit::Month, step, start)
+    }
+    /// every `step` years, on the same day and at the same local time
+    pub fn years(step: u32, start: DateTime<Z>) -> Self {
+        Self::new(CalendarUnit::Year, step, start)
+    }
+    /// Set the rule for days missing from a month, [`DayOverflow::Clamp`] by default.
+    pub fn with_overflow(mut self, overflow: DayOverflow) -> Self {
+        self.overflow = overflow;
+        self.seek(0);
+        self
+    }
+    /// Pick another day of the month, only meaningful for months and years.
+    ///
+    /// Occurrences before `start` are never emitted.
+    ///
+    /// # Panics
+    /// If the day is not in `1..=31`, or the nth weekday not in `1..=5` or `-5..=-1`.
+    pub fn on(mut self, day: MonthDay) -> Self {
+        match day {
+            MonthDay::Day(day) => {
+                assert!(
+                    (1..=31).contains(&day),
+                    "day of the month must be in 1..=31"
+                )
+            }
+            MonthDay::NthWeekday(nth, _) => assert!(
+                (1..=5).contains(&nth.unsigned_abs()),
+                "nth weekday must be in 1..=5 or -5..=-1"
+            ),
+            MonthDay::Last => {}
+        }
+        self.day = day;
+        self.seek(0);
+        self
+    }
+    pub fn unit(&self) -> CalendarUnit {
+        self.unit
+    }
+    pub fn step(&self) -> u32 {
+        self.step
+    }
+    pub fn overflow(&self) -> DayOverflow {
+        self.overflow
+    }
+    pub fn month_day(&self) -> MonthDay {
+        self.day
+    }
+    pub fn start(&self) -> &DateTime<Z> {
+        &self.start
+    }
+
+    /// The `index`-th candidate, `None` if it is skipped.
+    fn occurrence(&self, index: i64) -> Option<Dtu> {
+        let local = self.start.naive_local();
+        let step = self.step as i64;
+        let date = match self.unit {
+            CalendarUnit::Day => shift_days(local.date(), index.checked_mul(step)?)?,
+            CalendarUnit::Week => {
+                shift_days(local.date(), index.checked_mul(step)?.checked_mul(7)?)?
+            }
+            CalendarUnit::Month => {
+                let month = month_index(local.date()).checked_add(index.checked_mul(step)?)?;
+                self.pick_day(month)?
+            }
+            CalendarUnit::Year => {
+                let month = month_index(local.date())
+                    .checked_add(index.checked_mul(step)?.checked_mul(12)?)?;
+                self.pick_day(month)?
+            }
+        };
+        let time = to_utc(&self.start.timezone(), date.and_time(local.time()))?;
+        (time >= self.start.to_utc()).then_some(time)
+    }
+
+    /// The day picked in the month `month` (counted from year 0), following the overflow rule.
+    fn pick_day(&self, month: i64) -> Option<NaiveDate> {
+        let year = i32::try_from(month.div_euclid(12)).ok()?;
+        let month = month.rem_euclid(12) as u32 + 1;
+        let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+        let len = days_in_month(year, month);
+        match self.day {
+            MonthDay::Last => NaiveDate::from_ymd_opt(year, month, len),
+            MonthDay::Day(day) if day <= len => NaiveDate::from_ymd_opt(year, month, day),
+            MonthDay::Day(day) => match self.overflow {
+                DayOverflow::Clamp => NaiveDate::from_ymd_opt(year, month, len),
+                DayOverflow::Skip => None,
+                DayOverflow::RollOver => shift_days(first, day as i64 - 1),
+            },
+            MonthDay::NthWeekday(nth, weekday) if nth > 0 => {
+                let offset = (7 + weekday.num_days_from_monday() as i64
+                    - first.weekday().num_days_from_monday() as i64)
+                    % 7;
+                let day = offset + (nth as i64 - 1) * 7;
+                if day < len as i64 {
+                    shift_days(first, day)
+                } else {
+                    match self.overflow {
+                        DayOverflow::Clamp => shift_days(first, day - 7),
+                        DayOverflow::Skip => None,
+                        DayOverflow::RollOver => shift_days(first, day),
+                    }
+                }
+            }
+            MonthDay::NthWeekday(nth, weekday) => {
+                let last = NaiveDate::from_ymd_opt(year, month, len)?;
+                let offset = (7 + last.weekday().num_days_from_monday() as i64
+                    - weekday.num_days_from_monday() as i64)
+                    % 7;
+                let back = offset + (-(nth as i64) - 1) * 7;
+                if back < len as i64 {
+                    shift_days(last, -back)
+                } else {
+                    match self.overflow {
+                        DayOverflow::Clamp => shift_days(last, 7 - back),
+                        DayOverflow::Skip => None,
+                        DayOverflow::RollOver => shift_days(last, -back),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Move to the first accepted occurrence at or after the candidate `index`.
+    fn seek(&mut self, index: i64) {
+        for index in index..index.saturating_add(MAX_REJECTED) {
+            if let Some(next) = self.occurrence(index) {
+                self.index = index;
+                self.next = Some(next);
+                return;
+            }
+        }
+        self.next = None;
+    }
+
+    /// A candidate index whose occurrence is not after `dtu`, as close to it as possible.
+    fn estimate_index(&self, dtu: Dtu) -> i64 {
+        let from = self.start.naive_local();
+        let to = dtu.with_timezone(&self.start.timezone()).naive_local();
+        let step = self.step as i64;
+        let units = match self.unit {
+            CalendarUnit::Day => (to.date() - from.date()).num_days() / step,
+            CalendarUnit::Week => (to.date() - from.date()).num_days() / 7 / step,
+            CalendarUnit::Month => (month_index(to.date()) - month_index(from.date())) / step,
+            CalendarUnit::Year => (month_index(to.date()) - month_index(from.date())) / 12 / step,
+        };
+        // the day / time inside the unit may be anywhere, step back once to stay before `dtu`
+        units - 1
+    }
+}
+
+/// months since year 0
+fn month_index(date: NaiveDate) -> i64 {
+    date.year() as i64 * 12 + date.month0() as i64
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    let (next_year, next_month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    NaiveDate::from_ymd_opt(next_year, next_month, 1)
+        .and_then(|first| first.pred_opt())
+        .map_or(31, |last| last.day())
+}
+
+fn shift_days(date: NaiveDate, days: i64) -> Option<NaiveDate> {
+    if days >= 0 {
+        date.checked_add_days(Days::new(days as u64))
+    } else {
+        date.checked_sub_days(Days::new(days.unsigned_abs()))
+    }
+}
+
+/// Resolve a local time, a time in a gap is moved forward by the length of the gap.
+pub(crate) fn to_utc<Z: TimeZone>(tz: &Z, local: NaiveDateTime) -> Option<Dtu> {
+    match tz.from_local_datetime(&local) {
+        chrono::LocalResult::Single(time) => Some(time.to_utc()),
+        chrono::LocalResult::Ambiguous(earliest, _) => Some(earliest.to_utc()),
+        chrono::LocalResult::None => {
+            // use the offset in effect before the gap
+            let before = local.checked_sub_signed(chrono::TimeDelta::days(1))?;
+            let offset = tz.offset_from_local_datetime(&before).earliest()?;
+            let utc = local.checked_sub_signed(chrono::TimeDelta::seconds(
+                chrono::Offset::fix(&offset).local_minus_utc() as i64,
+            ))?;
+            Some(utc.and_utc())
+        }
+    }
+}
+
+impl<Z> Schedule for CalendarPeriod<Z>
+where
+    Z: TimeZone + Send + 'static,
+    Z::Offset: Send + 'static,
+{
+    fn peek_next(&mut self) -> Option<Dtu> {
+        self.next
+    }
+
+    fn next(&mut self) -> Option<Dtu> {
+        let next = self.next?;
+        self.seek(self.index + 1);
+        Some(next)
+    }
+
+    fn forward_to(&mut self, dtu: Dtu) {
+        if self.next.is_none_or(|next| next > dtu) {
+            return;
+        }
+        let index = self.estimate_index(dtu).max(self.index + 1);
+        self.seek(index);
+        while self.next.is_some_and(|next| next <= dtu) {
+            self.seek(self.index + 1);
+        }
+    }
+}
@@ -1,5 +1,8 @@
 use super::{IntoSchedule, Schedule};
-use crate::Dtu;
+use crate::{
+    Dtu,
+    clock::{Clock, SystemClock},
+};
 use chrono::{DateTime, Local, Utc};
 use cron::OwnedScheduleIterator;
 use std::{iter::Peekable, str::FromStr};
@@ -23,9 +26,17 @@ impl<Z: chrono::offset::TimeZone + std::fmt::Debug> std::fmt::Debug for Cron<Z>
 impl<Z: chrono::offset::TimeZone> Cron<Z> {
     /// Create a new cron schedule from a cron expression and timezone.
     pub fn from_cron_schedule(schedule: cron::Schedule, timezone: Z) -> Self {
+        Self::from_cron_schedule_with_clock(schedule, timezone, &SystemClock)
+    }
+    /// Like [`Cron::from_cron_schedule`], running from the current time of `clock`.
+    pub fn from_cron_schedule_with_clock(
+        schedule: cron::Schedule,
+        timezone: Z,
+        clock: &dyn Clock,
+    ) -> Self {
         Cron {
             schedule: schedule.clone(),
-            iterator: OwnedScheduleIterator::new(schedule, Utc::now().with_timezone(&timezone))
+            iterator: OwnedScheduleIterator::new(schedule, clock.now().with_timezone(&timezone))
                 .peekable(),
             tz: timezone,
         }
@@ -66,15 +77,60 @@ where
             OwnedScheduleIterator::new(self.schedule.clone(), dtu.with_timezone(&self.tz))
                 .peekable()
     }
+
+    /// Only for the [`Utc`] and [`chrono_tz::Tz`] time zones, and the expressions without a
+    /// year restriction.
+    #[cfg(feature = "rrule")]
+    fn to_rrule(&mut self) -> Option<super::RRule> {
+        use super::{Frequency, RRule, RecurrenceRule, WeekdayNum};
+        use cron::TimeUnitSpec;
+        use std::any::Any;
+        let tz = match (&self.tz as &dyn Any).downcast_ref::<chrono_tz::Tz>() {
+            Some(tz) => *tz,
+            None if (&self.tz as &dyn Any).is::<Utc>() => chrono_tz::Tz::UTC,
+            None => return None,
+        };
+        if !self.schedule.years().is_all() {
+            return None;
+        }
+        let dtstart = self.peek_next()?.with_timezone(&tz);
+        let schedule = &self.schedule;
+        // every field is a finite set, expanded by a daily rule
+        let mut rule = RecurrenceRule::new(Frequency::Daily);
+        rule.by_second = schedule.seconds().iter().map(|s| s as u8).collect();
+        rule.by_minute = schedule.minutes().iter().map(|m| m as u8).collect();
+        rule.by_hour = schedule.hours().iter().map(|h| h as u8).collect();
+        if !schedule.days_of_month().is_all() {
+            rule.by_month_day = schedule.days_of_month().iter().map(|d| d as i8).collect();
+        }
+        if !schedule.days_of_week().is_all() {
+            // numbered from sunday, `1..=7`
+            rule.by_day = schedule
+                .days_of_week()
+                .iter()
+                .map(|d| WeekdayNum {
+                    nth: None,
+                    weekday: chrono::Weekday::try_from(((d + 5) % 7) as u8)
+                        .expect("a weekday is in 0..7"),
+                })
+                .collect();
+        }
+        if !schedule.months().is_all() {
+            rule.by_month = schedule.months().iter().map(|m| m as u8).collect();
+        }
+        // the cron crate stops at the end of its supported years
+        let last_year = chrono::NaiveDate::from_ymd_opt(2100, 12, 31)?.and_hms_opt(23, 59, 59)?;
+        rule.until = Some(super::Until::Local(last_year));
+        Some(RRule::new(dtstart, rule))
+    }
 }
 
 impl IntoSchedule for cron::Schedule {
     type Output = Cron<chrono::Utc>;
     fn into_schedule(self) -> Self::Output {
-        Cron {
-            schedule: self.clone(),
-            iterator: OwnedScheduleIterator::new(self, Utc::now()).peekable(),
-            tz: Utc,
-        }
+        self.into_schedule_with_clock(&SystemClock)
+    }
+    fn into_schedule_with_clock(self, clock: &dyn Clock) -> Self::Output {
+        Cron::from_cron_schedule_with_clock(self, Utc, clock)
     }
 }
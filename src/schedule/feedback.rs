@@ -0,0 +1,267 @@
+use std::{
+    future::Future,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use chrono::TimeDelta;
+
+use super::{IntoSchedule, Schedule};
+#[cfg(feature = "thread")]
+use crate::runtime::Thread;
+use crate::{
+    Dtu, Task, TaskRun,
+    ctx::SchedulerCtx,
+    runtime::{AsyncRuntime, Local, Runtime},
+};
+
+/// When to run a feedback task next, returned by its run function.
+///
+/// See [`Task::local_feedback`] and [`Task::feedback_async`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NextRun {
+    /// some time after this run was dispatched
+    In(TimeDelta),
+    /// at a time point
+    At(Dtu),
+    /// at the next time point of the default schedule
+    Default,
+    /// never, the task is removed
+    Stop,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SlotState {
+    /// no run waiting for its output
+    Idle,
+    /// a run was dispatched, `rescheduled` tells if the schedule moved on without its output
+    Pending { rescheduled: bool },
+    /// a run was dispatched and the default schedule has no time point, the task waits for its
+    /// output
+    Waiting,
+    /// the output of the last run, with [`NextRun::In`] resolved to [`NextRun::At`]
+    Ready(NextRun),
+}
+
+/// The channel between the run function and the [`Feedback`] schedule.
+#[derive(Debug, Clone)]
+struct FeedbackSlot(Arc<Mutex<SlotState>>);
+
+impl FeedbackSlot {
+    fn lock(&self) -> MutexGuard<'_, SlotState> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+    /// a run is dispatched
+    fn begin(&self) {
+        *self.lock() = SlotState::Pending { rescheduled: false };
+    }
+    /// The output of a run, returns `true` if the scheduler has to take the next time point
+    /// again.
+    fn finish(&self, run: &TaskRun, next: NextRun) -> bool {
+        let next = match next {
+            NextRun::In(delta) => NextRun::At(run.dispatched().unwrap_or(run.time()) + delta),
+            next => next,
+        };
+        let mut state = self.lock();
+        match (*state, next) {
+            // the default time point is taken already
+            (SlotState::Pending { rescheduled: true }, NextRun::Default) => {
+                *state = SlotState::Idle;
+                false
+            }
+            (SlotState::Pending { rescheduled: true } | SlotState::Waiting, next) => {
+                *state = SlotState::Ready(next);
+                true
+            }
+            (_, next) => {
+                *state = SlotState::Ready(next);
+                false
+            }
+        }
+    }
+}
+
+/// A schedule whose next time point is decided by the output of the last run.
+///
+/// While the output of a run is pending, the next time point of the default schedule is taken.
+/// If it has none, the task waits without a next run, see [`Schedule::is_open`]. When the output
+/// comes, the scheduler is told to take the next time point again.
+#[derive(Debug)]
+pub struct Feedback<S> {
+    default: S,
+    slot: FeedbackSlot,
+}
+
+impl<S: Schedule> Schedule for Feedback<S> {
+    fn peek_next(&mut self) -> Option<Dtu> {
+        match *self.slot.lock() {
+            SlotState::Ready(NextRun::At(time)) => Some(time),
+            SlotState::Ready(NextRun::Stop) => None,
+            _ => self.default.peek_next(),
+        }
+    }
+    fn next(&mut self) -> Option<Dtu> {
+        let mut state = self.slot.lock();
+        match *state {
+            SlotState::Ready(NextRun::At(time)) => {
+                *state = SlotState::Idle;
+                self.default.forward_to(time);
+                Some(time)
+            }
+            SlotState::Ready(NextRun::Stop) => None,
+            SlotState::Pending { .. } | SlotState::Waiting => {
+                let next = self.default.next();
+                *state = match next {
+                    Some(_) => SlotState::Pending { rescheduled: true },
+                    None => SlotState::Waiting,
+                };
+                next
+            }
+            _ => {
+                *state = SlotState::Idle;
+                self.default.next()
+            }
+        }
+    }
+    fn forward_to(&mut self, dtu: Dtu) {
+        self.default.forward_to(dtu);
+    }
+
+    fn register(&mut self, waker: &crate::ctx::ScheduleWaker) {
+        self.default.register(waker)
+    }
+
+    fn is_open(&mut self) -> bool {
+        matches!(
+            *self.slot.lock(),
+            SlotState::Pending { .. } | SlotState::Waiting
+        ) || self.default.is_open()
+    }
+}
+
+/// Build the feedback schedule of a task, and its slot.
+fn feedback<S: IntoSchedule>(default: S) -> (Feedback<S::Output>, FeedbackSlot) {
+    let slot = FeedbackSlot(Arc::new(Mutex::new(SlotState::Idle)));
+    let schedule = Feedback {
+        default: default.into_schedule(),
+        slot: slot.clone(),
+    };
+    (schedule, slot)
+}
+
+/// tell the schedule the output of a run
+fn send<R: Runtime>(
+    slot: &FeedbackSlot,
+    run: &TaskRun,
+    ctx: Option<&SchedulerCtx<R>>,
+    next: NextRun,
+) {
+    if slot.finish(run, next)
+        && let Some(ctx) = ctx
+    {
+        ctx.refresh_self();
+    }
+}
+
+impl Task<Local> {
+    /// Create a task whose next run is decided by its run function, see [`NextRun`].
+    ///
+    /// # Example
+    /// ```
+    /// # use tsuki_scheduler::prelude::*;
+    /// use chrono::TimeDelta;
+    /// // poll every minute, back off to every hour when there is no work
+    /// let task = Task::local_feedback(Period::new(TimeDelta::minutes(1), now()), |_run| {
+    ///     let has_work = false;
+    ///     if has_work { NextRun::Default } else { NextRun::In(TimeDelta::hours(1)) }
+    /// });
+    /// ```
+    pub fn local_feedback<S, F>(default: S, run: F) -> Self
+    where
+        S: IntoSchedule,
+        S::Output: Send + 'static,
+        F: Fn(TaskRun) -> NextRun + Send + 'static,
+    {
+        let (schedule, slot) = feedback(default);
+        Task {
+            schedule: Box::new(schedule),
+            run: Box::new(move |_: &mut Local, task_run: &TaskRun| {
+                slot.begin();
+                let next = run(task_run.clone());
+                send::<Local>(&slot, task_run, None, next);
+            }),
+        }
+    }
+}
+
+#[cfg(feature = "thread")]
+impl Task<Thread> {
+    /// Create a thread task whose next run is decided by its run function, see [`NextRun`].
+    ///
+    /// Until the thread returns, the next run is planned by the default schedule.
+    pub fn thread_feedback<S, F>(default: S, run: F) -> Self
+    where
+        S: IntoSchedule,
+        S::Output: Send + 'static,
+        F: Fn(TaskRun) -> NextRun + Send + Sync + 'static,
+    {
+        let (schedule, slot) = feedback(default);
+        let run = Arc::new(run);
+        Task {
+            schedule: Box::new(schedule),
+            run: Box::new(move |_: &mut Thread, task_run: &TaskRun| {
+                slot.begin();
+                let (slot, run, task_run) = (slot.clone(), run.clone(), task_run.clone());
+                let ctx = SchedulerCtx::<Thread>::of_run(&task_run);
+                Thread::spawn(task_run.to_string(), move || {
+                    let next = run(task_run.clone());
+                    send(&slot, &task_run, ctx.as_ref(), next);
+                })
+            }),
+        }
+    }
+}
+
+impl<R: AsyncRuntime> Task<R> {
+    /// Create an async task whose next run is decided by its output, see [`NextRun`].
+    ///
+    /// Until the output comes, the next run is planned by the default schedule.
+    ///
+    /// # Example
+    /// ```
+    /// # #[cfg(not(feature = "tokio"))] fn main() {}
+    /// # #[cfg(feature = "tokio")] fn main() {
+    /// # use tsuki_scheduler::prelude::*;
+    /// use chrono::TimeDelta;
+    /// let task = Task::<Tokio>::feedback_async(Never, |_run| async {
+    ///     // retry in 5 seconds until it works
+    ///     let done = true;
+    ///     if done { NextRun::Stop } else { NextRun::In(TimeDelta::seconds(5)) }
+    /// });
+    /// # }
+    /// ```
+    pub fn feedback_async<S, F, Fut>(default: S, run: F) -> Self
+    where
+        S: IntoSchedule,
+        S::Output: Send + 'static,
+        F: Fn(TaskRun) -> Fut + Send + 'static,
+        Fut: Future<Output = NextRun> + Send + 'static,
+    {
+        let (schedule, slot) = feedback(default);
+        Task {
+            schedule: Box::new(schedule),
+            run: Box::new(move |_: &mut R, task_run: &TaskRun| {
+                slot.begin();
+                let slot = slot.clone();
+                let task_run = task_run.clone();
+                let ctx = SchedulerCtx::<R>::of_run(&task_run);
+                let output = run(task_run.clone());
+                R::spawn(async move {
+                    let next = output.await;
+                    send(&slot, &task_run, ctx.as_ref(), next);
+                })
+            }),
+        }
+    }
+}
@@ -92,6 +92,14 @@ impl<S: Schedule> Schedule for Filtered<S> {
     fn forward_to(&mut self, dtu: Dtu) {
         self.inner.forward_to(dtu)
     }
+
+    fn register(&mut self, waker: &crate::ctx::ScheduleWaker) {
+        self.inner.register(waker)
+    }
+
+    fn is_open(&mut self) -> bool {
+        self.inner.is_open()
+    }
 }
 
 /// A predicate over time points, see [`FilterKind`] for the supported combinators.
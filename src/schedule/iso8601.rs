@@ -0,0 +1,380 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, Datelike, FixedOffset, Months, SecondsFormat, TimeDelta};
+
+use super::{CalendarPeriod, IntoSchedule, Iter, MonthDay, Never, Period, Schedule, ScheduleExt};
+use crate::clock::{Clock, SystemClock};
+
+/// Error when parsing an ISO 8601 duration or repeating interval.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Iso8601Error {
+    /// an invalid part, e.g. the duration in `R/2025-01-01T00:00:00Z/P1X`
+    InvalidValue { name: &'static str, value: String },
+    /// a zero or negative interval, it can't repeat
+    EmptyInterval(String),
+}
+
+impl fmt::Display for Iso8601Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidValue { name, value } => write!(f, "invalid {name}: {value:?}"),
+            Self::EmptyInterval(interval) => write!(f, "empty interval {interval:?}"),
+        }
+    }
+}
+
+impl std::error::Error for Iso8601Error {}
+
+fn invalid(name: &'static str, value: &str) -> Iso8601Error {
+    Iso8601Error::InvalidValue {
+        name,
+        value: value.to_string(),
+    }
+}
+
+/// An ISO 8601 duration, e.g. `P1Y2M10DT2H30M` or `PT1.5S`.
+///
+/// The components are kept as written (a missing component is `None`), so that it prints back
+/// to the same text.
+///
+/// Years and months are calendar units, the other components have a fixed length: a day is
+/// 24 hours, since the date times of ISO 8601 have fixed offsets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct IsoDuration {
+    pub years: Option<u32>,
+    pub months: Option<u32>,
+    pub weeks: Option<u32>,
+    pub days: Option<u32>,
+    pub hours: Option<u32>,
+    pub minutes: Option<u32>,
+    pub seconds: Option<u32>,
+    /// the fraction of the seconds, in nanoseconds
+    pub nanoseconds: u32,
+}
+
+impl IsoDuration {
+    /// the calendar part, in months, none if it overflows
+    pub fn calendar_months(&self) -> Option<u32> {
+        self.years
+            .unwrap_or(0)
+            .checked_mul(12)?
+            .checked_add(self.months.unwrap_or(0))
+    }
+    /// the fixed part, everything but years and months
+    pub fn fixed(&self) -> TimeDelta {
+        let hours = (self.weeks.unwrap_or(0) as i64 * 7 + self.days.unwrap_or(0) as i64) * 24
+            + self.hours.unwrap_or(0) as i64;
+        let seconds =
+            (hours * 60 + self.minutes.unwrap_or(0) as i64) * 60 + self.seconds.unwrap_or(0) as i64;
+        TimeDelta::seconds(seconds) + TimeDelta::nanoseconds(self.nanoseconds as i64)
+    }
+    /// the duration as a time delta, if there are no years and no months
+    pub fn as_time_delta(&self) -> Option<TimeDelta> {
+        (self.calendar_months() == Some(0)).then(|| self.fixed())
+    }
+    fn is_zero(&self) -> bool {
+        self.calendar_months() == Some(0) && self.fixed() <= TimeDelta::zero()
+    }
+    /// `time + times × self`, the months are added first, then the fixed part
+    fn add_to(&self, time: DateTime<FixedOffset>, times: i64) -> Option<DateTime<FixedOffset>> {
+        let months = (self.calendar_months()? as i64).checked_mul(times)?;
+        let time = if months >= 0 {
+            time.checked_add_months(Months::new(u32::try_from(months).ok()?))?
+        } else {
+            time.checked_sub_months(Months::new(u32::try_from(-months).ok()?))?
+        };
+        let fixed = self.fixed();
+        let nanos = (fixed.num_seconds() as i128 * 1_000_000_000 + fixed.subsec_nanos() as i128)
+            .checked_mul(times as i128)?;
+        time.checked_add_signed(TimeDelta::nanoseconds(i64::try_from(nanos).ok()?))
+    }
+    /// A lower bound of the length, a month is at least 28 days, none if it overflows.
+    fn min_length(&self) -> Option<TimeDelta> {
+        TimeDelta::try_days(28 * self.calendar_months()? as i64)?.checked_add(&self.fixed())
+    }
+    /// Every duration from `start`, `start` included.
+    fn repeat_from(&self, start: DateTime<FixedOffset>) -> Box<dyn Schedule> {
+        let Some(months) = self.calendar_months() else {
+            return Never.dyn_box();
+        };
+        if let Some(delta) = self.as_time_delta() {
+            Period::starting_at(delta, start.to_utc()).dyn_box()
+        } else if self.fixed() == TimeDelta::zero() {
+            CalendarPeriod::months(months, start).dyn_box()
+        } else {
+            let this = *self;
+            Iter::new((0..).map_while(move |times| this.add_to(start, times).map(|t| t.to_utc())))
+                .dyn_box()
+        }
+    }
+    /// Every duration before `end`, from `end - times × self`, with the day of the month of `end`.
+    fn repeat_until(&self, end: DateTime<FixedOffset>, times: i64) -> Option<Box<dyn Schedule>> {
+        let start = self.add_to(end, -times)?;
+        let months = self.calendar_months()?;
+        let schedule = if self.as_time_delta().is_some() {
+            self.repeat_from(start)
+        } else if self.fixed() == TimeDelta::zero() {
+            CalendarPeriod::months(months, start)
+                .on(MonthDay::Day(end.day()))
+                .dyn_box()
+        } else {
+            let this = *self;
+            Iter::new(
+                (-times..0).map_while(move |times| this.add_to(end, times).map(|t| t.to_utc())),
+            )
+            .dyn_box()
+        };
+        Some(schedule.before(end.to_utc()).dyn_box())
+    }
+}
+
+impl FromStr for IsoDuration {
+    type Err = Iso8601Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || invalid("duration", s);
+        let mut duration = Self::default();
+        let mut number = String::new();
+        let mut in_time = false;
+        let mut empty = true;
+        for char in s.strip_prefix('P').ok_or_else(error)?.chars() {
+            match char {
+                '0'..='9' | '.' | ',' => number.push(if char == ',' { '.' } else { char }),
+                'T' if !in_time && number.is_empty() => {
+                    in_time = true;
+                    empty = true;
+                }
+                unit => {
+                    let (whole, fraction) = number.split_once('.').unwrap_or((&number, ""));
+                    let value: u32 = whole.parse().map_err(|_| error())?;
+                    let slot = match (in_time, unit) {
+                        (false, 'Y') => &mut duration.years,
+                        (false, 'M') => &mut duration.months,
+                        (false, 'W') => &mut duration.weeks,
+                        (false, 'D') => &mut duration.days,
+                        (true, 'H') => &mut duration.hours,
+                        (true, 'M') => &mut duration.minutes,
+                        (true, 'S') => &mut duration.seconds,
+                        _ => return Err(error()),
+                    };
+                    if slot.is_some() {
+                        return Err(error());
+                    }
+                    *slot = Some(value);
+                    if number.contains('.') {
+                        // only the seconds may have a fraction
+                        if unit != 'S' || fraction.is_empty() || fraction.len() > 9 {
+                            return Err(error());
+                        }
+                        let digits: u32 = fraction.parse().map_err(|_| error())?;
+                        duration.nanoseconds = digits * 10u32.pow(9 - fraction.len() as u32);
+                    }
+                    number.clear();
+                    empty = false;
+                }
+            }
+        }
+        if empty || !number.is_empty() || duration.calendar_months().is_none() {
+            return Err(error());
+        }
+        Ok(duration)
+    }
+}
+
+impl fmt::Display for IsoDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("P")?;
+        for (value, unit) in [
+            (self.years, 'Y'),
+            (self.months, 'M'),
+            (self.weeks, 'W'),
+            (self.days, 'D'),
+        ] {
+            if let Some(value) = value {
+                write!(f, "{value}{unit}")?;
+            }
+        }
+        if self.hours.is_some() || self.minutes.is_some() || self.seconds.is_some() {
+            f.write_str("T")?;
+            for (value, unit) in [(self.hours, 'H'), (self.minutes, 'M')] {
+                if let Some(value) = value {
+                    write!(f, "{value}{unit}")?;
+                }
+            }
+            if let Some(seconds) = self.seconds {
+                write!(f, "{seconds}")?;
+                if self.nanoseconds > 0 {
+                    let fraction = format!("{:09}", self.nanoseconds);
+                    write!(f, ".{}", fraction.trim_end_matches('0'))?;
+                }
+                f.write_str("S")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Every duration from now, like [`TimeDelta`] does.
+impl IntoSchedule for IsoDuration {
+    type Output = Box<dyn Schedule>;
+    fn into_schedule(self) -> Self::Output {
+        self.into_schedule_with_clock(&SystemClock)
+    }
+    fn into_schedule_with_clock(self, clock: &dyn Clock) -> Self::Output {
+        self.repeat_from(clock.now().fixed_offset())
+    }
+}
+
+/// The interval part of a [`RepeatingInterval`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IsoInterval {
+    /// `<start>/<end>`, repeating every `end - start`
+    StartEnd(DateTime<FixedOffset>, DateTime<FixedOffset>),
+    /// `<start>/<duration>`
+    StartDuration(DateTime<FixedOffset>, IsoDuration),
+    /// `<duration>/<end>`, the intervals are counted backward from `end`
+    DurationEnd(IsoDuration, DateTime<FixedOffset>),
+}
+
+/// An ISO 8601 repeating interval, e.g. `R5/2024-03-01T00:00:00Z/PT1H30M`.
+///
+/// As a schedule, it runs at the start of every interval:
+/// - `Rn` runs `n` times, `R` never stops,
+/// - `<start>/<end>` and `<start>/<duration>` run from `start`, every `end - start` or
+///   `duration`, to a [`Period`] if the duration has no years or months, to a
+///   [`CalendarPeriod`] if it only has years and months,
+/// - `<duration>/<end>` counts the intervals backward from `end`, the last one ends at `end`.
+///   Without a count, it runs from now until `end`.
+///
+/// Without a count, the time points before now are skipped. With `Rn`, they are kept since they
+/// are counted, forward the schedule to skip them.
+///
+/// # Example
+/// ```
+/// # use tsuki_scheduler::prelude::*;
+/// use chrono::{TimeZone, Utc};
+/// let interval: RepeatingInterval = "R3/2024-03-01T00:00:00Z/PT1H30M".parse().unwrap();
+/// assert_eq!(interval.to_string(), "R3/2024-03-01T00:00:00Z/PT1H30M");
+/// let mut schedule = interval.into_schedule();
+/// assert_eq!(schedule.next(), Some(Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap()));
+/// assert_eq!(schedule.next(), Some(Utc.with_ymd_and_hms(2024, 3, 1, 1, 30, 0).unwrap()));
+/// assert_eq!(schedule.next(), Some(Utc.with_ymd_and_hms(2024, 3, 1, 3, 0, 0).unwrap()));
+/// assert_eq!(schedule.next(), None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RepeatingInterval {
+    /// the number of intervals, `None` for an unbounded repetition
+    pub repetitions: Option<u64>,
+    pub interval: IsoInterval,
+}
+
+fn parse_date_time(value: &str) -> Result<DateTime<FixedOffset>, Iso8601Error> {
+    DateTime::parse_from_rfc3339(value).map_err(|_| invalid("date time", value))
+}
+
+fn format_date_time(time: &DateTime<FixedOffset>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+impl FromStr for RepeatingInterval {
+    type Err = Iso8601Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('/');
+        let (Some(repetitions), Some(first), Some(second), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid("repeating interval", s));
+        };
+        let repetitions = match repetitions.strip_prefix('R') {
+            Some("") => None,
+            Some(count) => Some(
+                count
+                    .parse()
+                    .map_err(|_| invalid("repetitions", repetitions))?,
+            ),
+            None => return Err(invalid("repetitions", repetitions)),
+        };
+        let interval = match (first.starts_with('P'), second.starts_with('P')) {
+            (false, false) => {
+                let (start, end) = (parse_date_time(first)?, parse_date_time(second)?);
+                if end <= start {
+                    return Err(Iso8601Error::EmptyInterval(s.to_string()));
+                }
+                IsoInterval::StartEnd(start, end)
+            }
+            (false, true) => IsoInterval::StartDuration(parse_date_time(first)?, second.parse()?),
+            (true, false) => IsoInterval::DurationEnd(first.parse()?, parse_date_time(second)?),
+            (true, true) => return Err(invalid("repeating interval", s)),
+        };
+        if let IsoInterval::StartDuration(_, duration) | IsoInterval::DurationEnd(duration, _) =
+            interval
+            && duration.is_zero()
+        {
+            return Err(Iso8601Error::EmptyInterval(s.to_string()));
+        }
+        Ok(Self {
+            repetitions,
+            interval,
+        })
+    }
+}
+
+impl fmt::Display for RepeatingInterval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("R")?;
+        if let Some(repetitions) = self.repetitions {
+            write!(f, "{repetitions}")?;
+        }
+        match &self.interval {
+            IsoInterval::StartEnd(start, end) => {
+                write!(f, "/{}/{}", format_date_time(start), format_date_time(end))
+            }
+            IsoInterval::StartDuration(start, duration) => {
+                write!(f, "/{}/{duration}", format_date_time(start))
+            }
+            IsoInterval::DurationEnd(duration, end) => {
+                write!(f, "/{duration}/{}", format_date_time(end))
+            }
+        }
+    }
+}
+
+impl IntoSchedule for RepeatingInterval {
+    type Output = Box<dyn Schedule>;
+    fn into_schedule(self) -> Self::Output {
+        self.into_schedule_with_clock(&SystemClock)
+    }
+    fn into_schedule_with_clock(self, clock: &dyn Clock) -> Self::Output {
+        let mut schedule = match self.interval {
+            IsoInterval::StartEnd(start, end) => {
+                Period::starting_at(end - start, start.to_utc()).dyn_box()
+            }
+            IsoInterval::StartDuration(start, duration) => duration.repeat_from(start),
+            IsoInterval::DurationEnd(duration, end) => {
+                let times = match self.repetitions {
+                    Some(repetitions) => i64::try_from(repetitions).ok(),
+                    // enough intervals to start before now
+                    None => {
+                        let left = (end.to_utc() - clock.now()).max(TimeDelta::zero());
+                        let step = duration
+                            .min_length()
+                            .map_or(i64::MAX, |length| length.num_seconds())
+                            .max(1);
+                        Some(left.num_seconds() / step + 1)
+                    }
+                };
+                let Some(schedule) = times.and_then(|times| duration.repeat_until(end, times))
+                else {
+                    return Never.dyn_box();
+                };
+                schedule
+            }
+        };
+        if self.repetitions.is_none() {
+            schedule.forward_to(clock.now());
+        }
+        match self.repetitions {
+            Some(repetitions) => schedule.take(repetitions).dyn_box(),
+            None => schedule,
+        }
+    }
+}
@@ -43,6 +43,15 @@ where
         self.0.forward_to(dtu);
         self.1.forward_to(dtu);
     }
+
+    fn register(&mut self, waker: &crate::ctx::ScheduleWaker) {
+        self.0.register(waker);
+        self.1.register(waker);
+    }
+
+    fn is_open(&mut self) -> bool {
+        self.0.is_open() || self.1.is_open()
+    }
 }
 
 impl<S0, S1> Or<S0, S1> {
@@ -1,7 +1,10 @@
-use chrono::{TimeDelta, Utc};
+use chrono::TimeDelta;
 
 use super::{IntoSchedule, Schedule};
-use crate::Dtu;
+use crate::{
+    Dtu,
+    clock::{Clock, SystemClock},
+};
 
 /// A schedule that runs at a fixed interval.
 #[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq)]
@@ -12,13 +15,23 @@ pub struct Period {
 
 impl Period {
     pub fn new(period: TimeDelta, from: Dtu) -> Self {
+        Self::new_with_clock(period, from, &SystemClock)
+    }
+    /// Like [`Period::new`], with `from` checked against the current time of `clock`.
+    pub fn new_with_clock(period: TimeDelta, from: Dtu, clock: &dyn Clock) -> Self {
         assert!(period > TimeDelta::zero(), "Period must be positive");
         assert!(
-            from > Utc::now() - period,
+            from > clock.now() - period,
             "start time must be in the future"
         );
         Self { period, next: from }
     }
+    /// Like [`Period::new`], but `from` may be in the past.
+    #[cfg(feature = "iso8601")]
+    pub(crate) fn starting_at(period: TimeDelta, from: Dtu) -> Self {
+        assert!(period > TimeDelta::zero(), "Period must be positive");
+        Self { period, next: from }
+    }
     pub fn period(&self) -> TimeDelta {
         self.period
     }
@@ -30,7 +43,7 @@ impl Period {
 const NANOS_PER_SEC: i64 = 1_000_000_000;
 
 /// total nanoseconds of a [`TimeDelta`], in `i128` to never overflow
-fn total_nanos(delta: TimeDelta) -> i128 {
+pub(super) fn total_nanos(delta: TimeDelta) -> i128 {
     delta.num_seconds() as i128 * NANOS_PER_SEC as i128 + delta.subsec_nanos() as i128
 }
 
@@ -72,11 +85,36 @@ impl Schedule for Period {
             self.next = dtu + self.period - rest;
         }
     }
+
+    #[cfg(feature = "rrule")]
+    fn to_rrule(&mut self) -> Option<super::RRule> {
+        use super::{Frequency, RRule, RecurrenceRule};
+        if self.period.subsec_nanos() != 0 {
+            return None;
+        }
+        let seconds = self.period.num_seconds();
+        let (freq, unit) = [
+            (Frequency::Weekly, 7 * 24 * 3600),
+            (Frequency::Daily, 24 * 3600),
+            (Frequency::Hourly, 3600),
+            (Frequency::Minutely, 60),
+            (Frequency::Secondly, 1),
+        ]
+        .into_iter()
+        .find(|(_, unit)| seconds % unit == 0)?;
+        let mut rule = RecurrenceRule::new(freq);
+        rule.interval = u32::try_from(seconds / unit).ok()?;
+        let dtstart = chrono::Timelike::with_nanosecond(&self.next, 0)?;
+        Some(RRule::new(dtstart.with_timezone(&chrono_tz::Tz::UTC), rule))
+    }
 }
 
 impl IntoSchedule for TimeDelta {
     type Output = Period;
     fn into_schedule(self) -> Self::Output {
-        Period::new(self, Utc::now())
+        self.into_schedule_with_clock(&SystemClock)
+    }
+    fn into_schedule_with_clock(self, clock: &dyn Clock) -> Self::Output {
+        Period::new_with_clock(self, clock.now(), clock)
     }
 }
@@ -0,0 +1,1298 @@
+use std::{
+    collections::{BTreeSet, VecDeque},
+    fmt,
+    ops::Bound,
+    str::FromStr,
+    sync::Arc,
+};
+
+use chrono::{
+    DateTime, Datelike, Days, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Timelike, Weekday,
+};
+use chrono_tz::Tz;
+
+use super::{Schedule, calendar_period::to_utc};
+use crate::{
+    Dtu,
+    timeset::{LowerBound, TimeSet, pred_dtu},
+};
+
+/// The `FREQ` rule part.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Frequency {
+    Secondly,
+    Minutely,
+    Hourly,
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// An item of the `BYDAY` rule part, e.g. `2TU` or `-1FR`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WeekdayNum {
+    /// The `nth` occurrence of the weekday inside the month or the year, negative counts from
+    /// the end. `None` means every such weekday.
+    pub nth: Option<i8>,
+    pub weekday: Weekday,
+}
+
+/// The `UNTIL` rule part, inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Until {
+    /// a `DATE` value, the whole day is included
+    Date(NaiveDate),
+    /// a floating `DATE-TIME`, in the time zone of `DTSTART`
+    Local(NaiveDateTime),
+    Utc(Dtu),
+}
+
+/// A parsed `RRULE` value, as defined by [RFC 5545](https://www.rfc-editor.org/rfc/rfc5545#section-3.3.10).
+///
+/// Parse it from `FREQ=MONTHLY;BYDAY=2TU;COUNT=10` (the `RRULE:` prefix is optional), and print
+/// it back with [`Display`](std::fmt::Display).
+///
+/// A rule alone has no start, see [`RRule`] for the schedule.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RecurrenceRule {
+    pub freq: Frequency,
+    pub interval: u32,
+    pub count: Option<u32>,
+    pub until: Option<Until>,
+    pub by_second: Vec<u8>,
+    pub by_minute: Vec<u8>,
+    pub by_hour: Vec<u8>,
+    pub by_day: Vec<WeekdayNum>,
+    pub by_month_day: Vec<i8>,
+    pub by_year_day: Vec<i16>,
+    pub by_week_no: Vec<i8>,
+    pub by_month: Vec<u8>,
+    pub by_set_pos: Vec<i16>,
+    pub week_start: Weekday,
+}
+
+impl RecurrenceRule {
+    pub fn new(freq: Frequency) -> Self {
+        Self {
+            freq,
+            interval: 1,
+            count: None,
+            until: None,
+            by_second: vec![],
+            by_minute: vec![],
+            by_hour: vec![],
+            by_day: vec![],
+            by_month_day: vec![],
+            by_year_day: vec![],
+            by_week_no: vec![],
+            by_month: vec![],
+            by_set_pos: vec![],
+            week_start: Weekday::Mon,
+        }
+    }
+}
+
+/// Error when parsing a recurrence rule or a recurrence set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RRuleError {
+    MissingDtStart,
+    MissingFreq,
+    InvalidLine(String),
+    InvalidValue { name: String, value: String },
+    UnknownTimeZone(String),
+    Unsupported(String),
+}
+
+impl fmt::Display for RRuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingDtStart => write!(f, "missing DTSTART"),
+            Self::MissingFreq => write!(f, "missing FREQ in RRULE"),
+            Self::InvalidLine(line) => write!(f, "invalid line: {line}"),
+            Self::InvalidValue { name, value } => write!(f, "invalid value for {name}: {value}"),
+            Self::UnknownTimeZone(tz) => write!(f, "unknown time zone: {tz}"),
+            Self::Unsupported(what) => write!(f, "unsupported: {what}"),
+        }
+    }
+}
+
+impl std::error::Error for RRuleError {}
+
+pub(crate) fn invalid(name: &str, value: &str) -> RRuleError {
+    RRuleError::InvalidValue {
+        name: name.to_string(),
+        value: value.to_string(),
+    }
+}
+
+fn parse_list<T: FromStr>(
+    name: &str,
+    value: &str,
+    valid: impl Fn(&T) -> bool,
+) -> Result<Vec<T>, RRuleError> {
+    value
+        .split(',')
+        .map(|item| {
+            item.trim()
+                .trim_start_matches('+')
+                .parse::<T>()
+                .ok()
+                .filter(&valid)
+                .ok_or_else(|| invalid(name, value))
+        })
+        .collect()
+}
+
+fn parse_weekday(value: &str) -> Option<Weekday> {
+    match value {
+        "MO" => Some(Weekday::Mon),
+        "TU" => Some(Weekday::Tue),
+        "WE" => Some(Weekday::Wed),
+        "TH" => Some(Weekday::Thu),
+        "FR" => Some(Weekday::Fri),
+        "SA" => Some(Weekday::Sat),
+        "SU" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+fn weekday_str(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+impl FromStr for WeekdayNum {
+    type Err = RRuleError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let split = s.len().saturating_sub(2);
+        let (nth, weekday) = s
+            .split_at_checked(split)
+            .ok_or_else(|| invalid("BYDAY", s))?;
+        let weekday = parse_weekday(weekday).ok_or_else(|| invalid("BYDAY", s))?;
+        let nth = match nth.trim_start_matches('+') {
+            "" => None,
+            nth => Some(
+                nth.parse::<i8>()
+                    .ok()
+                    .filter(|nth| *nth != 0 && (-53..=53).contains(nth))
+                    .ok_or_else(|| invalid("BYDAY", s))?,
+            ),
+        };
+        Ok(Self { nth, weekday })
+    }
+}
+
+impl fmt::Display for WeekdayNum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(nth) = self.nth {
+            write!(f, "{nth}")?;
+        }
+        f.write_str(weekday_str(self.weekday))
+    }
+}
+
+const DATE_FORMAT: &str = "%Y%m%d";
+const DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%S";
+
+/// A `DATE` or `DATE-TIME` value as written in the text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DateValue {
+    Date(NaiveDate),
+    Local(NaiveDateTime),
+    Utc(Dtu),
+}
+
+impl DateValue {
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if let Some(utc) = value.strip_suffix('Z') {
+            NaiveDateTime::parse_from_str(utc, DATE_TIME_FORMAT)
+                .ok()
+                .map(|utc| Self::Utc(utc.and_utc()))
+        } else if value.contains('T') {
+            NaiveDateTime::parse_from_str(value, DATE_TIME_FORMAT)
+                .ok()
+                .map(Self::Local)
+        } else {
+            NaiveDate::parse_from_str(value, DATE_FORMAT)
+                .ok()
+                .map(Self::Date)
+        }
+    }
+    /// The local time, in `tz` for the floating values.
+    pub(crate) fn naive_in(self, tz: &Tz) -> NaiveDateTime {
+        match self {
+            Self::Date(date) => date.and_time(NaiveTime::MIN),
+            Self::Local(local) => local,
+            Self::Utc(utc) => utc.with_timezone(tz).naive_local(),
+        }
+    }
+    /// The time point, the floating values are in `tz`.
+    pub(crate) fn resolve(self, tz: &Tz) -> Option<Dtu> {
+        match self {
+            Self::Utc(utc) => Some(utc),
+            other => to_utc(tz, other.naive_in(tz)),
+        }
+    }
+}
+
+impl FromStr for Until {
+    type Err = RRuleError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(
+            match DateValue::parse(s).ok_or_else(|| invalid("UNTIL", s))? {
+                DateValue::Date(date) => Self::Date(date),
+                DateValue::Local(local) => Self::Local(local),
+                DateValue::Utc(utc) => Self::Utc(utc),
+            },
+        )
+    }
+}
+
+impl fmt::Display for Until {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Date(date) => write!(f, "{}", date.format(DATE_FORMAT)),
+            Self::Local(local) => write!(f, "{}", local.format(DATE_TIME_FORMAT)),
+            Self::Utc(utc) => write!(f, "{}Z", utc.format(DATE_TIME_FORMAT)),
+        }
+    }
+}
+
+impl FromStr for Frequency {
+    type Err = RRuleError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "SECONDLY" => Self::Secondly,
+            "MINUTELY" => Self::Minutely,
+            "HOURLY" => Self::Hourly,
+            "DAILY" => Self::Daily,
+            "WEEKLY" => Self::Weekly,
+            "MONTHLY" => Self::Monthly,
+            "YEARLY" => Self::Yearly,
+            _ => return Err(invalid("FREQ", s)),
+        })
+    }
+}
+
+impl fmt::Display for Frequency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Secondly => "SECONDLY",
+            Self::Minutely => "MINUTELY",
+            Self::Hourly => "HOURLY",
+            Self::Daily => "DAILY",
+            Self::Weekly => "WEEKLY",
+            Self::Monthly => "MONTHLY",
+            Self::Yearly => "YEARLY",
+        })
+    }
+}
+
+impl FromStr for RecurrenceRule {
+    type Err = RRuleError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let s = s.strip_prefix("RRULE:").unwrap_or(s);
+        let mut freq = None;
+        let mut rule = RecurrenceRule::new(Frequency::Daily);
+        for part in s.split(';').filter(|part| !part.is_empty()) {
+            let (name, value) = part
+                .split_once('=')
+                .ok_or_else(|| RRuleError::InvalidLine(part.to_string()))?;
+            let name = name.trim().to_ascii_uppercase();
+            let value = value.trim();
+            match name.as_str() {
+                "FREQ" => freq = Some(value.to_ascii_uppercase().parse()?),
+                "INTERVAL" => {
+                    rule.interval = value
+                        .parse()
+                        .ok()
+                        .filter(|interval| *interval > 0)
+                        .ok_or_else(|| invalid(&name, value))?
+                }
+                "COUNT" => rule.count = Some(value.parse().map_err(|_| invalid(&name, value))?),
+                "UNTIL" => rule.until = Some(value.parse()?),
+                "BYSECOND" => rule.by_second = parse_list(&name, value, |v: &u8| *v < 60)?,
+                "BYMINUTE" => rule.by_minute = parse_list(&name, value, |v: &u8| *v < 60)?,
+                "BYHOUR" => rule.by_hour = parse_list(&name, value, |v: &u8| *v < 24)?,
+                "BYDAY" => {
+                    rule.by_day = value
+                        .split(',')
+                        .map(|day| day.to_ascii_uppercase().parse())
+                        .collect::<Result<_, _>>()?
+                }
+                "BYMONTHDAY" => {
+                    rule.by_month_day =
+                        parse_list(&name, value, |v: &i8| *v != 0 && (-31..=31).contains(v))?
+                }
+                "BYYEARDAY" => {
+                    rule.by_year_day =
+                        parse_list(&name, value, |v: &i16| *v != 0 && (-366..=366).contains(v))?
+                }
+                "BYWEEKNO" => {
+                    rule.by_week_no =
+                        parse_list(&name, value, |v: &i8| *v != 0 && (-53..=53).contains(v))?
+                }
+                "BYMONTH" => {
+                    rule.by_month = parse_list(&name, value, |v: &u8| (1..=12).contains(v))?
+                }
+                "BYSETPOS" => {
+                    rule.by_set_pos =
+                        parse_list(&name, value, |v: &i16| *v != 0 && (-366..=366).contains(v))?
+                }
+                "WKST" => {
+                    rule.week_start = parse_weekday(&value.to_ascii_uppercase())
+                        .ok_or_else(|| invalid(&name, value))?
+                }
+                _ => return Err(RRuleError::Unsupported(format!("rule part {name}"))),
+            }
+        }
+        rule.freq = freq.ok_or(RRuleError::MissingFreq)?;
+        if rule.count.is_some() && rule.until.is_some() {
+            return Err(invalid("COUNT", "COUNT and UNTIL are mutually exclusive"));
+        }
+        Ok(rule)
+    }
+}
+
+fn write_list<T: fmt::Display>(
+    f: &mut fmt::Formatter<'_>,
+    name: &str,
+    values: &[T],
+) -> fmt::Result {
+    if values.is_empty() {
+        return Ok(());
+    }
+    write!(f, ";{name}=")?;
+    for (index, value) in values.iter().enumerate() {
+        if index > 0 {
+            f.write_str(",")?;
+        }
+        write!(f, "{value}")?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for RecurrenceRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FREQ={}", self.freq)?;
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={count}")?;
+        }
+        if let Some(until) = self.until {
+            write!(f, ";UNTIL={until}")?;
+        }
+        write_list(f, "BYSECOND", &self.by_second)?;
+        write_list(f, "BYMINUTE", &self.by_minute)?;
+        write_list(f, "BYHOUR", &self.by_hour)?;
+        write_list(f, "BYDAY", &self.by_day)?;
+        write_list(f, "BYMONTHDAY", &self.by_month_day)?;
+        write_list(f, "BYYEARDAY", &self.by_year_day)?;
+        write_list(f, "BYWEEKNO", &self.by_week_no)?;
+        write_list(f, "BYMONTH", &self.by_month)?;
+        write_list(f, "BYSETPOS", &self.by_set_pos)?;
+        if self.week_start != Weekday::Mon {
+            write!(f, ";WKST={}", weekday_str(self.week_start))?;
+        }
+        Ok(())
+    }
+}
+
+/// A rule bound to a start, with the implicit `BY*` parts filled from `DTSTART`.
+#[derive(Debug, Clone)]
+struct BoundRule {
+    rule: RecurrenceRule,
+    /// `DTSTART` in local time
+    base: NaiveDateTime,
+    start: Dtu,
+    tz: Tz,
+    until: Option<Dtu>,
+}
+
+/// A search covering more than a whole gregorian cycle without any match will never match.
+const MAX_SEARCH_DAYS: i64 = 146_097;
+
+/// A sub-daily rule whose interval never lines up with its `BYxxx` parts, e.g.
+/// `FREQ=MINUTELY;INTERVAL=2;BYMINUTE=1`, is given up after this many periods without a match.
+const MAX_SEARCH_PERIODS: u32 = 1_000_000;
+
+impl BoundRule {
+    fn new(original: &RecurrenceRule, dtstart: &DateTime<Tz>) -> Self {
+        let base = dtstart.naive_local();
+        let mut rule = original.clone();
+        if rule.by_week_no.is_empty()
+            && rule.by_year_day.is_empty()
+            && rule.by_month_day.is_empty()
+            && rule.by_day.is_empty()
+        {
+            match rule.freq {
+                Frequency::Yearly => {
+                    if rule.by_month.is_empty() {
+                        rule.by_month = vec![base.month() as u8];
+                    }
+                    rule.by_month_day = vec![base.day() as i8];
+                }
+                Frequency::Monthly => rule.by_month_day = vec![base.day() as i8],
+                Frequency::Weekly => {
+                    rule.by_day = vec![WeekdayNum {
+                        nth: None,
+                        weekday: base.weekday(),
+                    }]
+                }
+                _ => {}
+            }
+        }
+        if rule.freq > Frequency::Hourly && rule.by_hour.is_empty() {
+            rule.by_hour = vec![base.hour() as u8];
+        }
+        if rule.freq > Frequency::Minutely && rule.by_minute.is_empty() {
+            rule.by_minute = vec![base.minute() as u8];
+        }
+        if rule.freq > Frequency::Secondly && rule.by_second.is_empty() {
+            rule.by_second = vec![base.second() as u8];
+        }
+        for list in [&mut rule.by_second, &mut rule.by_minute, &mut rule.by_hour] {
+            list.sort_unstable();
+            list.dedup();
+        }
+        let tz = dtstart.timezone();
+        let until = rule.until.and_then(|until| match until {
+            Until::Date(date) => to_utc(&tz, date.and_time(NaiveTime::MIN))
+                .and_then(|midnight| midnight.checked_add_signed(TimeDelta::days(1)))
+                .and_then(pred_dtu),
+            Until::Local(local) => to_utc(&tz, local),
+            Until::Utc(utc) => Some(utc),
+        });
+        Self {
+            rule,
+            base,
+            start: dtstart.to_utc(),
+            tz,
+            until,
+        }
+    }
+
+    fn unit(&self) -> Option<TimeDelta> {
+        match self.rule.freq {
+            Frequency::Hourly => Some(TimeDelta::hours(1)),
+            Frequency::Minutely => Some(TimeDelta::minutes(1)),
+            Frequency::Secondly => Some(TimeDelta::seconds(1)),
+            _ => None,
+        }
+    }
+
+    fn week_start_of(&self, date: NaiveDate) -> NaiveDate {
+        let back = (7 + date.weekday().num_days_from_monday()
+            - self.rule.week_start.num_days_from_monday())
+            % 7;
+        date.checked_sub_days(Days::new(back as u64))
+            .unwrap_or(date)
+    }
+
+    /// `DTSTART` truncated to the unit of a sub-daily frequency
+    fn base_slot(&self) -> NaiveDateTime {
+        let time = match self.rule.freq {
+            Frequency::Hourly => NaiveTime::from_hms_opt(self.base.hour(), 0, 0),
+            Frequency::Minutely => NaiveTime::from_hms_opt(self.base.hour(), self.base.minute(), 0),
+            _ => NaiveTime::from_hms_opt(self.base.hour(), self.base.minute(), self.base.second()),
+        };
+        self.base.date().and_time(time.unwrap_or(NaiveTime::MIN))
+    }
+
+    /// The first local time point covered by the period `index`, and the days it covers.
+    fn period(&self, index: i64) -> Option<(NaiveDateTime, NaiveDate, u32)> {
+        let steps = index.checked_mul(self.rule.interval as i64)?;
+        let date = self.base.date();
+        let (first, len) = match self.rule.freq {
+            Frequency::Yearly => {
+                let year = i32::try_from(date.year() as i64 + steps).ok()?;
+                let first = NaiveDate::from_ymd_opt(year, 1, 1)?;
+                (first, if first.leap_year() { 366 } else { 365 })
+            }
+            Frequency::Monthly => {
+                let month = month_index(date) + steps;
+                let year = i32::try_from(month.div_euclid(12)).ok()?;
+                let first = NaiveDate::from_ymd_opt(year, month.rem_euclid(12) as u32 + 1, 1)?;
+                (first, days_in_month(first))
+            }
+            Frequency::Weekly => (
+                shift_days(self.week_start_of(date), steps.checked_mul(7)?)?,
+                7,
+            ),
+            Frequency::Daily => (shift_days(date, steps)?, 1),
+            _ => {
+                let slot = self
+                    .base_slot()
+                    .checked_add_signed(self.unit()?.checked_mul(steps.try_into().ok()?)?)?;
+                return Some((slot, slot.date(), 1));
+            }
+        };
+        Some((first.and_time(NaiveTime::MIN), first, len))
+    }
+
+    /// The index of the period covering the local time `local`, may be negative.
+    fn period_of(&self, local: NaiveDateTime) -> i64 {
+        let date = self.base.date();
+        let units = match self.rule.freq {
+            Frequency::Yearly => (local.year() - date.year()) as i64,
+            Frequency::Monthly => month_index(local.date()) - month_index(date),
+            Frequency::Weekly => {
+                (self.week_start_of(local.date()) - self.week_start_of(date)).num_days() / 7
+            }
+            Frequency::Daily => (local.date() - date).num_days(),
+            Frequency::Hourly => (local - self.base_slot()).num_hours(),
+            Frequency::Minutely => (local - self.base_slot()).num_minutes(),
+            Frequency::Secondly => (local - self.base_slot()).num_seconds(),
+        };
+        units.div_euclid(self.rule.interval as i64)
+    }
+
+    /// The start of the next day, hour or minute if the sub-daily `slot` is in a rejected one.
+    fn rejected_until(&self, slot: NaiveDateTime) -> Option<NaiveDateTime> {
+        let rule = &self.rule;
+        let rejects = |list: &[u8], value: u32| !list.is_empty() && !list.contains(&(value as u8));
+        let day = slot.date().and_time(NaiveTime::MIN);
+        if !self.day_matches(slot.date()) {
+            return day.checked_add_signed(TimeDelta::days(1));
+        }
+        let hour = day + TimeDelta::hours(slot.hour() as i64);
+        if rule.freq != Frequency::Hourly && rejects(&rule.by_hour, slot.hour()) {
+            return hour.checked_add_signed(TimeDelta::hours(1));
+        }
+        if rule.freq == Frequency::Secondly && rejects(&rule.by_minute, slot.minute()) {
+            return (hour + TimeDelta::minutes(slot.minute() as i64))
+                .checked_add_signed(TimeDelta::minutes(1));
+        }
+        None
+    }
+
+    fn day_matches(&self, day: NaiveDate) -> bool {
+        let rule = &self.rule;
+        if !rule.by_month.is_empty() && !rule.by_month.contains(&(day.month() as u8)) {
+            return false;
+        }
+        if !rule.by_week_no.is_empty() {
+            let (week, weeks) = self.week_number(day);
+            if !rule
+                .by_week_no
+                .iter()
+                .any(|no| position_matches(*no as i64, week, weeks))
+            {
+                return false;
+            }
+        }
+        if !rule.by_year_day.is_empty() {
+            let year_len = if day.leap_year() { 366 } else { 365 };
+            if !rule
+                .by_year_day
+                .iter()
+                .any(|no| position_matches(*no as i64, day.ordinal() as i64, year_len))
+            {
+                return false;
+            }
+        }
+        if !rule.by_month_day.is_empty() {
+            let month_len = days_in_month(day.with_day(1).unwrap_or(day)) as i64;
+            if !rule
+                .by_month_day
+                .iter()
+                .any(|no| position_matches(*no as i64, day.day() as i64, month_len))
+            {
+                return false;
+            }
+        }
+        if !rule.by_day.is_empty() {
+            // the nth weekday counts in the month for monthly rules, and for yearly rules with
+            // `BYMONTH`, in the year for the other yearly rules, it is ignored otherwise
+            let scope = match rule.freq {
+                Frequency::Monthly => Some(true),
+                Frequency::Yearly => Some(!rule.by_month.is_empty()),
+                _ => None,
+            };
+            if !rule.by_day.iter().any(|by_day| {
+                by_day.weekday == day.weekday()
+                    && match (by_day.nth, scope) {
+                        (Some(nth), Some(in_month)) => nth_weekday_matches(day, nth, in_month),
+                        _ => true,
+                    }
+            }) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// The week number of `day` and the number of weeks in its week-numbering year, week 1 is
+    /// the first week with at least 4 days in the year.
+    fn week_number(&self, day: NaiveDate) -> (i64, i64) {
+        let week_one = |year: i32| {
+            let jan_1 = NaiveDate::from_ymd_opt(year, 1, 1).unwrap_or(day);
+            let start = self.week_start_of(jan_1);
+            if (jan_1 - start).num_days() <= 3 {
+                start
+            } else {
+                shift_days(start, 7).unwrap_or(start)
+            }
+        };
+        let mut year = day.year();
+        if day >= week_one(year + 1) {
+            year += 1;
+        } else if day < week_one(year) {
+            year -= 1;
+        }
+        let first = week_one(year);
+        let weeks = (week_one(year + 1) - first).num_days() / 7;
+        ((day - first).num_days() / 7 + 1, weeks)
+    }
+
+    fn times(&self, slot: NaiveDateTime) -> Vec<NaiveTime> {
+        let rule = &self.rule;
+        let check = |list: &[u8], value: u32| list.is_empty() || list.contains(&(value as u8));
+        let with_default = |list: &[u8], value: u32| {
+            if list.is_empty() {
+                vec![value as u8]
+            } else {
+                list.to_vec()
+            }
+        };
+        let (hours, minutes, seconds) = match rule.freq {
+            Frequency::Hourly => {
+                if !check(&rule.by_hour, slot.hour()) {
+                    return vec![];
+                }
+                (
+                    vec![slot.hour() as u8],
+                    with_default(&rule.by_minute, self.base.minute()),
+                    with_default(&rule.by_second, self.base.second()),
+                )
+            }
+            Frequency::Minutely => {
+                if !check(&rule.by_hour, slot.hour()) || !check(&rule.by_minute, slot.minute()) {
+                    return vec![];
+                }
+                (
+                    vec![slot.hour() as u8],
+                    vec![slot.minute() as u8],
+                    with_default(&rule.by_second, self.base.second()),
+                )
+            }
+            Frequency::Secondly => {
+                if !check(&rule.by_hour, slot.hour())
+                    || !check(&rule.by_minute, slot.minute())
+                    || !check(&rule.by_second, slot.second())
+                {
+                    return vec![];
+                }
+                return vec![slot.time()];
+            }
+            _ => (
+                rule.by_hour.clone(),
+                rule.by_minute.clone(),
+                rule.by_second.clone(),
+            ),
+        };
+        let mut times = vec![];
+        for hour in &hours {
+            for minute in &minutes {
+                for second in &seconds {
+                    if let Some(time) =
+                        NaiveTime::from_hms_opt(*hour as u32, *minute as u32, *second as u32)
+                    {
+                        times.push(time);
+                    }
+                }
+            }
+        }
+        times
+    }
+
+    /// All the local occurrences of the period `index`, sorted, before `BYSETPOS` is applied.
+    fn expand(&self, index: i64) -> Option<Vec<NaiveDateTime>> {
+        let (slot, first, len) = self.period(index)?;
+        let mut occurrences = vec![];
+        for day in first.iter_days().take(len as usize) {
+            if !self.day_matches(day) {
+                continue;
+            }
+            let slot = if self.unit().is_some() {
+                slot
+            } else {
+                day.and_time(NaiveTime::MIN)
+            };
+            occurrences.extend(self.times(slot).into_iter().map(|time| day.and_time(time)));
+        }
+        occurrences.sort_unstable();
+        if !self.rule.by_set_pos.is_empty() {
+            let len = occurrences.len() as i64;
+            let mut picked: Vec<_> = self
+                .rule
+                .by_set_pos
+                .iter()
+                .filter_map(|pos| {
+                    let pos = *pos as i64;
+                    let index = if pos > 0 { pos - 1 } else { len + pos };
+                    (0..len)
+                        .contains(&index)
+                        .then(|| occurrences[index as usize])
+                })
+                .collect();
+            picked.sort_unstable();
+            picked.dedup();
+            occurrences = picked;
+        }
+        Some(occurrences)
+    }
+}
+
+fn month_index(date: NaiveDate) -> i64 {
+    date.year() as i64 * 12 + date.month0() as i64
+}
+
+fn days_in_month(first: NaiveDate) -> u32 {
+    let next = first
+        .checked_add_months(chrono::Months::new(1))
+        .unwrap_or(first);
+    (next - first).num_days() as u32
+}
+
+fn shift_days(date: NaiveDate, days: i64) -> Option<NaiveDate> {
+    if days >= 0 {
+        date.checked_add_days(Days::new(days as u64))
+    } else {
+        date.checked_sub_days(Days::new(days.unsigned_abs()))
+    }
+}
+
+/// whether the 1-based `position` out of `len` is the `wanted` one, negative counts from the end
+fn position_matches(wanted: i64, position: i64, len: i64) -> bool {
+    if wanted > 0 {
+        wanted == position
+    } else {
+        len + wanted + 1 == position
+    }
+}
+
+fn nth_weekday_matches(day: NaiveDate, nth: i8, in_month: bool) -> bool {
+    let (position, len) = if in_month {
+        let first = day.with_day(1).unwrap_or(day);
+        (day.day() as i64, days_in_month(first) as i64)
+    } else {
+        let len = if day.leap_year() { 366 } else { 365 };
+        (day.ordinal() as i64, len)
+    };
+    let nth = nth as i64;
+    if nth > 0 {
+        (position - 1) / 7 + 1 == nth
+    } else {
+        (len - position) / 7 + 1 == -nth
+    }
+}
+
+/// The iteration state of a single rule.
+#[derive(Debug, Clone)]
+struct RuleCursor {
+    rule: Arc<BoundRule>,
+    period: i64,
+    pending: VecDeque<Dtu>,
+    emitted: u32,
+    started: bool,
+    exhausted: bool,
+}
+
+impl RuleCursor {
+    fn new(rule: Arc<BoundRule>) -> Self {
+        Self {
+            rule,
+            period: 0,
+            pending: VecDeque::new(),
+            emitted: 0,
+            started: false,
+            exhausted: false,
+        }
+    }
+
+    fn fill(&mut self) {
+        let rule = self.rule.clone();
+        let search_start = rule.period(self.period).map(|(slot, ..)| slot);
+        let mut budget = MAX_SEARCH_PERIODS;
+        while self.pending.is_empty() && !self.exhausted {
+            let Some((slot, ..)) = rule.period(self.period) else {
+                self.exhausted = true;
+                return;
+            };
+            if search_start.is_some_and(|start| (slot - start).num_days() > MAX_SEARCH_DAYS) {
+                self.exhausted = true;
+                return;
+            }
+            if budget == 0 {
+                self.exhausted = true;
+                return;
+            }
+            budget -= 1;
+            // a sub-daily period in a rejected day, hour or minute: jump to the next one at once
+            if let Some(unit) = rule.unit()
+                && let Some(boundary) = rule.rejected_until(slot)
+            {
+                let step = unit * rule.rule.interval as i32;
+                let distance = (boundary - rule.base_slot()).num_seconds();
+                let step = step.num_seconds();
+                let skip = (distance + step - 1).div_euclid(step);
+                self.period = skip.max(self.period + 1);
+                continue;
+            }
+            let Some(occurrences) = rule.expand(self.period) else {
+                self.exhausted = true;
+                return;
+            };
+            self.period += 1;
+            let mut occurrences: Vec<Dtu> = occurrences
+                .into_iter()
+                .filter_map(|local| to_utc(&rule.tz, local))
+                .filter(|utc| *utc >= rule.start)
+                .collect();
+            occurrences.sort_unstable();
+            occurrences.dedup();
+            for occurrence in occurrences {
+                if rule.until.is_some_and(|until| occurrence > until) {
+                    self.exhausted = true;
+                    break;
+                }
+                self.pending.push_back(occurrence);
+            }
+        }
+    }
+
+    fn peek(&mut self) -> Option<Dtu> {
+        if self
+            .rule
+            .rule
+            .count
+            .is_some_and(|count| self.emitted >= count)
+        {
+            return None;
+        }
+        self.fill();
+        let next = *self.pending.front()?;
+        if !self.started && next != self.rule.start {
+            // `DTSTART` always counts as the first occurrence
+            self.started = true;
+            self.emitted += 1;
+            return self.peek();
+        }
+        Some(next)
+    }
+
+    fn advance(&mut self) {
+        if self.peek().is_some() {
+            self.started = true;
+            self.pending.pop_front();
+            self.emitted += 1;
+        }
+    }
+
+    /// drop every occurrence up to and including `dtu`
+    fn forward_to(&mut self, dtu: Dtu) {
+        if self.rule.rule.count.is_none() {
+            let local = dtu.with_timezone(&self.rule.tz).naive_local();
+            let period = self.rule.period_of(local);
+            if period > self.period {
+                self.period = period;
+                self.pending.clear();
+                self.started = true;
+            }
+        }
+        while self.peek().is_some_and(|next| next <= dtu) {
+            self.advance();
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Definition {
+    dtstart: DateTime<Tz>,
+    rules: Vec<RecurrenceRule>,
+    bound: Vec<Arc<BoundRule>>,
+    rdates: BTreeSet<Dtu>,
+    exdates: BTreeSet<Dtu>,
+}
+
+/// A recurrence set: `DTSTART`, `RRULE`s, `RDATE`s and `EXDATE`s, as defined by
+/// [RFC 5545](https://www.rfc-editor.org/rfc/rfc5545#section-3.8.5).
+///
+/// # Parse
+/// ```
+/// # use tsuki_scheduler::prelude::*;
+/// let mut schedule: RRule = "DTSTART;TZID=America/New_York:19970902T090000\n\
+///     RRULE:FREQ=MONTHLY;BYDAY=2TU;COUNT=10\n\
+///     EXDATE;TZID=America/New_York:19970909T090000"
+///     .parse()
+///     .expect("valid rrule");
+/// // DTSTART is always the first occurrence, the first second tuesday is excluded
+/// assert_eq!(schedule.next().unwrap().to_rfc3339(), "1997-09-02T13:00:00+00:00");
+/// assert_eq!(schedule.next().unwrap().to_rfc3339(), "1997-10-14T13:00:00+00:00");
+/// ```
+///
+/// Time points written without a time zone (floating time) are read in the time zone of
+/// `DTSTART`, a floating `DTSTART` is read in UTC.
+///
+/// # Performance
+/// The occurrences are computed period by period, and [`forward_to`](Schedule::forward_to)
+/// jumps to the period of its argument directly. A rule with a `COUNT` can't jump, since all
+/// the previous occurrences must be counted, it is still bounded by the count though.
+///
+/// # Time set
+/// An [`RRule`] is also a [`TimeSet`] containing its occurrences (from `DTSTART`, whatever the
+/// state of the schedule is), so it can be used in [`Filtered`](super::Filtered).
+#[derive(Debug, Clone)]
+pub struct RRule {
+    definition: Arc<Definition>,
+    cursors: Vec<RuleCursor>,
+    /// every time point up to this one has been consumed
+    floor: Option<Dtu>,
+    next: Option<Option<Dtu>>,
+}
+
+impl RRule {
+    /// A recurrence set with a single rule.
+    pub fn new(dtstart: DateTime<Tz>, rule: RecurrenceRule) -> Self {
+        Self::from_definition(Definition {
+            dtstart,
+            rules: vec![],
+            bound: vec![],
+            rdates: BTreeSet::new(),
+            exdates: BTreeSet::new(),
+        })
+        .with_rule(rule)
+    }
+    /// A recurrence set containing only `DTSTART`, add rules and dates with the `with_*` methods.
+    pub fn from_dtstart(dtstart: DateTime<Tz>) -> Self {
+        Self::from_definition(Definition {
+            dtstart,
+            rules: vec![],
+            bound: vec![],
+            rdates: BTreeSet::new(),
+            exdates: BTreeSet::new(),
+        })
+    }
+    fn from_definition(definition: Definition) -> Self {
+        let cursors = definition
+            .bound
+            .iter()
+            .cloned()
+            .map(RuleCursor::new)
+            .collect();
+        Self {
+            definition: Arc::new(definition),
+            cursors,
+            floor: None,
+            next: None,
+        }
+    }
+    fn edit(self, edit: impl FnOnce(&mut Definition)) -> Self {
+        let mut definition = Arc::unwrap_or_clone(self.definition);
+        edit(&mut definition);
+        Self::from_definition(definition)
+    }
+    /// add a rule
+    pub fn with_rule(self, rule: RecurrenceRule) -> Self {
+        self.edit(|definition| {
+            definition
+                .bound
+                .push(Arc::new(BoundRule::new(&rule, &definition.dtstart)));
+            definition.rules.push(rule);
+        })
+    }
+    /// add extra occurrences
+    pub fn with_rdates<I: IntoIterator<Item = Dtu>>(self, rdates: I) -> Self {
+        self.edit(|definition| definition.rdates.extend(rdates))
+    }
+    /// exclude occurrences
+    pub fn with_exdates<I: IntoIterator<Item = Dtu>>(self, exdates: I) -> Self {
+        self.edit(|definition| definition.exdates.extend(exdates))
+    }
+    pub fn dtstart(&self) -> &DateTime<Tz> {
+        &self.definition.dtstart
+    }
+    pub fn rules(&self) -> &[RecurrenceRule] {
+        &self.definition.rules
+    }
+    pub fn rdates(&self) -> &BTreeSet<Dtu> {
+        &self.definition.rdates
+    }
+    pub fn exdates(&self) -> &BTreeSet<Dtu> {
+        &self.definition.exdates
+    }
+
+    fn compute_next(&mut self) -> Option<Dtu> {
+        let definition = self.definition.clone();
+        loop {
+            let floor = self.floor;
+            let after = |dtu: &Dtu| floor.is_none_or(|floor| *dtu > floor);
+            let dtstart = Some(definition.dtstart.to_utc()).filter(after);
+            let rdate = match floor {
+                Some(floor) => definition
+                    .rdates
+                    .range((Bound::Excluded(floor), Bound::Unbounded))
+                    .next()
+                    .copied(),
+                None => definition.rdates.first().copied(),
+            };
+            let mut next = dtstart.into_iter().chain(rdate).min();
+            for cursor in &mut self.cursors {
+                if let Some(floor) = floor {
+                    cursor.forward_to(floor);
+                }
+                if let Some(peek) = cursor.peek() {
+                    next = Some(next.map_or(peek, |next| next.min(peek)));
+                }
+            }
+            let next = next?;
+            if definition.exdates.contains(&next) {
+                self.floor = Some(next);
+                continue;
+            }
+            return Some(next);
+        }
+    }
+}
+
+impl Schedule for RRule {
+    fn peek_next(&mut self) -> Option<Dtu> {
+        if let Some(next) = self.next {
+            return next;
+        }
+        let next = self.compute_next();
+        self.next = Some(next);
+        next
+    }
+
+    fn next(&mut self) -> Option<Dtu> {
+        let next = self.peek_next()?;
+        self.floor = Some(next);
+        self.next = None;
+        Some(next)
+    }
+
+    fn forward_to(&mut self, dtu: Dtu) {
+        if self.floor.is_none_or(|floor| floor < dtu) {
+            self.floor = Some(dtu);
+            self.next = None;
+        }
+    }
+}
+
+impl TimeSet for RRule {
+    fn contains(&self, dtu: Dtu) -> bool {
+        self.lower_bound(dtu) == LowerBound::At(dtu)
+    }
+    fn lower_bound(&self, from: Dtu) -> LowerBound {
+        let mut fresh = Self::from_definition(self.definition.as_ref().clone());
+        if let Some(floor) = pred_dtu(from) {
+            fresh.forward_to(floor);
+        }
+        match fresh.peek_next() {
+            Some(next) => LowerBound::At(next),
+            None => LowerBound::Never,
+        }
+    }
+}
+
+/// The value of a `TZID` parameter.
+pub(crate) fn parse_tz(tzid: &str) -> Result<Tz, RRuleError> {
+    tzid.trim_matches('"')
+        .parse()
+        .map_err(|_| RRuleError::UnknownTimeZone(tzid.to_string()))
+}
+
+/// The parameters of a content line, with upper case names.
+pub(crate) type Params = Vec<(String, String)>;
+
+/// A content line `NAME;PARAM=VALUE:VALUE`, split into its name, parameters and value.
+///
+/// Parameter values may be quoted, so that they can contain `:` and `;`.
+pub(crate) fn split_line(line: &str) -> Result<(String, Params, &str), RRuleError> {
+    let invalid_line = || RRuleError::InvalidLine(line.to_string());
+    let mut quoted = false;
+    let mut parts = vec![];
+    let mut part_start = 0;
+    let mut value_start = None;
+    for (index, char) in line.char_indices() {
+        match char {
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                parts.push(&line[part_start..index]);
+                part_start = index + 1;
+            }
+            ':' if !quoted => {
+                parts.push(&line[part_start..index]);
+                value_start = Some(index + 1);
+                break;
+            }
+            _ => {}
+        }
+    }
+    let value = &line[value_start.ok_or_else(invalid_line)?..];
+    let mut parts = parts.into_iter();
+    let name = parts.next().unwrap_or_default().trim().to_ascii_uppercase();
+    let params = parts
+        .map(|param| {
+            param
+                .split_once('=')
+                .map(|(key, value)| {
+                    (
+                        key.trim().to_ascii_uppercase(),
+                        value.trim().trim_matches('"').to_string(),
+                    )
+                })
+                .ok_or_else(invalid_line)
+        })
+        .collect::<Result<_, _>>()?;
+    Ok((name, params, value))
+}
+
+pub(crate) fn param<'a>(params: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    params
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.as_str())
+}
+
+/// Parse a `DTSTART` value and its parameters.
+pub(crate) fn parse_dtstart(
+    params: &[(String, String)],
+    value: &str,
+) -> Result<DateTime<Tz>, RRuleError> {
+    let date = DateValue::parse(value).ok_or_else(|| invalid("DTSTART", value))?;
+    let tz = match param(params, "TZID") {
+        Some(tzid) => parse_tz(tzid)?,
+        None => Tz::UTC,
+    };
+    let utc = date.resolve(&tz).ok_or_else(|| invalid("DTSTART", value))?;
+    Ok(utc.with_timezone(&tz))
+}
+
+/// Parse a comma separated list of `DATE` / `DATE-TIME` (`RDATE`, `EXDATE`), floating values are
+/// read in `tz`.
+pub(crate) fn parse_dates(
+    name: &str,
+    params: &[(String, String)],
+    value: &str,
+    tz: &Tz,
+) -> Result<Vec<Dtu>, RRuleError> {
+    if param(params, "VALUE").is_some_and(|kind| kind.eq_ignore_ascii_case("PERIOD")) {
+        return Err(RRuleError::Unsupported(format!("{name} with VALUE=PERIOD")));
+    }
+    let tz = match param(params, "TZID") {
+        Some(tzid) => parse_tz(tzid)?,
+        None => *tz,
+    };
+    value
+        .split(',')
+        .map(|item| {
+            DateValue::parse(item)
+                .and_then(|date| date.resolve(&tz))
+                .ok_or_else(|| invalid(name, item))
+        })
+        .collect()
+}
+
+/// Join the folded lines (a line starting with a space or a tab continues the previous one).
+pub(crate) fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for line in text.lines() {
+        let line = line.trim_end_matches('\r');
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ if line.trim().is_empty() => {}
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+impl FromStr for RRule {
+    type Err = RRuleError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines = unfold(s);
+        let mut dtstart = None;
+        let mut rules = vec![];
+        let mut dates = vec![];
+        for line in &lines {
+            if line.trim_start().to_ascii_uppercase().starts_with("FREQ=") {
+                rules.push(line.parse::<RecurrenceRule>()?);
+                continue;
+            }
+            let (name, params, value) = split_line(line)?;
+            match name.as_str() {
+                "DTSTART" => dtstart = Some(parse_dtstart(&params, value)?),
+                "RRULE" => rules.push(value.parse()?),
+                "RDATE" | "EXDATE" => dates.push((name, params, value)),
+                _ => return Err(RRuleError::Unsupported(format!("property {name}"))),
+            }
+        }
+        let dtstart: DateTime<Tz> = dtstart.ok_or(RRuleError::MissingDtStart)?;
+        let tz = dtstart.timezone();
+        let mut rrule = RRule::from_dtstart(dtstart);
+        for rule in rules {
+            rrule = rrule.with_rule(rule);
+        }
+        for (name, params, value) in dates {
+            let dates = parse_dates(&name, &params, value, &tz)?;
+            rrule = if name == "RDATE" {
+                rrule.with_rdates(dates)
+            } else {
+                rrule.with_exdates(dates)
+            };
+        }
+        Ok(rrule)
+    }
+}
+
+fn write_dates(f: &mut fmt::Formatter<'_>, name: &str, dates: &BTreeSet<Dtu>) -> fmt::Result {
+    if dates.is_empty() {
+        return Ok(());
+    }
+    write!(f, "\n{name}:")?;
+    for (index, date) in dates.iter().enumerate() {
+        if index > 0 {
+            f.write_str(",")?;
+        }
+        write!(f, "{}", Until::Utc(*date))?;
+    }
+    Ok(())
+}
+
+/// Print the recurrence set as content lines, `RDATE` and `EXDATE` are written in UTC.
+impl fmt::Display for RRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let dtstart = &self.definition.dtstart;
+        if dtstart.timezone() == Tz::UTC {
+            write!(f, "DTSTART:{}", Until::Utc(dtstart.to_utc()))?;
+        } else {
+            write!(
+                f,
+                "DTSTART;TZID={}:{}",
+                dtstart.timezone().name(),
+                dtstart.naive_local().format(DATE_TIME_FORMAT)
+            )?;
+        }
+        for rule in &self.definition.rules {
+            write!(f, "\nRRULE:{rule}")?;
+        }
+        write_dates(f, "RDATE", &self.definition.rdates)?;
+        write_dates(f, "EXDATE", &self.definition.exdates)
+    }
+}
@@ -0,0 +1,720 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Timelike, Weekday};
+use chrono_tz::Tz;
+
+use super::{IntoSchedule, Period, Schedule, calendar_period::to_utc};
+use crate::{
+    Dtu,
+    clock::{Clock, SystemClock},
+    timeset::{LowerBound, TimeSet},
+};
+
+/// Error when parsing a systemd calendar event or time span.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SystemdError {
+    Empty,
+    /// an invalid component of a calendar event, e.g. the month in `*-13-01`
+    InvalidValue {
+        name: &'static str,
+        value: String,
+    },
+    UnknownTimeZone(String),
+    InvalidTimeSpan(String),
+}
+
+impl fmt::Display for SystemdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "empty calendar event"),
+            Self::InvalidValue { name, value } => write!(f, "invalid {name}: {value:?}"),
+            Self::UnknownTimeZone(tz) => write!(f, "unknown time zone {tz:?}"),
+            Self::InvalidTimeSpan(span) => write!(f, "invalid time span {span:?}"),
+        }
+    }
+}
+
+impl std::error::Error for SystemdError {}
+
+fn invalid(name: &'static str, value: &str) -> SystemdError {
+    SystemdError::InvalidValue {
+        name,
+        value: value.to_string(),
+    }
+}
+
+/// `start, start + step, ...` up to `end`, both inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Item {
+    start: u32,
+    end: u32,
+    step: u32,
+}
+
+/// The values allowed for one component, e.g. `1,15` or `0..23/2` for the hours.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Values {
+    items: Vec<Item>,
+}
+
+impl Values {
+    /// Parse `*`, `a`, `a..b`, `a/n`, `a..b/n`, `*/n` and comma separated lists of them.
+    ///
+    /// With `backward` (the days after `~`), `a/n` goes toward the end of the month: `a`, `a - n`,
+    /// down to `1`.
+    fn parse(
+        name: &'static str,
+        text: &str,
+        min: u32,
+        max: u32,
+        backward: bool,
+    ) -> Result<Self, SystemdError> {
+        let number = |value: &str| -> Result<u32, SystemdError> {
+            value
+                .parse::<u32>()
+                .ok()
+                .filter(|value| (min..=max).contains(value))
+                .ok_or_else(|| invalid(name, text))
+        };
+        let mut items = vec![];
+        for item in text.split(',') {
+            let (range, step) = match item.split_once('/') {
+                Some((range, step)) => {
+                    let step = step.parse::<u32>().ok().filter(|step| *step > 0);
+                    (range, Some(step.ok_or_else(|| invalid(name, text))?))
+                }
+                None => (item, None),
+            };
+            let (start, end) = match range.split_once("..") {
+                Some((start, end)) => (number(start)?, number(end)?),
+                None if range == "*" => (min, max),
+                None => {
+                    let start = number(range)?;
+                    (start, if step.is_some() { max } else { start })
+                }
+            };
+            if start > end {
+                return Err(invalid(name, text));
+            }
+            let step_only = step.is_some() && !range.contains("..") && range != "*";
+            let step = step.unwrap_or(1);
+            items.push(if backward && step_only {
+                Item {
+                    start: (start - 1) % step + 1,
+                    end: start,
+                    step,
+                }
+            } else {
+                Item { start, end, step }
+            });
+        }
+        Ok(Self { items })
+    }
+    fn contains(&self, value: u32) -> bool {
+        self.items.iter().any(|item| {
+            (item.start..=item.end).contains(&value)
+                && (value - item.start).is_multiple_of(item.step)
+        })
+    }
+    /// the smallest allowed value at or after `from`
+    fn first_from(&self, from: u32) -> Option<u32> {
+        self.items
+            .iter()
+            .filter_map(|item| {
+                let value = if from <= item.start {
+                    item.start
+                } else {
+                    let steps = (from - item.start).div_ceil(item.step);
+                    item.start.checked_add(steps.checked_mul(item.step)?)?
+                };
+                (value <= item.end).then_some(value)
+            })
+            .min()
+    }
+}
+
+/// The time zone of a calendar event, the local one when none is given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Zone {
+    Local,
+    Tz(Tz),
+}
+
+impl Zone {
+    fn to_local(self, dtu: Dtu) -> NaiveDateTime {
+        match self {
+            Self::Local => dtu.with_timezone(&chrono::Local).naive_local(),
+            Self::Tz(tz) => dtu.with_timezone(&tz).naive_local(),
+        }
+    }
+    fn to_utc(self, local: NaiveDateTime) -> Option<Dtu> {
+        match self {
+            Self::Local => to_utc(&chrono::Local, local),
+            Self::Tz(tz) => to_utc(&tz, local),
+        }
+    }
+}
+
+const MIN_YEAR: u32 = 1970;
+const MAX_YEAR: u32 = 9999;
+
+/// Years scanned without a match before the event is considered to never happen again.
+///
+/// The gregorian calendar (months, days and weekdays) repeats every 400 years.
+const MAX_SCANNED_YEARS: u32 = 400;
+
+/// A systemd calendar event, the value of `OnCalendar=` in a timer unit, see
+/// [systemd.time(7)](https://www.freedesktop.org/software/systemd/man/latest/systemd.time.html#Calendar%20Events).
+///
+/// The format is `[WEEKDAYS] [YEAR-]MONTH-DAY [HOUR:MINUTE[:SECOND]] [TIME ZONE]`:
+/// - every component accepts `*`, lists `a,b`, ranges `a..b` and repetitions `a/n`,
+/// - `~` instead of the last `-` counts the days from the end of the month: `*-*~01` is the
+///   last day of every month, `Mon *-05~07/1` the last monday of May,
+/// - the shorthands `minutely`, `hourly`, `daily`, `weekly`, `monthly`, `yearly` (`annually`),
+///   `quarterly` and `semiannually` are accepted,
+/// - the time zone is an IANA name (e.g. `Europe/Paris`) or `UTC`, the local time zone is used
+///   when it is omitted.
+///
+/// It is a [`TimeSet`] of the matching seconds, use [`schedule`](CalendarEvent::schedule) or
+/// [`IntoSchedule`] for the [`OnCalendar`] schedule.
+///
+/// # Example
+/// ```
+/// # use tsuki_scheduler::prelude::*;
+/// use chrono::{TimeZone, Utc};
+/// let event: CalendarEvent = "Mon..Fri *-*-* 09:00:00 Europe/Paris".parse().unwrap();
+/// let friday = Utc.with_ymd_and_hms(2025, 1, 3, 12, 0, 0).unwrap();
+/// let mut schedule = event.schedule_after(friday);
+/// // the next monday, at 09:00 in Paris
+/// assert_eq!(schedule.next(), Some(Utc.with_ymd_and_hms(2025, 1, 6, 8, 0, 0).unwrap()));
+///
+/// let last_day: CalendarEvent = "*-*~01 23:00 UTC".parse().unwrap();
+/// assert!(last_day.contains(Utc.with_ymd_and_hms(2024, 2, 29, 23, 0, 0).unwrap()));
+/// ```
+///
+/// # Time zone transitions
+/// A local time falling into a gap is moved forward by the length of the gap, an ambiguous
+/// local time only happens once, at its earliest instant.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CalendarEvent {
+    weekdays: Option<[bool; 7]>,
+    years: Values,
+    months: Values,
+    days: Values,
+    /// whether the days count from the end of the month (`~`)
+    backward: bool,
+    hours: Values,
+    minutes: Values,
+    seconds: Values,
+    zone: Zone,
+}
+
+fn parse_weekday(name: &str, text: &str) -> Result<Weekday, SystemdError> {
+    let weekday = match name.to_ascii_lowercase().as_str() {
+        "mon" | "monday" => Weekday::Mon,
+        "tue" | "tuesday" => Weekday::Tue,
+        "wed" | "wednesday" => Weekday::Wed,
+        "thu" | "thursday" => Weekday::Thu,
+        "fri" | "friday" => Weekday::Fri,
+        "sat" | "saturday" => Weekday::Sat,
+        "sun" | "sunday" => Weekday::Sun,
+        _ => return Err(invalid("weekday", text)),
+    };
+    Ok(weekday)
+}
+
+fn parse_weekdays(text: &str) -> Result<[bool; 7], SystemdError> {
+    let mut weekdays = [false; 7];
+    for item in text.split(',').filter(|item| !item.is_empty()) {
+        match item.split_once("..").or_else(|| item.split_once('-')) {
+            Some((from, to)) => {
+                let mut weekday = parse_weekday(from, text)?;
+                let to = parse_weekday(to, text)?;
+                loop {
+                    weekdays[weekday.num_days_from_monday() as usize] = true;
+                    if weekday == to {
+                        break;
+                    }
+                    weekday = weekday.succ();
+                }
+            }
+            None => weekdays[parse_weekday(item, text)?.num_days_from_monday() as usize] = true,
+        }
+    }
+    Ok(weekdays)
+}
+
+/// the expression of a shorthand, in the normalized form
+fn shorthand(name: &str) -> Option<&'static str> {
+    let expanded = match name.to_ascii_lowercase().as_str() {
+        "minutely" => "*-*-* *:*:00",
+        "hourly" => "*-*-* *:00:00",
+        "daily" => "*-*-* 00:00:00",
+        "weekly" => "Mon *-*-* 00:00:00",
+        "monthly" => "*-*-01 00:00:00",
+        "yearly" | "annually" => "*-01-01 00:00:00",
+        "quarterly" => "*-01,04,07,10-01 00:00:00",
+        "semiannually" => "*-01,07-01 00:00:00",
+        _ => return None,
+    };
+    Some(expanded)
+}
+
+impl FromStr for CalendarEvent {
+    type Err = SystemdError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tokens: Vec<&str> = s.split_whitespace().collect();
+        let zone = match tokens.last().map(|last| (last, last.parse::<Tz>())) {
+            Some((_, Ok(tz))) => {
+                tokens.pop();
+                Zone::Tz(tz)
+            }
+            Some((last, Err(_)))
+                if last.contains('/') && last.starts_with(|c: char| c.is_ascii_uppercase()) =>
+            {
+                return Err(SystemdError::UnknownTimeZone(last.to_string()));
+            }
+            _ => Zone::Local,
+        };
+        let expanded;
+        if let [name] = tokens.as_slice()
+            && let Some(expression) = shorthand(name)
+        {
+            expanded = expression;
+            tokens = expanded.split_whitespace().collect();
+        }
+        if tokens.is_empty() {
+            return Err(SystemdError::Empty);
+        }
+        let mut tokens = tokens.into_iter().peekable();
+        let weekdays = match tokens.peek() {
+            Some(first) if first.starts_with(|c: char| c.is_ascii_alphabetic()) => {
+                Some(parse_weekdays(tokens.next().unwrap_or_default())?)
+            }
+            _ => None,
+        };
+        let mut date = None;
+        let mut time = None;
+        for token in tokens {
+            if token.contains(':') && time.is_none() {
+                time = Some(token);
+            } else if date.is_none() && time.is_none() {
+                date = Some(token);
+            } else {
+                return Err(invalid("calendar event", s));
+            }
+        }
+
+        // [YEAR-]MONTH(-|~)DAY
+        let date = date.unwrap_or("*-*-*");
+        let (head, day, backward) = match date.rsplit_once('~') {
+            Some((head, day)) => (head, day, true),
+            None => match date.rsplit_once('-') {
+                Some((head, day)) => (head, day, false),
+                None => return Err(invalid("date", date)),
+            },
+        };
+        let (year, month) = match head.split_once('-') {
+            Some((year, month)) => (year, month),
+            None => ("*", head),
+        };
+        let years = Values::parse("year", year, MIN_YEAR, MAX_YEAR, false)?;
+        let months = Values::parse("month", month, 1, 12, false)?;
+        let days = Values::parse("day", day, 1, 31, backward)?;
+
+        // HOUR:MINUTE[:SECOND]
+        let time = time.unwrap_or("00:00:00");
+        let mut parts = time.split(':');
+        let (Some(hour), Some(minute), second, None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid("time", time));
+        };
+        let hours = Values::parse("hour", hour, 0, 23, false)?;
+        let minutes = Values::parse("minute", minute, 0, 59, false)?;
+        let seconds = Values::parse("second", second.unwrap_or("00"), 0, 59, false)?;
+
+        Ok(Self {
+            weekdays,
+            years,
+            months,
+            days,
+            backward,
+            hours,
+            minutes,
+            seconds,
+            zone,
+        })
+    }
+}
+
+impl CalendarEvent {
+    /// the time zone, `None` for the local one
+    pub fn time_zone(&self) -> Option<Tz> {
+        match self.zone {
+            Zone::Local => None,
+            Zone::Tz(tz) => Some(tz),
+        }
+    }
+    /// The schedule of the matching seconds after now.
+    pub fn schedule(self) -> OnCalendar {
+        self.schedule_with_clock(&SystemClock)
+    }
+    /// Like [`CalendarEvent::schedule`], after the current time of `clock`.
+    pub fn schedule_with_clock(self, clock: &dyn Clock) -> OnCalendar {
+        self.schedule_after(clock.now())
+    }
+    /// The schedule of the matching seconds after `dtu`.
+    pub fn schedule_after(self, dtu: Dtu) -> OnCalendar {
+        let next = self.next_after(dtu);
+        OnCalendar { event: self, next }
+    }
+    /// The first matching second strictly after `dtu`.
+    pub fn next_after(&self, dtu: Dtu) -> Option<Dtu> {
+        let from = dtu.with_nanosecond(0)? + TimeDelta::seconds(1);
+        self.next_at_or_after(from)
+    }
+    /// The first matching second at or after `dtu`.
+    fn next_at_or_after(&self, dtu: Dtu) -> Option<Dtu> {
+        let from = match dtu.nanosecond() {
+            0 => dtu,
+            _ => dtu.with_nanosecond(0)? + TimeDelta::seconds(1),
+        };
+        let mut local_from = self.zone.to_local(from);
+        loop {
+            let local = self.next_local(local_from)?;
+            let utc = self.zone.to_utc(local)?;
+            if utc >= from {
+                return Some(utc);
+            }
+            // the earliest instant of an ambiguous local time, already passed
+            local_from = local + TimeDelta::seconds(1);
+        }
+    }
+
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        let day = if self.backward {
+            days_in_month(date.year(), date.month()) - date.day() + 1
+        } else {
+            date.day()
+        };
+        self.days.contains(day)
+            && self
+                .weekdays
+                .is_none_or(|weekdays| weekdays[date.weekday().num_days_from_monday() as usize])
+    }
+
+    /// the first time of a day at or after `from`
+    fn first_time(&self, from: NaiveTime) -> Option<NaiveTime> {
+        let (from_hour, from_minute, from_second) = (from.hour(), from.minute(), from.second());
+        let mut hour = self.hours.first_from(from_hour)?;
+        loop {
+            let minute_from = if hour == from_hour { from_minute } else { 0 };
+            let mut minute = self.minutes.first_from(minute_from);
+            while let Some(current) = minute {
+                let second_from = if hour == from_hour && current == from_minute {
+                    from_second
+                } else {
+                    0
+                };
+                if let Some(second) = self.seconds.first_from(second_from) {
+                    return NaiveTime::from_hms_opt(hour, current, second);
+                }
+                minute = self.minutes.first_from(current + 1);
+            }
+            hour = self.hours.first_from(hour + 1)?;
+        }
+    }
+
+    /// the first matching local time at or after `from`
+    fn next_local(&self, from: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut from = Some(from);
+        let mut year = self.years.first_from(u32::try_from(from?.year()).ok()?)?;
+        for _ in 0..MAX_SCANNED_YEARS {
+            if year > MAX_YEAR {
+                return None;
+            }
+            let start = from.filter(|from| from.year() as u32 == year);
+            let mut month = self.months.first_from(start.map_or(1, |from| from.month()));
+            while let Some(current) = month {
+                let first_day = start
+                    .filter(|from| from.month() == current)
+                    .map_or(1, |from| from.day());
+                for day in first_day..=days_in_month(year as i32, current) {
+                    let date = NaiveDate::from_ymd_opt(year as i32, current, day)?;
+                    if !self.matches_date(date) {
+                        continue;
+                    }
+                    let time_from = start
+                        .filter(|from| from.date() == date)
+                        .map_or(NaiveTime::MIN, |from| from.time());
+                    if let Some(time) = self.first_time(time_from) {
+                        return Some(date.and_time(time));
+                    }
+                }
+                month = self.months.first_from(current + 1);
+            }
+            from = None;
+            year = self.years.first_from(year + 1)?;
+        }
+        None
+    }
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    let (next_year, next_month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    NaiveDate::from_ymd_opt(next_year, next_month, 1)
+        .and_then(|first| first.pred_opt())
+        .map_or(31, |last| last.day())
+}
+
+impl TimeSet for CalendarEvent {
+    fn contains(&self, dtu: Dtu) -> bool {
+        self.next_at_or_after(dtu) == Some(dtu)
+    }
+    fn lower_bound(&self, from: Dtu) -> LowerBound {
+        match self.next_at_or_after(from) {
+            Some(next) => LowerBound::At(next),
+            None => LowerBound::Never,
+        }
+    }
+}
+
+/// A schedule running at every second matching a [`CalendarEvent`], like a systemd timer with
+/// `OnCalendar=`.
+///
+/// # Example
+/// ```
+/// # use tsuki_scheduler::prelude::*;
+/// let schedule = OnCalendar::from_expr("*-*-01 00:00 UTC").unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct OnCalendar {
+    event: CalendarEvent,
+    next: Option<Dtu>,
+}
+
+impl OnCalendar {
+    /// Parse a calendar event, the schedule starts after now.
+    pub fn from_expr(expr: &str) -> Result<Self, SystemdError> {
+        Self::from_expr_with_clock(expr, &SystemClock)
+    }
+    /// Like [`OnCalendar::from_expr`], the schedule starts after the current time of `clock`.
+    pub fn from_expr_with_clock(expr: &str, clock: &dyn Clock) -> Result<Self, SystemdError> {
+        Ok(expr.parse::<CalendarEvent>()?.schedule_with_clock(clock))
+    }
+    pub fn event(&self) -> &CalendarEvent {
+        &self.event
+    }
+}
+
+impl Schedule for OnCalendar {
+    fn peek_next(&mut self) -> Option<Dtu> {
+        self.next
+    }
+
+    fn next(&mut self) -> Option<Dtu> {
+        let next = self.next?;
+        self.next = self.event.next_after(next);
+        Some(next)
+    }
+
+    fn forward_to(&mut self, dtu: Dtu) {
+        if self.next.is_some_and(|next| next <= dtu) {
+            self.next = self.event.next_after(dtu);
+        }
+    }
+}
+
+impl IntoSchedule for CalendarEvent {
+    type Output = OnCalendar;
+    fn into_schedule(self) -> Self::Output {
+        self.schedule()
+    }
+    fn into_schedule_with_clock(self, clock: &dyn Clock) -> Self::Output {
+        self.schedule_with_clock(clock)
+    }
+}
+
+/// A systemd time span, the value of `OnUnitActiveSec=` or `OnBootSec=` in a timer unit, e.g.
+/// `1h 30min`, `2d` or `500ms`, see
+/// [systemd.time(7)](https://www.freedesktop.org/software/systemd/man/latest/systemd.time.html#Parsing%20Time%20Spans).
+///
+/// A number without a unit is in seconds. A month is 30.44 days, a year 365.25 days. A zero
+/// span is rejected, it can't be the interval of a schedule.
+///
+/// As a schedule, it runs every span, the first run one span from now, like
+/// `OnUnitActiveSec=` after the activation of the timer.
+///
+/// # Example
+/// ```
+/// # use tsuki_scheduler::prelude::*;
+/// use chrono::TimeDelta;
+/// let span: TimeSpan = "1h 30min".parse().unwrap();
+/// assert_eq!(span.as_delta(), TimeDelta::minutes(90));
+/// assert_eq!(span.to_string(), "1h 30min");
+/// let schedule = span.into_schedule();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TimeSpan(TimeDelta);
+
+const NANOS_PER_SECOND: i128 = 1_000_000_000;
+
+/// units and their length in nanoseconds, longest names first
+const UNITS: &[(&str, i128)] = &[
+    ("nsec", 1),
+    ("ns", 1),
+    ("usec", 1_000),
+    ("us", 1_000),
+    ("µs", 1_000),
+    ("msec", 1_000_000),
+    ("ms", 1_000_000),
+    ("seconds", NANOS_PER_SECOND),
+    ("second", NANOS_PER_SECOND),
+    ("sec", NANOS_PER_SECOND),
+    ("s", NANOS_PER_SECOND),
+    ("minutes", 60 * NANOS_PER_SECOND),
+    ("minute", 60 * NANOS_PER_SECOND),
+    ("min", 60 * NANOS_PER_SECOND),
+    ("m", 60 * NANOS_PER_SECOND),
+    ("hours", 3600 * NANOS_PER_SECOND),
+    ("hour", 3600 * NANOS_PER_SECOND),
+    ("hr", 3600 * NANOS_PER_SECOND),
+    ("h", 3600 * NANOS_PER_SECOND),
+    ("days", 86400 * NANOS_PER_SECOND),
+    ("day", 86400 * NANOS_PER_SECOND),
+    ("d", 86400 * NANOS_PER_SECOND),
+    ("weeks", 604800 * NANOS_PER_SECOND),
+    ("week", 604800 * NANOS_PER_SECOND),
+    ("w", 604800 * NANOS_PER_SECOND),
+    ("months", 2_629_800 * NANOS_PER_SECOND),
+    ("month", 2_629_800 * NANOS_PER_SECOND),
+    ("M", 2_629_800 * NANOS_PER_SECOND),
+    ("years", 31_557_600 * NANOS_PER_SECOND),
+    ("year", 31_557_600 * NANOS_PER_SECOND),
+    ("y", 31_557_600 * NANOS_PER_SECOND),
+];
+
+impl TimeSpan {
+    pub fn new(delta: TimeDelta) -> Self {
+        Self(delta)
+    }
+    pub fn as_delta(&self) -> TimeDelta {
+        self.0
+    }
+    /// Run every span, the first run one span after `start`.
+    ///
+    /// # Panics
+    /// if the span, built by [`TimeSpan::new`], is not positive
+    pub fn schedule_from(self, start: Dtu) -> Period {
+        self.schedule_from_with_clock(start, &SystemClock)
+    }
+    /// Like [`TimeSpan::schedule_from`], with `start` checked against the current time of
+    /// `clock`, see [`Period::new_with_clock`].
+    pub fn schedule_from_with_clock(self, start: Dtu, clock: &dyn Clock) -> Period {
+        Period::new_with_clock(self.0, start + self.0, clock)
+    }
+}
+
+impl From<TimeSpan> for TimeDelta {
+    fn from(span: TimeSpan) -> Self {
+        span.0
+    }
+}
+
+impl FromStr for TimeSpan {
+    type Err = SystemdError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || SystemdError::InvalidTimeSpan(s.to_string());
+        let mut rest = s.trim();
+        if rest.is_empty() {
+            return Err(error());
+        }
+        let mut total: i128 = 0;
+        while !rest.is_empty() {
+            let number_len = rest
+                .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                .unwrap_or(rest.len());
+            let (number, tail) = rest.split_at(number_len);
+            let tail = tail.trim_start();
+            let unit_len = tail
+                .find(|c: char| !c.is_alphabetic())
+                .unwrap_or(tail.len());
+            let (unit, tail) = tail.split_at(unit_len);
+            let nanos = match unit {
+                "" => NANOS_PER_SECOND,
+                unit => {
+                    UNITS
+                        .iter()
+                        .find(|(name, _)| *name == unit)
+                        .ok_or_else(error)?
+                        .1
+                }
+            };
+            let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+            if whole.is_empty() && fraction.is_empty() {
+                return Err(error());
+            }
+            let whole: i128 = if whole.is_empty() {
+                0
+            } else {
+                whole.parse().map_err(|_| error())?
+            };
+            let mut value = whole.checked_mul(nanos).ok_or_else(error)?;
+            let mut scale = nanos;
+            for digit in fraction.chars() {
+                scale /= 10;
+                value += digit.to_digit(10).ok_or_else(error)? as i128 * scale;
+            }
+            total = total.checked_add(value).ok_or_else(error)?;
+            rest = tail.trim_start();
+        }
+        let seconds = i64::try_from(total / NANOS_PER_SECOND).map_err(|_| error())?;
+        let delta = TimeDelta::new(seconds, (total % NANOS_PER_SECOND) as u32).ok_or_else(error)?;
+        if delta.is_zero() {
+            return Err(error());
+        }
+        Ok(Self(delta))
+    }
+}
+
+/// Print the span with the largest units first, e.g. `1d 2h 30min`.
+impl fmt::Display for TimeSpan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut rest =
+            self.0.num_seconds() as i128 * NANOS_PER_SECOND + self.0.subsec_nanos() as i128;
+        if rest == 0 {
+            return f.write_str("0");
+        }
+        let mut first = true;
+        for name in ["y", "M", "w", "d", "h", "min", "s", "ms", "us", "ns"] {
+            let nanos = UNITS
+                .iter()
+                .find(|(unit, _)| *unit == name)
+                .map_or(1, |(_, nanos)| *nanos);
+            if rest >= nanos {
+                if !first {
+                    f.write_str(" ")?;
+                }
+                write!(f, "{}{name}", rest / nanos)?;
+                rest %= nanos;
+                first = false;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl IntoSchedule for TimeSpan {
+    type Output = Period;
+    fn into_schedule(self) -> Self::Output {
+        self.into_schedule_with_clock(&SystemClock)
+    }
+    fn into_schedule_with_clock(self, clock: &dyn Clock) -> Self::Output {
+        self.schedule_from_with_clock(clock.now(), clock)
+    }
+}
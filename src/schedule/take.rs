@@ -0,0 +1,56 @@
+use super::Schedule;
+use crate::Dtu;
+
+/// A wrapper around a schedule that only keeps its first `count` time points.
+///
+/// The skipped time points are counted too, so [`forward_to`](Schedule::forward_to) walks
+/// through them one by one, at most `count` of them.
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq)]
+pub struct Take<S> {
+    pub remaining: u64,
+    pub inner: S,
+}
+
+impl<S: Schedule> Take<S> {
+    pub fn new(count: u64, inner: S) -> Self {
+        Self {
+            remaining: count,
+            inner,
+        }
+    }
+    /// the number of time points left
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+}
+
+impl<S: Schedule> Schedule for Take<S> {
+    fn peek_next(&mut self) -> Option<Dtu> {
+        if self.remaining == 0 {
+            None
+        } else {
+            self.inner.peek_next()
+        }
+    }
+
+    fn next(&mut self) -> Option<Dtu> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let next = self.inner.next()?;
+        self.remaining -= 1;
+        Some(next)
+    }
+
+    fn forward_to(&mut self, dtu: Dtu) {
+        super::forward_to_default(self, dtu)
+    }
+
+    fn register(&mut self, waker: &crate::ctx::ScheduleWaker) {
+        self.inner.register(waker)
+    }
+
+    fn is_open(&mut self) -> bool {
+        self.remaining > 0 && self.inner.is_open()
+    }
+}
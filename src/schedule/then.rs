@@ -36,4 +36,13 @@ impl<S0: Schedule, S1: Schedule> Schedule for Then<S0, S1> {
         self.firstly.forward_to(dtu);
         self.then.forward_to(dtu);
     }
+
+    fn register(&mut self, waker: &crate::ctx::ScheduleWaker) {
+        self.firstly.register(waker);
+        self.then.register(waker);
+    }
+
+    fn is_open(&mut self) -> bool {
+        self.firstly.is_open() || self.then.is_open()
+    }
 }
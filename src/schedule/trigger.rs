@@ -0,0 +1,129 @@
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use super::Schedule;
+use crate::{Dtu, ctx::ScheduleWaker};
+
+#[derive(Debug, Default)]
+struct TriggerState {
+    points: BinaryHeap<Reverse<Dtu>>,
+    /// the scheduler of the task, once added
+    waker: Option<ScheduleWaker>,
+}
+
+#[derive(Debug, Default)]
+struct TriggerShared {
+    state: Mutex<TriggerState>,
+}
+
+impl TriggerShared {
+    /// the state, also when poisoned: a fired time point is pushed into the heap in one step
+    fn lock(&self) -> MutexGuard<'_, TriggerState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// A schedule whose time points are pushed by its [`TriggerHandle`]s, e.g. from a webhook.
+///
+/// It is open: a task waits for the next push instead of being removed, see
+/// [`Schedule::is_open`]. Combine it with the usual cadence of the task with
+/// [`or`](super::ScheduleExt::or), and limit it with
+/// [`throttling`](super::ScheduleExt::throttling) or [`filtered`](super::ScheduleExt::filtered).
+///
+/// # Example
+/// ```
+/// # use tsuki_scheduler::prelude::*;
+/// use chrono::TimeDelta;
+/// let mut scheduler = Scheduler::new(Local::new());
+/// let trigger = Trigger::new();
+/// let handle = trigger.handle();
+/// let id = TaskUid::new(0);
+/// let schedule = Period::new(TimeDelta::hours(1), now() + TimeDelta::hours(1))
+///     .or(trigger.throttling(TimeDelta::seconds(10)));
+/// scheduler.add_task(id, Task::local(schedule, || println!("refresh")));
+/// // e.g. from a webhook handler, on any thread
+/// handle.fire();
+/// scheduler.execute_by_now();
+/// ```
+#[derive(Debug, Default)]
+pub struct Trigger {
+    shared: Arc<TriggerShared>,
+}
+
+impl Trigger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// a handle to push time points into this schedule
+    pub fn handle(&self) -> TriggerHandle {
+        TriggerHandle {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Schedule for Trigger {
+    fn peek_next(&mut self) -> Option<Dtu> {
+        self.shared
+            .lock()
+            .points
+            .peek()
+            .map(|&Reverse(point)| point)
+    }
+
+    fn next(&mut self) -> Option<Dtu> {
+        self.shared.lock().points.pop().map(|Reverse(point)| point)
+    }
+
+    fn forward_to(&mut self, dtu: Dtu) {
+        let mut state = self.shared.lock();
+        while state
+            .points
+            .peek()
+            .is_some_and(|&Reverse(point)| point <= dtu)
+        {
+            state.points.pop();
+        }
+    }
+
+    fn register(&mut self, waker: &ScheduleWaker) {
+        self.shared.lock().waker = Some(waker.clone());
+    }
+
+    fn is_open(&mut self) -> bool {
+        true
+    }
+}
+
+/// Pushes time points into a [`Trigger`], from any thread.
+///
+/// # Clone
+/// this handle is cheap to clone.
+#[derive(Debug, Clone)]
+pub struct TriggerHandle {
+    shared: Arc<TriggerShared>,
+}
+
+impl TriggerHandle {
+    /// run the task now, by the clock of the scheduler once the task is added
+    pub fn fire(&self) {
+        let now = self.shared.lock().waker.as_ref().map(ScheduleWaker::now);
+        self.fire_at(now.unwrap_or_else(crate::now));
+    }
+    /// run the task at `time`, it is run at the next execution of the scheduler if `time` is past
+    pub fn fire_at(&self, time: Dtu) {
+        let waker = {
+            let mut state = self.shared.lock();
+            state.points.push(Reverse(time));
+            state.waker.clone()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::cmp::Reverse;
+
+use chrono::TimeDelta;
+
+use crate::{Dtu, Scheduler, TaskRun, TaskUid, runtime::Runtime};
+
+impl<R: Runtime, H> Scheduler<R, H> {
+    /// Replay the schedules of all tasks over `from..=to`, without running anything.
+    ///
+    /// The schedules are forwarded to `from` at once, then the runs in `from..=to` are logged.
+    /// Nothing is spawned and the handle manager is not called.
+    ///
+    /// The schedules can't be cloned, walking them uses them up: the scheduler is consumed.
+    /// Build one for the simulation, with the same schedules as the running one.
+    ///
+    /// # Example
+    /// ```
+    /// # use tsuki_scheduler::prelude::*;
+    /// use chrono::{TimeDelta, TimeZone, Utc};
+    /// let start = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+    /// let mut scheduler = Scheduler::new(Local::new());
+    /// let every_hour = Iter::new((0..).map(move |h| start + TimeDelta::hours(h)));
+    /// scheduler.add_task(TaskUid::new(1), Task::local(every_hour, || {}));
+    /// let log = scheduler.simulate(start, start + TimeDelta::days(1));
+    /// assert_eq!(log.count_of(TaskUid::new(1)), 25);
+    /// ```
+    pub fn simulate(mut self, from: Dtu, to: Dtu) -> SimulationLog {
+        self.skip_before(from);
+        let mut runs = Vec::new();
+        while let Some(next_up) = self.pop_due(to) {
+            if next_up.time >= from {
+                runs.push(next_up.clone());
+            }
+            self.reschedule(next_up);
+        }
+        SimulationLog::new(runs)
+    }
+    /// move the tasks late for `from` to their first run from then, at once
+    fn skip_before(&mut self, from: Dtu) {
+        let late: Vec<TaskUid> = self
+            .task_map
+            .iter()
+            .filter(|(_, entry)| entry.next.is_some_and(|next| next < from))
+            .map(|(key, _)| *key)
+            .collect();
+        // a schedule forwarded to a time point may skip it, the runs at `from` are kept
+        let before = from - TimeDelta::nanoseconds(1);
+        for key in late {
+            let entry = self.task_map.get_mut(&key).expect("listed above");
+            entry.task.schedule.forward_to(before);
+            while entry
+                .deferred
+                .peek()
+                .is_some_and(|&Reverse(deferred)| deferred < from)
+            {
+                entry.deferred.pop();
+            }
+            let next = entry.take_next();
+            self.plan(key, next);
+        }
+    }
+}
+
+/// The runs logged by [`Scheduler::simulate`], ordered by time, then by task uid.
+///
+/// # Example
+/// ```
+/// # use tsuki_scheduler::prelude::*;
+/// use chrono::{TimeDelta, TimeZone, Utc};
+/// let start = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+/// let (backup, report) = (TaskUid::new(1), TaskUid::new(2));
+/// let mut scheduler = Scheduler::new(Local::new());
+/// scheduler.add_task(backup, Task::local([start, start + TimeDelta::hours(1)], || {}));
+/// scheduler.add_task(report, Task::local([start + TimeDelta::minutes(3)], || {}));
+/// let log = scheduler.simulate(start, start + TimeDelta::days(1));
+/// assert_eq!(log.times_of(backup), [start, start + TimeDelta::hours(1)]);
+/// // the backup and the report are only 3 minutes apart
+/// assert!(log.conflict_within(TimeDelta::minutes(5)).is_some());
+/// assert!(log.conflict_within(TimeDelta::minutes(2)).is_none());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SimulationLog {
+    runs: Vec<TaskRun>,
+}
+
+impl SimulationLog {
+    fn new(mut runs: Vec<TaskRun>) -> Self {
+        runs.sort_by_key(|run| (run.time, run.key));
+        Self { runs }
+    }
+    pub fn runs(&self) -> &[TaskRun] {
+        &self.runs
+    }
+    pub fn into_runs(self) -> Vec<TaskRun> {
+        self.runs
+    }
+    pub fn len(&self) -> usize {
+        self.runs.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.runs.is_empty()
+    }
+    pub fn iter(&self) -> std::slice::Iter<'_, TaskRun> {
+        self.runs.iter()
+    }
+    /// the times the task `key` ran at
+    pub fn times_of(&self, key: TaskUid) -> Vec<Dtu> {
+        self.iter()
+            .filter(|run| run.key == key)
+            .map(|run| run.time)
+            .collect()
+    }
+    /// the number of runs of the task `key`
+    pub fn count_of(&self, key: TaskUid) -> usize {
+        self.iter().filter(|run| run.key == key).count()
+    }
+    /// the runs of a group of tasks
+    pub fn group(&self, keys: impl IntoIterator<Item = TaskUid>) -> Self {
+        let keys: Vec<_> = keys.into_iter().collect();
+        Self {
+            runs: self
+                .iter()
+                .filter(|run| keys.contains(&run.key))
+                .cloned()
+                .collect(),
+        }
+    }
+    /// The first two runs of different tasks less than `window` apart.
+    pub fn conflict_within(&self, window: TimeDelta) -> Option<(&TaskRun, &TaskRun)> {
+        self.runs.iter().enumerate().find_map(|(index, run)| {
+            self.runs[..index]
+                .iter()
+                .rev()
+                .take_while(|earlier| run.time - earlier.time < window)
+                .find(|earlier| earlier.key != run.key)
+                .map(|earlier| (earlier, run))
+        })
+    }
+}
+
+impl<'a> IntoIterator for &'a SimulationLog {
+    type Item = &'a TaskRun;
+    type IntoIter = std::slice::Iter<'a, TaskRun>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for SimulationLog {
+    type Item = TaskRun;
+    type IntoIter = std::vec::IntoIter<TaskRun>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.runs.into_iter()
+    }
+}
@@ -0,0 +1,102 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    ops::Deref,
+    sync::Arc,
+};
+
+use crate::{Scheduler, TaskRun, runtime::Runtime, runtime::TaskRunArg};
+
+/// The shared states of a scheduler, one per type.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct StateMap {
+    states: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+impl StateMap {
+    fn insert<T: Send + Sync + 'static>(&mut self, state: T) {
+        self.states.insert(TypeId::of::<T>(), Arc::new(state));
+    }
+    fn get<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        let state = self.states.get(&TypeId::of::<T>())?.clone();
+        state.downcast().ok()
+    }
+}
+
+impl<R: Runtime, H> Scheduler<R, H> {
+    /// Share a state with the task functions, they receive it with a [`State`] argument.
+    ///
+    /// There is one state per type, it replaces the state of the same type.
+    ///
+    /// # Example
+    /// ```
+    /// # use tsuki_scheduler::prelude::*;
+    /// struct Config {
+    ///     greeting: &'static str,
+    /// }
+    /// let mut scheduler = Scheduler::new(Local::new()).with_state(Config { greeting: "hello" });
+    /// scheduler.add_task(
+    ///     TaskUid::new(0),
+    ///     Task::new(now(), |config: State<Config>| println!("{}", config.greeting)),
+    /// );
+    /// scheduler.execute_by_now();
+    /// ```
+    pub fn with_state<T: Send + Sync + 'static>(mut self, state: T) -> Self {
+        self.insert_state(state);
+        self
+    }
+    /// share a state with the task functions, see [`with_state`](Scheduler::with_state)
+    pub fn insert_state<T: Send + Sync + 'static>(&mut self, state: T) {
+        Arc::make_mut(&mut self.state).insert(state);
+    }
+    /// the shared state of type `T`
+    pub fn state<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        self.state.get()
+    }
+}
+
+impl TaskRun {
+    /// the state of type `T` shared by the scheduler which dispatched this run
+    pub fn state<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        self.context.state.as_ref()?.get()
+    }
+}
+
+/// A state shared by the scheduler, see [`Scheduler::with_state`].
+///
+/// # Panics
+/// The extraction panics if the scheduler has no state of type `T`, take an
+/// `Option<State<T>>` for an optional state.
+#[derive(Debug)]
+pub struct State<T>(pub Arc<T>);
+
+impl<T> Clone for State<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> Deref for State<T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T: Send + Sync + 'static> TaskRunArg for Option<State<T>> {
+    fn extract(task_run: &TaskRun) -> Self {
+        task_run.state().map(State)
+    }
+}
+
+impl<T: Send + Sync + 'static> TaskRunArg for State<T> {
+    fn extract(task_run: &TaskRun) -> Self {
+        match task_run.state() {
+            Some(state) => State(state),
+            None => panic!(
+                "no state of type `{}` in the scheduler, see `Scheduler::with_state`",
+                std::any::type_name::<T>()
+            ),
+        }
+    }
+}
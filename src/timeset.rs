@@ -102,6 +102,14 @@ pub trait TimeSet: Send + 'static {
         let _ = from;
         LowerBound::Unknown
     }
+    /// The contained intervals within `[from, to)`, see [`Interval`].
+    ///
+    /// `None` if the set is not made of intervals that can be listed (e.g. a set of time points,
+    /// or a predicate), the default implementation.
+    fn intervals(&self, from: Dtu, to: Dtu) -> Option<Vec<Interval>> {
+        let _ = (from, to);
+        None
+    }
 }
 
 impl TimeSet for DynTimeSet {
@@ -111,6 +119,78 @@ impl TimeSet for DynTimeSet {
     fn lower_bound(&self, from: Dtu) -> LowerBound {
         self.as_ref().lower_bound(from)
     }
+    fn intervals(&self, from: Dtu, to: Dtu) -> Option<Vec<Interval>> {
+        self.as_ref().intervals(from, to)
+    }
+}
+
+/// A non-empty half-open interval `[start, end)`.
+///
+/// [`TimeSet::intervals`] lists them sorted, disjoint and not touching each other, so that each
+/// one is a maximal block of the set.
+pub type Interval = std::ops::Range<Dtu>;
+
+/// Sort and merge overlapping or touching intervals, dropping the empty ones.
+pub fn normalize_intervals(mut intervals: Vec<Interval>) -> Vec<Interval> {
+    intervals.retain(|interval| interval.start < interval.end);
+    intervals.sort_by_key(|interval| interval.start);
+    let mut merged: Vec<Interval> = Vec::with_capacity(intervals.len());
+    for interval in intervals {
+        match merged.last_mut() {
+            Some(last) if interval.start <= last.end => last.end = last.end.max(interval.end),
+            _ => merged.push(interval),
+        }
+    }
+    merged
+}
+
+/// The intersection of two normalized interval lists.
+fn intersect_intervals(a: &[Interval], b: &[Interval]) -> Vec<Interval> {
+    let (mut i, mut j) = (0, 0);
+    let mut result = vec![];
+    while i < a.len() && j < b.len() {
+        let start = a[i].start.max(b[j].start);
+        let end = a[i].end.min(b[j].end);
+        if start < end {
+            result.push(start..end);
+        }
+        if a[i].end < b[j].end {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    result
+}
+
+/// `a - b`, for two normalized interval lists.
+fn subtract_intervals(a: &[Interval], b: &[Interval]) -> Vec<Interval> {
+    let mut result = vec![];
+    let mut cut = b.iter().peekable();
+    for interval in a {
+        let mut start = interval.start;
+        while let Some(hole) = cut.peek() {
+            if hole.end <= start {
+                cut.next();
+                continue;
+            }
+            if hole.start >= interval.end {
+                break;
+            }
+            if hole.start > start {
+                result.push(start..hole.start);
+            }
+            start = start.max(hole.end);
+            if hole.end > interval.end {
+                break;
+            }
+            cut.next();
+        }
+        if start < interval.end {
+            result.push(start..interval.end);
+        }
+    }
+    result
 }
 
 /// shortcuts for combining time sets
@@ -312,6 +392,18 @@ impl TimeSet for Range {
             _ => LowerBound::At(from),
         }
     }
+    fn intervals(&self, from: Dtu, to: Dtu) -> Option<Vec<Interval>> {
+        let start = match self.from {
+            Some(start) => start.first_allowed().map_or(to, |first| first.max(from)),
+            None => from,
+        };
+        let end = match self.to {
+            Some(end) if end.inclusive => succ_dtu(end.value).unwrap_or(end.value).min(to),
+            Some(end) => end.value.min(to),
+            None => to,
+        };
+        Some(normalize_intervals(vec![start..end]))
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -357,6 +449,9 @@ impl TimeSet for Empty {
     fn lower_bound(&self, _from: Dtu) -> LowerBound {
         LowerBound::Never
     }
+    fn intervals(&self, _from: Dtu, _to: Dtu) -> Option<Vec<Interval>> {
+        Some(vec![])
+    }
 }
 
 /// A time set containing every time point.
@@ -370,6 +465,9 @@ impl TimeSet for Universal {
     fn lower_bound(&self, from: Dtu) -> LowerBound {
         LowerBound::At(from)
     }
+    fn intervals(&self, from: Dtu, to: Dtu) -> Option<Vec<Interval>> {
+        Some(normalize_intervals(vec![from..to]))
+    }
 }
 
 /// The union of several time sets.
@@ -432,6 +530,13 @@ impl TimeSet for Union {
             ),
         }
     }
+    fn intervals(&self, from: Dtu, to: Dtu) -> Option<Vec<Interval>> {
+        let mut intervals = vec![];
+        for collection in &self.collections {
+            intervals.extend(collection.intervals(from, to)?);
+        }
+        Some(normalize_intervals(intervals))
+    }
 }
 
 /// The intersection of several time sets.
@@ -490,6 +595,18 @@ impl TimeSet for Intersection {
             ),
         }
     }
+    fn intervals(&self, from: Dtu, to: Dtu) -> Option<Vec<Interval>> {
+        let mut collections = self.collections.iter();
+        let mut intervals = match collections.next() {
+            Some(first) => first.intervals(from, to)?,
+            // an empty intersection contains everything
+            None => return Universal.intervals(from, to),
+        };
+        for collection in collections {
+            intervals = intersect_intervals(&intervals, &collection.intervals(from, to)?);
+        }
+        Some(intervals)
+    }
 }
 
 /// whether `dtu` may be contained by a set whose cached start bound is `start`
@@ -522,6 +639,12 @@ impl<T: TimeSet, U: TimeSet> TimeSet for Difference<T, U> {
         // we cannot say more without knowing where the intervals of `b` end.
         self.a.lower_bound(from)
     }
+    fn intervals(&self, from: Dtu, to: Dtu) -> Option<Vec<Interval>> {
+        Some(subtract_intervals(
+            &self.a.intervals(from, to)?,
+            &self.b.intervals(from, to)?,
+        ))
+    }
 }
 
 #[cfg(test)]
@@ -624,4 +747,29 @@ mod tests {
         assert!(!set.contains(now + TimeDelta::seconds(1)));
         assert_eq!(set.lower_bound(min_dtu()), LowerBound::At(now));
     }
+
+    #[test]
+    fn test_intervals() {
+        let now = Utc::now();
+        let day = |n: i64| now + TimeDelta::days(n);
+        let a = Range::between(day(1), day(3)).expect("valid");
+        let b = Range::between(day(2), day(5)).expect("valid");
+        let c = Range::between(day(7), day(8)).expect("valid");
+        let union = Union::new(vec![a.dyn_box(), b.dyn_box(), c.dyn_box()]);
+        assert_eq!(
+            union.intervals(day(0), day(10)),
+            Some(vec![day(1)..day(5), day(7)..day(8)])
+        );
+        // clipped to the query
+        assert_eq!(union.intervals(day(4), day(7)), Some(vec![day(4)..day(5)]));
+        assert_eq!(
+            a.intersection(b).intervals(day(0), day(10)),
+            Some(vec![day(2)..day(3)])
+        );
+        assert_eq!(
+            Universal.difference(union).intervals(day(0), day(10)),
+            Some(vec![day(0)..day(1), day(5)..day(7), day(8)..day(10)])
+        );
+        assert_eq!(Discrete::new([now]).intervals(day(0), day(1)), None);
+    }
 }
@@ -0,0 +1,26 @@
+//! Helpers shared by the integration tests, each test crate uses a part of them.
+#![allow(dead_code)]
+
+use chrono::{TimeDelta, TimeZone, Utc};
+use tsuki_scheduler::prelude::*;
+
+pub fn utc(y: i32, m: u32, d: u32, h: u32, min: u32) -> Dtu {
+    Utc.with_ymd_and_hms(y, m, d, h, min, 0)
+        .single()
+        .expect("valid time")
+}
+
+/// a time of 2025-01-01
+pub fn at(h: u32, min: u32) -> Dtu {
+    utc(2025, 1, 1, h, min)
+}
+
+/// the next `n` time points of a schedule, fewer if it ends
+pub fn take<S: Schedule>(schedule: &mut S, n: usize) -> Vec<Dtu> {
+    (0..n).map_while(|_| schedule.next()).collect()
+}
+
+/// every `period` from `from`, included
+pub fn every(period: TimeDelta, from: Dtu) -> Iter<impl Iterator<Item = Dtu> + Send + 'static> {
+    Iter::new((0..).map(move |n| from + period * n))
+}
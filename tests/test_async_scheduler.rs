@@ -0,0 +1,559 @@
+use std::{
+    pin::Pin,
+    time::{Duration, Instant},
+};
+
+use chrono::TimeDelta;
+use futures_core::Stream;
+use tsuki_scheduler::{TaskRun, prelude::*};
+
+#[tokio::test]
+async fn test_wake_at_deadline() {
+    let runner =
+        AsyncSchedulerRunner::<Tokio>::default().with_execute_duration(Duration::from_secs(3600));
+    let client = runner.client();
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let start = Instant::now();
+    let task = Task::new_async(now() + TimeDelta::milliseconds(300), move || {
+        let sender = sender.clone();
+        async move {
+            sender.send(Instant::now()).expect("receiver alive");
+        }
+    });
+    // queued before the runner starts
+    client
+        .try_add_task(TaskUid::new(0), task)
+        .expect("room in the channel");
+    let running =
+        tokio::spawn(runner.run_with_shutdown_signal(tokio::time::sleep(Duration::from_secs(1))));
+    let fired = receiver.recv().await.expect("task fired");
+    let late = fired - start;
+    assert!(late >= Duration::from_millis(290), "{late:?}");
+    assert!(late < Duration::from_millis(390), "{late:?}");
+    running.await.expect("runner finished");
+}
+
+#[tokio::test]
+async fn test_client_wakes_runner() {
+    // nothing to do, the runner sleeps for an hour
+    let runner =
+        AsyncSchedulerRunner::<Tokio>::default().with_execute_duration(Duration::from_secs(3600));
+    let client = runner.client();
+    let running =
+        tokio::spawn(runner.run_with_shutdown_signal(tokio::time::sleep(Duration::from_secs(1))));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let start = Instant::now();
+    let task = Task::new_async(now(), move || {
+        let sender = sender.clone();
+        async move {
+            sender.send(Instant::now()).expect("receiver alive");
+        }
+    });
+    client
+        .try_add_task(TaskUid::new(0), task)
+        .expect("room in the channel");
+    let fired = receiver.recv().await.expect("task fired");
+    assert!(
+        fired - start < Duration::from_millis(50),
+        "{:?}",
+        fired - start
+    );
+
+    // the task ran once and was removed
+    let (mut runner, ()) = running.await.expect("runner finished");
+    assert!(runner.scheduler.delete_task(TaskUid::new(0)).is_none());
+}
+
+async fn check_replies<R: AsyncRuntime>(client: AsyncSchedulerClient<R>, task: fn() -> Task<R>)
+where
+    R::Handle: Send,
+{
+    let id = TaskUid::new(7);
+    let outcome = client.add_task(id, task()).await.expect("runner alive");
+    let AddOutcome::Added { next_run } = outcome else {
+        panic!("unexpected {outcome:?}")
+    };
+    assert_eq!(
+        client.next_run(id).await.expect("runner alive"),
+        Some(next_run)
+    );
+    assert!(matches!(
+        client.add_task(id, task()).await,
+        Ok(AddOutcome::Replaced { .. })
+    ));
+    let list = client.list().await.expect("runner alive");
+    assert_eq!(list.len(), 1);
+    assert_eq!(list[0].key(), id);
+
+    let exhausted = Task {
+        schedule: Box::new(Never),
+        ..task()
+    };
+    assert_eq!(
+        client.add_task(TaskUid::new(8), exhausted).await,
+        Ok(AddOutcome::Exhausted)
+    );
+    let removed = client.remove_task(id).await.expect("runner alive");
+    assert_eq!(removed.map(|removed| removed.key), Some(id));
+    assert_eq!(client.remove_task(id).await, Ok(None));
+    assert_eq!(client.next_run(id).await, Ok(None));
+
+    // a task waiting for a time point is removed too
+    let waiting = Task {
+        schedule: Box::new(Trigger::new()),
+        ..task()
+    };
+    assert_eq!(
+        client.add_task(id, waiting).await,
+        Ok(AddOutcome::Waiting { replaced: false })
+    );
+    assert_eq!(
+        client.remove_task(id).await,
+        Ok(Some(RemovedTask {
+            key: id,
+            next_run: None
+        }))
+    );
+}
+
+#[tokio::test]
+async fn test_tokio_replies() {
+    let runner = AsyncSchedulerRunner::<Tokio>::default();
+    let client = runner.client();
+    let running = tokio::spawn(runner.run());
+    check_replies(client.clone(), || {
+        Task::new_async(now() + TimeDelta::hours(1), || async {})
+    })
+    .await;
+    running.abort();
+
+    // the queued requests are canceled when the runner is dropped
+    let runner = AsyncSchedulerRunner::<Tokio>::default();
+    let client = runner.client();
+    let reply = client
+        .try_add_task(TaskUid::new(0), Task::new_async(now(), || async {}))
+        .expect("room in the channel");
+    drop(runner);
+    assert_eq!(reply.await, Err(Canceled));
+    assert_eq!(client.list().await, Err(Canceled));
+    assert!(matches!(
+        client.try_add_task(TaskUid::new(0), Task::new_async(now(), || async {})),
+        Err(TryAddTaskError::Closed(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_backpressure() {
+    let scheduler = Scheduler::new(Tokio);
+    let runner = AsyncSchedulerRunner::with_capacity(scheduler, 2);
+    let client = runner.client();
+    let task = || Task::new_async(now() + TimeDelta::hours(1), || async {});
+    for id in 0..2 {
+        client
+            .try_add_task(TaskUid::new(id), task())
+            .expect("room in the channel");
+    }
+    let full = client.try_add_task(TaskUid::new(2), task());
+    assert!(matches!(full, Err(TryAddTaskError::Full(_))));
+
+    // waits for the runner to make room
+    let waiting = tokio::spawn({
+        let client = client.clone();
+        async move { client.add_task(TaskUid::new(2), task()).await }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!waiting.is_finished());
+    let running = tokio::spawn(runner.run());
+    let outcome = waiting.await.expect("not panicked");
+    assert!(matches!(outcome, Ok(AddOutcome::Added { .. })));
+    assert_eq!(client.list().await.expect("runner alive").len(), 3);
+    running.abort();
+}
+
+#[cfg(feature = "async-std")]
+#[test]
+fn test_async_std_replies() {
+    async_std::task::block_on(async {
+        let runner = AsyncSchedulerRunner::<AsyncStd>::default();
+        let client = runner.client();
+        let running = async_std::task::spawn(runner.run());
+        check_replies(client, || {
+            Task::new_async(now() + TimeDelta::hours(1), || async {})
+        })
+        .await;
+        running.cancel().await;
+    });
+}
+
+#[tokio::test]
+async fn test_graceful_shutdown() {
+    let runner = AsyncSchedulerRunner::<Tokio, TrackHandles<_>>::default();
+    let client = runner.client();
+    let (quick, slow) = (TaskUid::new(1), TaskUid::new(2));
+    let slow_finished = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    client
+        .try_add_task(
+            quick,
+            Task::new_async(now(), || async {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }),
+        )
+        .expect("room in the channel");
+    client
+        .try_add_task(
+            slow,
+            Task::new_async(now(), {
+                let slow_finished = slow_finished.clone();
+                move || {
+                    let slow_finished = slow_finished.clone();
+                    async move {
+                        tokio::time::sleep(Duration::from_secs(10)).await;
+                        slow_finished.store(true, std::sync::atomic::Ordering::SeqCst);
+                    }
+                }
+            }),
+        )
+        .expect("room in the channel");
+    let start = Instant::now();
+    let signal = tokio::time::sleep(Duration::from_millis(50));
+    let (runner, (), report) = runner
+        .run_with_graceful_shutdown(signal, Duration::from_millis(300))
+        .await;
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(340), "{elapsed:?}");
+    assert!(elapsed < Duration::from_millis(1000), "{elapsed:?}");
+    let keys = |runs: &[TaskRun]| runs.iter().map(TaskRun::key).collect::<Vec<_>>();
+    assert_eq!(keys(&report.completed), [quick]);
+    assert_eq!(keys(&report.aborted), [slow]);
+    assert!(report.running.is_empty());
+    assert!(!report.is_clean());
+    assert!(runner.scheduler.handle_manager.is_empty());
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!slow_finished.load(std::sync::atomic::Ordering::SeqCst));
+}
+
+#[tokio::test]
+async fn test_drain_without_runs() {
+    let mut runner = AsyncSchedulerRunner::<Tokio, TrackHandles<_>>::default();
+    let start = Instant::now();
+    let report = runner.drain(Duration::from_secs(10)).await;
+    assert!(start.elapsed() < Duration::from_millis(100));
+    assert_eq!(report, ShutdownReport::default());
+}
+
+#[cfg(feature = "async-std")]
+#[test]
+fn test_async_std_graceful_shutdown() {
+    async_std::task::block_on(async {
+        let runner = AsyncSchedulerRunner::<AsyncStd, TrackHandles<_>>::default();
+        let client = runner.client();
+        client
+            .try_add_task(
+                TaskUid::new(1),
+                Task::new_async(now(), || async {
+                    async_std::task::sleep(Duration::from_secs(10)).await;
+                }),
+            )
+            .expect("room in the channel");
+        let signal = async_std::task::sleep(Duration::from_millis(50));
+        let (_, (), report) = runner
+            .run_with_graceful_shutdown(signal, Duration::from_millis(50))
+            .await;
+        // async-std can't abort a run
+        assert!(report.aborted.is_empty());
+        assert_eq!(report.running.len(), 1);
+    });
+}
+
+#[tokio::test]
+async fn test_shutdown_reason() {
+    #[derive(Debug, PartialEq)]
+    enum Reason {
+        Stop,
+    }
+    let runner = AsyncSchedulerRunner::<Tokio>::default();
+    let client = runner.client();
+    let (stop, stopped) = tokio::sync::oneshot::channel();
+    // an async block is not Unpin
+    let running = tokio::spawn(runner.run_with_shutdown_signal(async move {
+        stopped.await.expect("sender alive");
+        Reason::Stop
+    }));
+    client
+        .add_task(
+            TaskUid::new(0),
+            Task::new_async(now() + TimeDelta::hours(1), || async {}),
+        )
+        .await
+        .expect("runner alive");
+    stop.send(()).expect("runner alive");
+    let (runner, reason) = running.await.expect("runner finished");
+    assert_eq!(reason, Reason::Stop);
+    assert!(runner.scheduler.next_run(TaskUid::new(0)).is_some());
+}
+
+#[tokio::test]
+async fn test_history_outcomes() {
+    let scheduler = Scheduler::new(Tokio)
+        .with_handle_manager(JoinSetManager::new())
+        .with_history(8);
+    let runner =
+        AsyncSchedulerRunner::new(scheduler).with_execute_duration(Duration::from_millis(20));
+    let client = runner.client();
+    let running = tokio::spawn(runner.run());
+    let (ok, panics) = (TaskUid::new(0), TaskUid::new(1));
+    client
+        .add_task(ok, Task::new_async([now()], || async {}))
+        .await
+        .expect("runner alive");
+    client
+        .add_task(
+            panics,
+            Task::new_async([now()], || async { panic!("oops") }),
+        )
+        .await
+        .expect("runner alive");
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let outcomes = |records: Vec<RunRecord>| {
+        records
+            .iter()
+            .map(|record| record.outcome)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        outcomes(client.history(ok).await.expect("runner alive")),
+        [RunOutcome::Succeeded]
+    );
+    assert_eq!(
+        outcomes(client.history(panics).await.expect("runner alive")),
+        [RunOutcome::Panicked]
+    );
+    running.abort();
+}
+
+#[tokio::test]
+async fn test_ctx_wakes_runner() {
+    let runner =
+        AsyncSchedulerRunner::<Tokio>::default().with_execute_duration(Duration::from_secs(3600));
+    let client = runner.client();
+    let running = tokio::spawn(runner.run());
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let (first, follow_up) = (TaskUid::new(0), TaskUid::new(1));
+    client
+        .add_task(
+            first,
+            Task::new_async(now(), move |ctx: SchedulerCtx<Tokio>| {
+                let sender = sender.clone();
+                async move {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    ctx.add_task(
+                        follow_up,
+                        Task::new_async(now(), move || {
+                            let sender = sender.clone();
+                            async move {
+                                sender.send(Instant::now()).expect("receiver alive");
+                            }
+                        }),
+                    );
+                }
+            }),
+        )
+        .await
+        .expect("runner alive");
+    let start = Instant::now();
+    // the runner sleeps for an hour, the command wakes it
+    let fired = receiver.recv().await.expect("follow up fired");
+    let elapsed = fired - start;
+    assert!(elapsed < Duration::from_millis(200), "{elapsed:?}");
+    running.abort();
+}
+
+#[tokio::test]
+async fn test_feedback_wakes_runner() {
+    let runner =
+        AsyncSchedulerRunner::<Tokio>::default().with_execute_duration(Duration::from_secs(3600));
+    let client = runner.client();
+    let running = tokio::spawn(runner.run());
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    client
+        .add_task(
+            TaskUid::new(0),
+            // no default time point, the next run comes from the output
+            Task::feedback_async([now()], move |run: TaskRun| {
+                let sender = sender.clone();
+                async move {
+                    sender.send(Instant::now()).expect("receiver alive");
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    match run.run_count() {
+                        1 => NextRun::In(TimeDelta::milliseconds(100)),
+                        _ => NextRun::Stop,
+                    }
+                }
+            }),
+        )
+        .await
+        .expect("runner alive");
+    let first = receiver.recv().await.expect("first run");
+    // the runner sleeps for an hour, the output wakes it
+    let second = receiver.recv().await.expect("second run");
+    let elapsed = second - first;
+    assert!(elapsed < Duration::from_millis(300), "{elapsed:?}");
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(client.list().await.expect("runner alive").is_empty());
+    running.abort();
+}
+
+#[tokio::test]
+async fn test_trigger_wakes_runner() {
+    let runner =
+        AsyncSchedulerRunner::<Tokio>::default().with_execute_duration(Duration::from_secs(3600));
+    let client = runner.client();
+    let running = tokio::spawn(runner.run());
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let trigger = Trigger::new();
+    let handle = trigger.handle();
+    let outcome = client
+        .add_task(
+            TaskUid::new(0),
+            Task::new_async(trigger, move || {
+                let sender = sender.clone();
+                async move {
+                    sender.send(Instant::now()).expect("receiver alive");
+                }
+            }),
+        )
+        .await
+        .expect("runner alive");
+    assert_eq!(outcome, AddOutcome::Waiting { replaced: false });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    // the runner sleeps for an hour, the trigger wakes it
+    let start = Instant::now();
+    std::thread::spawn(move || handle.fire())
+        .join()
+        .expect("fired");
+    let fired = receiver.recv().await.expect("triggered run");
+    let elapsed = fired - start;
+    assert!(elapsed < Duration::from_millis(200), "{elapsed:?}");
+    running.abort();
+}
+
+#[tokio::test]
+async fn test_client_trigger_now() {
+    let runner =
+        AsyncSchedulerRunner::<Tokio>::default().with_execute_duration(Duration::from_secs(3600));
+    let client = runner.client();
+    let running = tokio::spawn(runner.run());
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let id = TaskUid::new(0);
+    let next = now() + TimeDelta::hours(1);
+    client
+        .add_task(
+            id,
+            Task::new_async(next, move |run: TaskRun| {
+                let sender = sender.clone();
+                async move {
+                    sender.send(run.is_manual()).expect("receiver alive");
+                }
+            }),
+        )
+        .await
+        .expect("runner alive");
+    let run = client.trigger_now(id).await.expect("runner alive");
+    assert!(run.is_some_and(|run| run.is_manual()));
+    assert_eq!(receiver.recv().await, Some(true));
+    assert_eq!(client.next_run(id).await.expect("runner alive"), Some(next));
+    assert_eq!(
+        client
+            .trigger_now(TaskUid::new(1))
+            .await
+            .expect("runner alive"),
+        None
+    );
+    running.abort();
+}
+
+#[tokio::test]
+async fn test_ticker() {
+    let start = now();
+    let schedule = Iter::new((1..=3).map(move |n| start + TimeDelta::milliseconds(100 * n)));
+    let mut ticks = Ticker::tokio(schedule);
+    let begin = Instant::now();
+    for n in 1..=3 {
+        let time = ticks.tick().await.expect("a time point left");
+        assert_eq!(time, start + TimeDelta::milliseconds(100 * n));
+        assert!(now() >= time);
+    }
+    assert_eq!(ticks.tick().await, None);
+    let elapsed = begin.elapsed();
+    assert!(elapsed >= Duration::from_millis(250), "{elapsed:?}");
+    assert!(elapsed < Duration::from_millis(600), "{elapsed:?}");
+}
+
+#[tokio::test]
+async fn test_ticker_missed_ticks() {
+    let start = now();
+    let missed_then_one = move || {
+        Iter::new(
+            [-3, -2, -1, 1]
+                .into_iter()
+                .map(move |s| start + TimeDelta::milliseconds(100 * s)),
+        )
+    };
+    // every missed time point right away, as a stream
+    let mut burst = Ticker::tokio(missed_then_one());
+    let begin = Instant::now();
+    for s in [-3, -2, -1] {
+        let time = std::future::poll_fn(|cx| Pin::new(&mut burst).poll_next(cx)).await;
+        assert_eq!(time, Some(start + TimeDelta::milliseconds(100 * s)));
+    }
+    assert!(begin.elapsed() < Duration::from_millis(50));
+
+    let mut skip =
+        Ticker::tokio(missed_then_one()).with_missed_tick_behavior(MissedTickBehavior::Skip);
+    let begin = Instant::now();
+    assert_eq!(
+        skip.tick().await,
+        Some(start - TimeDelta::milliseconds(300))
+    );
+    assert!(begin.elapsed() < Duration::from_millis(50));
+    // the other missed time points are skipped
+    assert_eq!(
+        skip.tick().await,
+        Some(start + TimeDelta::milliseconds(100))
+    );
+    assert_eq!(skip.tick().await, None);
+}
+
+#[tokio::test]
+async fn test_ticker_delay() {
+    let clock = ManualClock::new(now());
+    let start = clock.now();
+    let every_100ms = Iter::new((-3..).map(move |n| start + TimeDelta::milliseconds(100 * n)));
+    let mut ticks = ticker(every_100ms)
+        .with_clock(clock.clone())
+        .with_missed_tick_behavior(MissedTickBehavior::Delay);
+    // late by 300 milliseconds
+    assert_eq!(
+        ticks.tick().await,
+        Some(start - TimeDelta::milliseconds(300))
+    );
+    // the next ones are put off by as much, one interval after now
+    clock.advance(TimeDelta::milliseconds(100));
+    assert_eq!(
+        ticks.tick().await,
+        Some(start + TimeDelta::milliseconds(100))
+    );
+    // late by 50 milliseconds more
+    clock.advance(TimeDelta::milliseconds(150));
+    assert_eq!(
+        ticks.tick().await,
+        Some(start + TimeDelta::milliseconds(200))
+    );
+    clock.advance(TimeDelta::milliseconds(100));
+    assert_eq!(
+        ticks.tick().await,
+        Some(start + TimeDelta::milliseconds(350))
+    );
+}
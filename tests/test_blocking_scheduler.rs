@@ -0,0 +1,179 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    time::{Duration, Instant},
+};
+
+use chrono::TimeDelta;
+use tsuki_scheduler::prelude::*;
+
+#[test]
+fn test_wake_at_deadline() {
+    let runner = BlockingSchedulerRunner::new(Scheduler::new(Local::new()))
+        .with_execute_duration(Duration::from_secs(3600));
+    let client = runner.client();
+    let (sender, receiver) = mpsc::channel();
+    let start = Instant::now();
+    let outcome = client
+        .add_task(
+            TaskUid::new(0),
+            Task::local(now() + TimeDelta::milliseconds(200), move || {
+                sender.send(Instant::now()).expect("receiver alive");
+            }),
+        )
+        .expect("runner alive");
+    assert!(matches!(outcome, AddOutcome::Added { .. }));
+    let fired = receiver
+        .recv_timeout(Duration::from_secs(1))
+        .expect("task fired");
+    let late = fired - start;
+    assert!(late >= Duration::from_millis(190), "{late:?}");
+    assert!(late < Duration::from_millis(290), "{late:?}");
+
+    // the task ran once and was removed
+    let mut scheduler = runner.shutdown();
+    assert!(scheduler.delete_task(TaskUid::new(0)).is_none());
+}
+
+#[test]
+fn test_client_commands() {
+    let runner = BlockingSchedulerRunner::new(Scheduler::new(Local::new()));
+    let client = runner.client();
+    let id = TaskUid::new(1);
+    let count = Arc::new(AtomicUsize::new(0));
+    let task = || {
+        let count = count.clone();
+        Task::local(Period::new(TimeDelta::hours(1), now()), move || {
+            count.fetch_add(1, Ordering::SeqCst);
+        })
+    };
+    // clients are `Send`
+    let added = std::thread::spawn({
+        let client = client.clone();
+        let task = task();
+        move || client.add_task(id, task)
+    })
+    .join()
+    .expect("not panicked");
+    let Ok(AddOutcome::Added { next_run }) = added else {
+        panic!("unexpected {added:?}")
+    };
+    assert!(matches!(
+        client.add_task(id, task()),
+        Ok(AddOutcome::Replaced { .. })
+    ));
+    let list = client.list().expect("runner alive");
+    assert_eq!(list.len(), 1);
+    assert!(list[0].time() > next_run);
+    assert_eq!(client.next_run(id), Ok(Some(list[0].time())));
+    // both tasks ran once, at their first time point
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+
+    let removed = client.remove_task(id).expect("runner alive");
+    assert_eq!(removed.map(|removed| removed.key), Some(id));
+    assert_eq!(client.next_run(id), Ok(None));
+
+    // a task waiting for a time point is removed too
+    let waiting = Task::local(Trigger::new(), || {});
+    assert_eq!(
+        client.add_task(id, waiting),
+        Ok(AddOutcome::Waiting { replaced: false })
+    );
+    assert_eq!(
+        client.remove_task(id),
+        Ok(Some(RemovedTask {
+            key: id,
+            next_run: None
+        }))
+    );
+
+    let scheduler = runner.shutdown();
+    assert!(scheduler.list().is_empty());
+    assert_eq!(client.list(), Err(Stopped));
+}
+
+#[test]
+fn test_shutdown_with_queued_request() {
+    let runner = BlockingSchedulerRunner::new(Scheduler::new(Local::new()));
+    let client = runner.client();
+    let (started, task_started) = mpsc::channel();
+    // keep the runner thread busy while the request is queued
+    let task = Task::local(now(), move || {
+        started.send(()).expect("receiver alive");
+        std::thread::sleep(Duration::from_millis(200));
+    });
+    client
+        .add_task(TaskUid::new(0), task)
+        .expect("runner alive");
+    task_started
+        .recv_timeout(Duration::from_secs(1))
+        .expect("task fired");
+    let (sender, replied) = mpsc::channel();
+    std::thread::spawn({
+        let client = client.clone();
+        move || sender.send(client.list())
+    });
+    std::thread::sleep(Duration::from_millis(50));
+    runner.shutdown();
+    assert_eq!(
+        replied.recv_timeout(Duration::from_secs(1)),
+        Ok(Err(Stopped))
+    );
+}
+
+#[test]
+fn test_task_panic_stops_runner() {
+    let runner = BlockingSchedulerRunner::new(Scheduler::new(Local::new()));
+    let client = runner.client();
+    client
+        .add_task(
+            TaskUid::new(0),
+            Task::local(now(), || panic!("task failed")),
+        )
+        .expect("runner alive");
+    let (sender, replied) = mpsc::channel();
+    std::thread::spawn(move || {
+        // the first calls may be queued before the thread dies
+        let replies: Vec<_> = (0..3).map(|_| client.list()).collect();
+        sender.send(replies)
+    });
+    let replies = replied
+        .recv_timeout(Duration::from_secs(1))
+        .expect("the client doesn't block");
+    assert_eq!(replies.last(), Some(&Err(Stopped)));
+    // the runner is dropped without joining the panicked thread
+    drop(runner);
+}
+
+#[cfg(feature = "thread")]
+#[test]
+fn test_thread_runtime() {
+    let scheduler = Scheduler::new(Thread::new()).with_handle_manager(vec![]);
+    let runner = BlockingSchedulerRunner::new(scheduler);
+    let client = runner.client();
+    let (sender, receiver) = mpsc::channel();
+    let sender = Arc::new(std::sync::Mutex::new(sender));
+    let start = now();
+    client
+        .add_task(
+            TaskUid::new(0),
+            Task::thread(
+                Iter::new((1..=3).map(move |n| start + TimeDelta::milliseconds(50 * n))),
+                move || {
+                    let sender = sender.lock().expect("not poisoned");
+                    sender.send(()).expect("receiver alive");
+                },
+            ),
+        )
+        .expect("runner alive");
+    for _ in 0..3 {
+        receiver
+            .recv_timeout(Duration::from_secs(1))
+            .expect("task fired");
+    }
+    let scheduler = runner.shutdown();
+    assert_eq!(scheduler.handle_manager.len(), 3);
+}
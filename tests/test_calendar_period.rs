@@ -0,0 +1,178 @@
+use chrono::{DateTime, TimeDelta, TimeZone, Utc, Weekday};
+use chrono_tz::Europe::Paris;
+use tsuki_scheduler::prelude::*;
+
+mod common;
+use common::{take, utc};
+
+#[test]
+fn test_month_end_overflow() {
+    let start = utc(2024, 1, 31, 9, 0);
+
+    let mut clamp = CalendarPeriod::months(1, start);
+    assert_eq!(
+        take(&mut clamp, 4),
+        vec![
+            utc(2024, 1, 31, 9, 0),
+            utc(2024, 2, 29, 9, 0),
+            utc(2024, 3, 31, 9, 0),
+            utc(2024, 4, 30, 9, 0)
+        ]
+    );
+
+    let mut skip = CalendarPeriod::months(1, start).with_overflow(DayOverflow::Skip);
+    assert_eq!(
+        take(&mut skip, 3),
+        vec![
+            utc(2024, 1, 31, 9, 0),
+            utc(2024, 3, 31, 9, 0),
+            utc(2024, 5, 31, 9, 0)
+        ]
+    );
+
+    let mut roll_over = CalendarPeriod::months(1, start).with_overflow(DayOverflow::RollOver);
+    assert_eq!(
+        take(&mut roll_over, 4),
+        vec![
+            utc(2024, 1, 31, 9, 0),
+            utc(2024, 3, 2, 9, 0),
+            utc(2024, 3, 31, 9, 0),
+            utc(2024, 5, 1, 9, 0)
+        ]
+    );
+}
+
+#[test]
+fn test_leap_day_every_year() {
+    let start = utc(2024, 2, 29, 0, 0);
+    let mut skip = CalendarPeriod::years(1, start).with_overflow(DayOverflow::Skip);
+    assert_eq!(
+        take(&mut skip, 3),
+        vec![
+            utc(2024, 2, 29, 0, 0),
+            utc(2028, 2, 29, 0, 0),
+            utc(2032, 2, 29, 0, 0)
+        ]
+    );
+    let mut clamp = CalendarPeriod::years(1, start);
+    assert_eq!(
+        take(&mut clamp, 2),
+        vec![utc(2024, 2, 29, 0, 0), utc(2025, 2, 28, 0, 0)]
+    );
+}
+
+#[test]
+fn test_nth_weekday() {
+    let start = utc(2025, 1, 1, 8, 0);
+    // every quarter on the first monday
+    let mut first_monday =
+        CalendarPeriod::months(3, start).on(MonthDay::NthWeekday(1, Weekday::Mon));
+    assert_eq!(
+        take(&mut first_monday, 4),
+        vec![
+            utc(2025, 1, 6, 8, 0),
+            utc(2025, 4, 7, 8, 0),
+            utc(2025, 7, 7, 8, 0),
+            utc(2025, 10, 6, 8, 0)
+        ]
+    );
+    // every month on the last friday
+    let mut last_friday =
+        CalendarPeriod::months(1, start).on(MonthDay::NthWeekday(-1, Weekday::Fri));
+    assert_eq!(
+        take(&mut last_friday, 2),
+        vec![utc(2025, 1, 31, 8, 0), utc(2025, 2, 28, 8, 0)]
+    );
+    // the 5th monday only exists in some months
+    let mut fifth_monday = CalendarPeriod::months(1, start)
+        .on(MonthDay::NthWeekday(5, Weekday::Mon))
+        .with_overflow(DayOverflow::Skip);
+    assert_eq!(
+        take(&mut fifth_monday, 2),
+        vec![utc(2025, 3, 31, 8, 0), utc(2025, 6, 30, 8, 0)]
+    );
+}
+
+#[test]
+fn test_local_time_is_kept_across_dst() {
+    // 09:00 in Paris is 08:00 UTC in winter and 07:00 UTC in summer
+    let start = Paris
+        .with_ymd_and_hms(2025, 3, 29, 9, 0, 0)
+        .single()
+        .expect("valid time");
+    let mut daily = CalendarPeriod::days(1, start);
+    assert_eq!(
+        take(&mut daily, 2),
+        vec![utc(2025, 3, 29, 8, 0), utc(2025, 3, 30, 7, 0)]
+    );
+
+    // 02:30 doesn't exist on the spring-forward day, it is moved to 03:30
+    let start = Paris
+        .with_ymd_and_hms(2025, 3, 29, 2, 30, 0)
+        .single()
+        .expect("valid time");
+    let mut daily = CalendarPeriod::days(1, start);
+    daily.next();
+    let shifted = daily.next().expect("infinite schedule");
+    assert_eq!(
+        shifted,
+        Paris
+            .with_ymd_and_hms(2025, 3, 30, 3, 30, 0)
+            .single()
+            .expect("valid time")
+    );
+}
+
+#[test]
+fn test_forward_to_matches_stepping() {
+    let start = utc(2000, 1, 31, 12, 0);
+    let target = utc(2123, 6, 15, 0, 0);
+    for (unit, step) in [
+        (CalendarUnit::Day, 3),
+        (CalendarUnit::Week, 2),
+        (CalendarUnit::Month, 1),
+        (CalendarUnit::Month, 5),
+        (CalendarUnit::Year, 1),
+    ] {
+        for overflow in [DayOverflow::Clamp, DayOverflow::Skip, DayOverflow::RollOver] {
+            let mut jumped = CalendarPeriod::new(unit, step, start).with_overflow(overflow);
+            jumped.forward_to(target);
+            let mut walked = CalendarPeriod::new(unit, step, start).with_overflow(overflow);
+            while walked.peek_next().is_some_and(|next| next <= target) {
+                walked.next();
+            }
+            assert_eq!(
+                take(&mut jumped, 5),
+                take(&mut walked, 5),
+                "{unit:?} x {step}, {overflow:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_never_matching_rule_is_exhausted() {
+    // the 31st of june never exists
+    let start: DateTime<Utc> = utc(2025, 6, 1, 0, 0);
+    let mut schedule = CalendarPeriod::years(1, start)
+        .on(MonthDay::Day(31))
+        .with_overflow(DayOverflow::Skip);
+    assert_eq!(schedule.next(), None);
+
+    // but it composes with the other combinators when it matches
+    let mut schedule = CalendarPeriod::weeks(1, start).before(start + TimeDelta::days(15));
+    assert_eq!(take(&mut schedule, 5).len(), 3);
+}
+
+#[test]
+#[should_panic(expected = "nth weekday must be in 1..=5 or -5..=-1")]
+fn test_zeroth_weekday_is_rejected() {
+    let _ =
+        CalendarPeriod::months(1, utc(2025, 1, 1, 0, 0)).on(MonthDay::NthWeekday(0, Weekday::Mon));
+}
+
+#[test]
+#[should_panic(expected = "day of the month must be in 1..=31")]
+fn test_zeroth_day_is_rejected() {
+    let _ = CalendarPeriod::months(1, utc(2025, 1, 1, 0, 0)).on(MonthDay::Day(0));
+}
@@ -0,0 +1,150 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use chrono::{TimeDelta, Utc};
+use tsuki_scheduler::prelude::*;
+
+mod common;
+use common::utc;
+
+#[test]
+fn test_manual_clock() {
+    let clock = ManualClock::new(utc(2025, 1, 1, 0, 0));
+    let shared = clock.clone();
+    clock.advance(TimeDelta::hours(1));
+    assert_eq!(shared.now(), utc(2025, 1, 1, 1, 0));
+    // it may go backward
+    shared.set(utc(2024, 1, 1, 0, 0));
+    assert_eq!(clock.now(), utc(2024, 1, 1, 0, 0));
+
+    let offset = OffsetClock::with_inner(clock.clone(), TimeDelta::days(-1));
+    assert_eq!(offset.now(), utc(2023, 12, 31, 0, 0));
+}
+
+#[test]
+fn test_scheduler_with_clock() {
+    let clock = ManualClock::new(utc(2025, 1, 1, 0, 0));
+    let mut scheduler = Scheduler::new(Local::new()).with_clock(clock.clone());
+    assert_eq!(scheduler.now(), utc(2025, 1, 1, 0, 0));
+
+    let count = Arc::new(AtomicUsize::new(0));
+    let counter = count.clone();
+    // a virtual start in the past of the system clock
+    let schedule = TimeDelta::minutes(10).into_schedule_with_clock(&clock);
+    scheduler.add_task(
+        TaskUid::new(0),
+        Task::local(schedule, move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }),
+    );
+    scheduler.execute_by_now();
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+    clock.advance(TimeDelta::minutes(25));
+    scheduler.execute_by_now();
+    assert_eq!(count.load(Ordering::SeqCst), 3);
+    // nothing runs while the clock stands still
+    scheduler.execute_by_now();
+    assert_eq!(count.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn test_schedule_constructors() {
+    let clock = ManualClock::new(utc(2025, 1, 1, 0, 0));
+    let mut period = Period::new_with_clock(TimeDelta::hours(1), utc(2025, 1, 1, 0, 30), &clock);
+    assert_eq!(period.next(), Some(utc(2025, 1, 1, 0, 30)));
+
+    // the future is checked against the clock
+    let late = std::panic::catch_unwind(|| {
+        Period::new_with_clock(TimeDelta::hours(1), utc(2024, 1, 1, 0, 0), &clock)
+    });
+    assert!(late.is_err());
+
+    // a conversion without the current time ignores the clock
+    let mut once = utc(2030, 1, 1, 0, 0).into_schedule_with_clock(&clock);
+    assert_eq!(once.next(), Some(utc(2030, 1, 1, 0, 0)));
+}
+
+#[cfg(feature = "cron")]
+#[test]
+fn test_cron_with_clock() {
+    use std::str::FromStr;
+    let clock = OffsetClock::with_inner(
+        ManualClock::new(utc(2025, 1, 1, 0, 0)),
+        TimeDelta::minutes(90),
+    );
+    let schedule = cron::Schedule::from_str("0 0 * * * *").expect("valid cron");
+    let mut cron = Cron::from_cron_schedule_with_clock(schedule.clone(), Utc, &clock);
+    assert_eq!(cron.next(), Some(utc(2025, 1, 1, 2, 0)));
+    let mut cron = schedule.into_schedule_with_clock(&clock);
+    assert_eq!(cron.next(), Some(utc(2025, 1, 1, 2, 0)));
+}
+
+#[test]
+fn test_run_context_extractors() {
+    use std::sync::Mutex;
+    let clock = ManualClock::new(utc(2025, 1, 1, 0, 0));
+    let mut scheduler = Scheduler::new(Local::new()).with_clock(clock.clone());
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let dispatched = Arc::new(Mutex::new(Vec::new()));
+    let times = [
+        utc(2025, 1, 1, 1, 0),
+        utc(2025, 1, 1, 2, 0),
+        utc(2025, 1, 1, 3, 0),
+    ];
+    scheduler.add_task(
+        TaskUid::new(0),
+        Task::new(times, {
+            let seen = seen.clone();
+            move |count: RunCount, previous: PreviousRun, lateness: Lateness, next: NextPlanned| {
+                seen.lock()
+                    .expect("not poisoned")
+                    .push((count, previous, lateness, next));
+            }
+        }),
+    );
+    scheduler.add_task(
+        TaskUid::new(1),
+        Task::new(times, {
+            let dispatched = dispatched.clone();
+            move |at: Dispatched| dispatched.lock().expect("not poisoned").push(at)
+        }),
+    );
+    clock.set(utc(2025, 1, 1, 1, 5));
+    scheduler.execute_by_now();
+    clock.set(utc(2025, 1, 1, 3, 0));
+    scheduler.execute_by_now();
+    let seen = seen.lock().expect("not poisoned");
+    assert_eq!(
+        *seen,
+        [
+            (
+                RunCount(1),
+                PreviousRun(None),
+                Lateness(TimeDelta::minutes(5)),
+                NextPlanned(Some(times[1]))
+            ),
+            (
+                RunCount(2),
+                PreviousRun(Some(times[0])),
+                Lateness(TimeDelta::hours(1)),
+                NextPlanned(Some(times[2]))
+            ),
+            (
+                RunCount(3),
+                PreviousRun(Some(times[1])),
+                Lateness(TimeDelta::zero()),
+                NextPlanned(None)
+            ),
+        ]
+    );
+    assert_eq!(
+        *dispatched.lock().expect("not poisoned"),
+        [
+            Dispatched(utc(2025, 1, 1, 1, 5)),
+            Dispatched(utc(2025, 1, 1, 3, 0)),
+            Dispatched(utc(2025, 1, 1, 3, 0)),
+        ]
+    );
+}
@@ -0,0 +1,72 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use chrono::TimeDelta;
+use tsuki_scheduler::prelude::*;
+
+mod common;
+use common::{at, every};
+
+#[test]
+fn test_commands_after_execution() {
+    let clock = ManualClock::new(at(0, 0));
+    let mut scheduler = Scheduler::new(Local::new()).with_clock(clock.clone());
+    let runs = Arc::new(AtomicUsize::new(0));
+    let (poller, follow_up) = (TaskUid::new(0), TaskUid::new(1));
+    scheduler.add_task(
+        poller,
+        Task::new(every(TimeDelta::minutes(1), at(0, 1)), {
+            let runs = runs.clone();
+            move |ctx: SchedulerCtx<Local>, count: RunCount| {
+                runs.fetch_add(1, Ordering::SeqCst);
+                match count.0 {
+                    // slow down
+                    1 => ctx.reschedule_self(every(TimeDelta::hours(1), at(1, 1))),
+                    // done, hand over to a one-off task
+                    _ => {
+                        let runs = runs.clone();
+                        ctx.add_task(
+                            follow_up,
+                            Task::local([at(2, 0)], move || {
+                                runs.fetch_add(100, Ordering::SeqCst);
+                            }),
+                        );
+                        ctx.delete_self();
+                    }
+                }
+            }
+        }),
+    );
+    clock.set(at(0, 1));
+    scheduler.execute_by_now();
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+    // rescheduled by the first run, the period ran only once
+    assert_eq!(scheduler.next_run(poller), Some(at(1, 1)));
+
+    clock.set(at(1, 1));
+    scheduler.execute_by_now();
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+    assert_eq!(scheduler.next_run(poller), None);
+    assert_eq!(scheduler.next_run(follow_up), Some(at(2, 0)));
+
+    clock.set(at(2, 0));
+    scheduler.execute_by_now();
+    assert_eq!(runs.load(Ordering::SeqCst), 102);
+    assert!(scheduler.list().is_empty());
+}
+
+#[test]
+fn test_set_schedule() {
+    let mut scheduler = Scheduler::new(Local::new());
+    let id = TaskUid::new(0);
+    assert_eq!(scheduler.set_schedule(id, [at(1, 0)]), None);
+    scheduler.add_task(id, Task::local([at(1, 0)], || {}));
+    assert_eq!(scheduler.set_schedule(id, [at(3, 0)]), Some(at(3, 0)));
+    assert_eq!(scheduler.list().len(), 1);
+    assert_eq!(scheduler.next_deadline(), Some(at(3, 0)));
+    // an exhausted schedule removes the task
+    assert_eq!(scheduler.set_schedule(id, Never), None);
+    assert!(scheduler.next_run(id).is_none());
+}
@@ -0,0 +1,70 @@
+use std::sync::{Arc, Mutex};
+
+use chrono::TimeDelta;
+use tsuki_scheduler::{TaskRun, prelude::*};
+
+mod common;
+use common::{at, every};
+
+#[test]
+fn test_local_feedback() {
+    let clock = ManualClock::new(at(0, 0));
+    let mut scheduler = Scheduler::new(Local::new()).with_clock(clock.clone());
+    let id = TaskUid::new(0);
+    let decisions = Arc::new(Mutex::new(vec![
+        NextRun::Stop,
+        NextRun::Default,
+        NextRun::At(at(5, 0)),
+        NextRun::In(TimeDelta::hours(2)),
+    ]));
+    scheduler.add_task(
+        id,
+        Task::local_feedback(every(TimeDelta::minutes(1), at(0, 1)), {
+            let decisions = decisions.clone();
+            move |_| decisions.lock().unwrap().pop().expect("a decision left")
+        }),
+    );
+    assert_eq!(scheduler.next_run(id), Some(at(0, 1)));
+
+    // in 2 hours from the dispatch, late by 4 minutes
+    clock.set(at(0, 5));
+    scheduler.execute_by_now();
+    assert_eq!(scheduler.next_run(id), Some(at(2, 5)));
+
+    clock.set(at(2, 5));
+    scheduler.execute_by_now();
+    assert_eq!(scheduler.next_run(id), Some(at(5, 0)));
+
+    // the default schedule is forwarded past the feedback time points
+    clock.set(at(5, 0));
+    scheduler.execute_by_now();
+    assert_eq!(scheduler.next_run(id), Some(at(5, 1)));
+
+    clock.set(at(5, 1));
+    scheduler.execute_by_now();
+    assert_eq!(scheduler.next_run(id), None);
+    assert!(decisions.lock().unwrap().is_empty());
+}
+
+#[test]
+fn test_feedback_without_default() {
+    let clock = ManualClock::new(at(0, 0));
+    let mut scheduler = Scheduler::new(Local::new()).with_clock(clock.clone());
+    let id = TaskUid::new(0);
+    scheduler.add_task(
+        id,
+        Task::local_feedback([at(1, 0)], |run: TaskRun| {
+            if run.time() < at(3, 0) {
+                NextRun::In(TimeDelta::hours(1))
+            } else {
+                // the default schedule is exhausted
+                NextRun::Default
+            }
+        }),
+    );
+    for hour in 1..=3 {
+        clock.set(at(hour, 0));
+        scheduler.execute_by_now();
+    }
+    assert_eq!(scheduler.next_run(id), None);
+}
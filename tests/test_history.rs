@@ -0,0 +1,107 @@
+use chrono::TimeDelta;
+use tsuki_scheduler::prelude::*;
+
+mod common;
+use common::at;
+
+#[test]
+fn test_history_ring() {
+    let clock = ManualClock::new(at(0, 0));
+    let mut scheduler = Scheduler::new(Local::new())
+        .with_handle_manager(KeyedHandles::new())
+        .with_clock(clock.clone())
+        .with_history(3);
+    let id = TaskUid::new(0);
+    let every_hour = Iter::new((0..).map(|h| at(0, 0) + TimeDelta::hours(h)));
+    scheduler.add_task(id, Task::local(every_hour, || {}));
+    for _ in 0..5 {
+        // started 10 minutes late
+        clock.advance(TimeDelta::minutes(10));
+        scheduler.execute_by_now();
+        clock.advance(TimeDelta::minutes(50));
+    }
+    let history = scheduler.history(id);
+    let times: Vec<_> = history.iter().map(|record| record.run.time()).collect();
+    assert_eq!(times, [at(2, 0), at(3, 0), at(4, 0)]);
+    for record in &history[..2] {
+        assert_eq!(record.outcome, RunOutcome::Finished);
+        assert_eq!(record.lateness(), TimeDelta::minutes(10));
+        // a local run ends before it is handed to the handle manager
+        assert_eq!(record.duration(), Some(TimeDelta::zero()));
+    }
+    let last = scheduler.last_run(id).expect("recorded");
+    assert_eq!(last, history[2]);
+    assert_eq!(last.outcome, RunOutcome::Running);
+    assert_eq!(last.started, at(4, 10));
+    assert!(scheduler.history(TaskUid::new(1)).is_empty());
+
+    // reported at the next execution, by the time it ended
+    clock.advance(TimeDelta::minutes(20));
+    scheduler.reap();
+    let last = scheduler.last_run(id).expect("recorded");
+    assert_eq!(last.outcome, RunOutcome::Finished);
+    assert_eq!(last.ended, Some(at(4, 10)));
+
+    // removed with the task
+    scheduler.delete_task(id);
+    assert!(scheduler.history(id).is_empty());
+    assert!(scheduler.last_run(id).is_none());
+}
+
+#[test]
+fn test_without_history() {
+    let mut scheduler = Scheduler::new(Local::new()).with_handle_manager(KeyedHandles::new());
+    let id = TaskUid::new(0);
+    scheduler.add_task(id, Task::local([now()], || {}));
+    scheduler.execute_by_now();
+    assert!(scheduler.history(id).is_empty());
+    assert!(scheduler.last_run(id).is_none());
+}
+
+#[test]
+fn test_trigger_now() {
+    use std::sync::{Arc, Mutex};
+    let clock = ManualClock::new(at(0, 0));
+    let mut scheduler = Scheduler::new(Local::new())
+        .with_handle_manager(KeyedHandles::new())
+        .with_clock(clock.clone())
+        .with_history(10);
+    let id = TaskUid::new(0);
+    let every_hour = Iter::new((1..).map(|h| at(0, 0) + TimeDelta::hours(h)));
+    let runs = Arc::new(Mutex::new(Vec::new()));
+    scheduler.add_task(
+        id,
+        Task::new(every_hour, {
+            let runs = runs.clone();
+            move |manual: Manual, count: RunCount, previous: PreviousRun, next: NextPlanned| {
+                runs.lock()
+                    .unwrap()
+                    .push((manual.0, count.0, previous.0, next.0));
+            }
+        }),
+    );
+    assert!(scheduler.trigger_now(TaskUid::new(1)).is_none());
+
+    clock.set(at(0, 30));
+    let run = scheduler.trigger_now(id).expect("the task exists");
+    assert!(run.is_manual());
+    assert_eq!(run.time(), at(0, 30));
+    // the schedule is left untouched
+    assert_eq!(scheduler.next_run(id), Some(at(1, 0)));
+
+    clock.set(at(1, 0));
+    scheduler.execute_by_now();
+    assert_eq!(scheduler.next_run(id), Some(at(2, 0)));
+    assert_eq!(
+        *runs.lock().unwrap(),
+        [
+            (true, 1, None, Some(at(1, 0))),
+            (false, 2, None, Some(at(2, 0))),
+        ]
+    );
+    let history = scheduler.history(id);
+    assert_eq!(history.len(), 2);
+    assert!(history[0].run.is_manual());
+    assert_eq!(history[0].outcome, RunOutcome::Finished);
+    assert!(!history[1].run.is_manual());
+}
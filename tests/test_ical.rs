@@ -0,0 +1,272 @@
+use chrono::{TimeDelta, Utc};
+use tsuki_scheduler::{
+    ical::{Calendar, Export},
+    prelude::*,
+};
+
+mod common;
+use common::{take, utc};
+
+const MAINTENANCE: &str = "\
+BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:-//Example//Maintenance//EN\r
+BEGIN:VTIMEZONE\r
+TZID:Europe/Paris\r
+BEGIN:STANDARD\r
+DTSTART:19701025T030000\r
+TZOFFSETFROM:+0200\r
+TZOFFSETTO:+0100\r
+END:STANDARD\r
+END:VTIMEZONE\r
+BEGIN:VEVENT\r
+UID:db-maintenance@example.com\r
+SUMMARY:Database maintenance\\, weekly\r
+DTSTART;TZID=Europe/Paris:20250105T020000\r
+DTEND;TZID=Europe/Paris:20250105T040000\r
+RRULE:FREQ=WEEKLY;BYDAY=SU;COUNT=4\r
+EXDATE;TZID=Europe/Paris:20250112T020000\r
+BEGIN:VALARM\r
+ACTION:DISPLAY\r
+TRIGGER:-PT15M\r
+DESCRIPTION:Reminder\r
+END:VALARM\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:db-maintenance@example.com\r
+RECURRENCE-ID;TZID=Europe/Paris:20250119T020000\r
+DTSTART;TZID=Europe/Paris:20250119T050000\r
+DTEND;TZID=Europe/Paris:20250119T070000\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:network-freeze@example.com\r
+SUMMARY:Network\r
+  freeze\r
+DTSTART:20250201T000000Z\r
+DURATION:P1DT12H\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:holiday@example.com\r
+DTSTART;VALUE=DATE:20250501\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+#[test]
+fn test_parse_events() {
+    let calendar: Calendar = MAINTENANCE.parse().expect("valid calendar");
+    let events = calendar.events();
+    assert_eq!(events.len(), 3);
+
+    let maintenance = &events[0];
+    assert_eq!(maintenance.uid(), "db-maintenance@example.com");
+    assert_eq!(maintenance.summary(), Some("Database maintenance, weekly"));
+    assert_eq!(maintenance.duration(), Some(TimeDelta::hours(2)));
+    // the 12th is excluded, the 19th is moved to 05:00
+    assert_eq!(
+        take(&mut maintenance.schedule(), 10),
+        vec![
+            utc(2025, 1, 5, 1, 0),
+            utc(2025, 1, 19, 4, 0),
+            utc(2025, 1, 26, 1, 0)
+        ]
+    );
+
+    let freeze = &events[1];
+    assert_eq!(freeze.summary(), Some("Network freeze"));
+    assert_eq!(freeze.duration(), Some(TimeDelta::hours(36)));
+
+    // an all-day event lasts one day
+    let holiday = &events[2];
+    assert_eq!(holiday.duration(), Some(TimeDelta::days(1)));
+}
+
+#[test]
+fn test_stable_task_uid() {
+    let calendar: Calendar = MAINTENANCE.parse().expect("valid calendar");
+    let again: Calendar = MAINTENANCE.parse().expect("valid calendar");
+    let uids: Vec<_> = calendar.events().iter().map(|e| e.task_uid()).collect();
+    let uids_again: Vec<_> = again.events().iter().map(|e| e.task_uid()).collect();
+    assert_eq!(uids, uids_again);
+    assert_eq!(uids[0], TaskUid::from_name("db-maintenance@example.com"));
+    assert_ne!(uids[0], uids[1]);
+
+    let tasks = calendar.tasks::<Local, _, _>(|| {});
+    assert_eq!(tasks.iter().map(|(uid, _)| *uid).collect::<Vec<_>>(), uids);
+}
+
+#[test]
+fn test_windows() {
+    let calendar: Calendar = MAINTENANCE.parse().expect("valid calendar");
+    let maintenance = &calendar.events()[0];
+    let first = maintenance.window().expect("has a duration");
+    assert!(first.contains(utc(2025, 1, 5, 1, 0)));
+    assert!(!first.contains(utc(2025, 1, 5, 3, 0)));
+
+    let windows = calendar.windows();
+    assert!(windows.contains(utc(2025, 1, 5, 2, 59)));
+    assert!(!windows.contains(utc(2025, 1, 12, 1, 30)));
+    assert!(windows.contains(utc(2025, 1, 19, 5, 0)));
+    assert!(!windows.contains(utc(2025, 1, 19, 1, 30)));
+    assert!(windows.contains(utc(2025, 2, 2, 11, 59)));
+    assert!(!windows.contains(utc(2025, 2, 2, 12, 0)));
+
+    // keep an hourly job out of the windows
+    let hourly = (0..).map(|hour| utc(2025, 1, 5, 0, 0) + TimeDelta::hours(hour));
+    let mut outside =
+        Iter::new(hourly).filtered_in(timeset::Universal.difference(calendar.windows()));
+    assert_eq!(
+        take(&mut outside, 3),
+        vec![
+            utc(2025, 1, 5, 0, 0),
+            utc(2025, 1, 5, 3, 0),
+            utc(2025, 1, 5, 4, 0)
+        ]
+    );
+}
+
+#[test]
+fn test_from_file() {
+    let path = std::env::temp_dir().join(format!("tsuki-test-{}.ics", std::process::id()));
+    std::fs::write(&path, MAINTENANCE).expect("writable temp dir");
+    let calendar = Calendar::from_file(&path);
+    std::fs::remove_file(&path).expect("removable temp file");
+    assert_eq!(calendar.expect("valid calendar").events().len(), 3);
+
+    assert!(Calendar::from_file(std::env::temp_dir().join("tsuki-missing.ics")).is_err());
+}
+
+#[test]
+fn test_invalid_calendar() {
+    let unbalanced =
+        "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:a\nDTSTART:20250101T000000Z\nEND:VCALENDAR";
+    assert!(unbalanced.parse::<Calendar>().is_err());
+    let missing_start = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:a\nEND:VEVENT\nEND:VCALENDAR";
+    assert!(missing_start.parse::<Calendar>().is_err());
+    let unknown_tz = "BEGIN:VEVENT\nUID:a\nDTSTART;TZID=Mars/Olympus:20250101T000000\nEND:VEVENT";
+    assert!(unknown_tz.parse::<Calendar>().is_err());
+}
+
+#[test]
+fn test_export_exact_rule() {
+    let start = Utc::now() + TimeDelta::hours(1);
+    let mut export = Export::new().with_dtstamp(utc(2025, 1, 1, 0, 0));
+    export.push_schedule(
+        "backup",
+        "Backup",
+        Period::new(TimeDelta::days(2), start),
+        10,
+    );
+    export.push_schedule(
+        "minutes",
+        "Every 90 minutes",
+        Period::new(TimeDelta::minutes(90), start),
+        10,
+    );
+    let ics = export.to_string();
+    assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+    assert!(ics.contains("DTSTAMP:20250101T000000Z\r\n"));
+    assert!(ics.contains("RRULE:FREQ=DAILY;INTERVAL=2\r\n"));
+    assert!(ics.contains("RRULE:FREQ=MINUTELY;INTERVAL=90\r\n"));
+
+    // reading it back gives the same time points, to the second
+    let calendar: Calendar = ics.parse().expect("valid calendar");
+    let mut imported = calendar.events()[0].schedule();
+    let mut period = Period::new(TimeDelta::days(2), start);
+    for _ in 0..5 {
+        let expected = period.next().expect("infinite").timestamp();
+        assert_eq!(imported.next().expect("infinite").timestamp(), expected);
+    }
+}
+
+#[cfg(feature = "cron")]
+#[test]
+fn test_export_cron() {
+    let cron = Cron::utc_from_cron_expr("0 30 9,17 * * Mon-Fri").expect("valid cron");
+    let rrule = cron.dyn_box().to_rrule().expect("a simple cron expression");
+    let mut expected = Cron::utc_from_cron_expr("0 30 9,17 * * Mon-Fri").expect("valid cron");
+    let mut rrule_schedule = rrule.clone();
+    assert_eq!(take(&mut rrule_schedule, 20), take(&mut expected, 20));
+    assert!(rrule.to_string().contains("BYDAY=MO,TU,WE,TH,FR"));
+}
+
+#[test]
+fn test_export_occurrences() {
+    let start = Utc::now() + TimeDelta::hours(1);
+    let throttled = Period::new(TimeDelta::minutes(20), start).throttling(TimeDelta::hours(1));
+    let mut export = Export::new();
+    export.push_schedule("report", "Weekly report; draft", throttled, 30);
+    let ics = export.to_string();
+    assert!(!ics.contains("RRULE"));
+    assert!(ics.contains("SUMMARY:Weekly report\\; draft\r\n"));
+    // the long RDATE line is folded
+    assert!(ics.lines().all(|line| line.len() <= 75));
+
+    let calendar: Calendar = ics.parse().expect("valid calendar");
+    let event = &calendar.events()[0];
+    assert_eq!(event.summary(), Some("Weekly report; draft"));
+    let mut expected = Period::new(TimeDelta::minutes(20), start).throttling(TimeDelta::hours(1));
+    let expected: Vec<_> = take(&mut expected, 30)
+        .into_iter()
+        .map(|time| time.timestamp())
+        .collect();
+    let imported: Vec<_> = take(&mut event.schedule(), 100)
+        .into_iter()
+        .map(|time| time.timestamp())
+        .collect();
+    assert_eq!(imported, expected);
+}
+
+#[test]
+fn test_export_busy() {
+    let calendar: Calendar = MAINTENANCE.parse().expect("valid calendar");
+    let mut export = Export::new();
+    export
+        .push_busy(
+            "maintenance",
+            "Maintenance",
+            &calendar.windows(),
+            utc(2025, 1, 1, 0, 0),
+            utc(2025, 2, 1, 12, 0),
+        )
+        .expect("windows are intervals");
+    let ics = export.to_string();
+    let starts: Vec<_> = ics
+        .lines()
+        .filter_map(|line| line.strip_prefix("DTSTART:"))
+        .collect();
+    assert_eq!(
+        starts,
+        [
+            "20250105T010000Z",
+            "20250119T040000Z",
+            "20250126T010000Z",
+            "20250201T000000Z"
+        ]
+    );
+    assert!(ics.contains("DTEND:20250201T120000Z\r\n"));
+
+    let points = timeset::Discrete::new([utc(2025, 1, 1, 0, 0)]);
+    assert!(
+        export
+            .push_busy(
+                "points",
+                "",
+                &points,
+                utc(2025, 1, 1, 0, 0),
+                utc(2025, 2, 1, 0, 0)
+            )
+            .is_err()
+    );
+
+    // imported events export back
+    let mut export = Export::new();
+    export.push_event(&calendar.events()[0]);
+    let again: Calendar = export.to_string().parse().expect("valid calendar");
+    let event = &again.events()[0];
+    assert_eq!(event.duration(), Some(TimeDelta::hours(2)));
+    assert_eq!(
+        take(&mut event.schedule(), 10),
+        take(&mut calendar.events()[0].schedule(), 10)
+    );
+}
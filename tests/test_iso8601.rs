@@ -0,0 +1,190 @@
+use chrono::{TimeDelta, Utc};
+use tsuki_scheduler::prelude::*;
+
+mod common;
+use common::{take, utc};
+
+#[test]
+fn test_parse_duration() {
+    let duration: IsoDuration = "P1Y2M10DT2H30M".parse().expect("valid duration");
+    assert_eq!(duration.years, Some(1));
+    assert_eq!(duration.months, Some(2));
+    assert_eq!(duration.days, Some(10));
+    assert_eq!(duration.calendar_months(), Some(14));
+    assert_eq!(duration.as_time_delta(), None);
+    assert_eq!(duration.to_string(), "P1Y2M10DT2H30M");
+
+    let duration: IsoDuration = "PT1,5S".parse().expect("valid duration");
+    assert_eq!(
+        duration.as_time_delta(),
+        Some(TimeDelta::milliseconds(1500))
+    );
+    assert_eq!(duration.to_string(), "PT1.5S");
+
+    let duration: IsoDuration = "P2W".parse().expect("valid duration");
+    assert_eq!(duration.as_time_delta(), Some(TimeDelta::weeks(2)));
+
+    for invalid in ["", "P", "PT", "P1H", "PT1D", "P1.5D", "P1M1M", "1D", "P1DT"] {
+        assert!(invalid.parse::<IsoDuration>().is_err(), "{invalid}");
+    }
+
+    // too many months
+    assert!("P400000000Y".parse::<IsoDuration>().is_err());
+    let duration = IsoDuration {
+        years: Some(400_000_000),
+        ..Default::default()
+    };
+    assert_eq!(duration.calendar_months(), None);
+    assert_eq!(duration.into_schedule().next(), None);
+}
+
+#[test]
+fn test_round_trip() {
+    for text in [
+        "R5/2024-03-01T00:00:00Z/PT1H30M",
+        "R/P1M/2025-01-01T00:00:00Z",
+        "R2/2024-03-01T00:00:00+01:00/2024-03-01T00:00:00.500+01:00",
+        "R10/2024-03-01T08:00:00-05:00/P1DT12H",
+    ] {
+        let interval: RepeatingInterval = text.parse().expect("valid interval");
+        assert_eq!(interval.to_string(), text);
+    }
+    for invalid in [
+        "R5/2024-03-01T00:00:00Z",
+        "5/2024-03-01T00:00:00Z/PT1H",
+        "Rx/2024-03-01T00:00:00Z/PT1H",
+        "R/PT1H/PT1H",
+        "R/2024-03-01T00:00:00Z/PT0S",
+        "R/2024-03-01T00:00:00Z/2024-03-01T00:00:00Z",
+        "R/2024-03-01/PT1H",
+        "R/2025-01-01T00:00:00Z/P400000000Y",
+    ] {
+        assert!(invalid.parse::<RepeatingInterval>().is_err(), "{invalid}");
+    }
+}
+
+#[test]
+fn test_count() {
+    let interval: RepeatingInterval = "R5/2024-03-01T00:00:00Z/PT1H30M".parse().unwrap();
+    let mut schedule = interval.into_schedule();
+    let times = take(&mut schedule, 10);
+    assert_eq!(times.len(), 5);
+    assert_eq!(times[0], utc(2024, 3, 1, 0, 0));
+    assert_eq!(times[4], utc(2024, 3, 1, 6, 0));
+
+    // a start and an end repeat every `end - start`
+    let interval: RepeatingInterval = "R3/2024-03-01T00:00:00+01:00/2024-03-01T00:20:00+01:00"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        take(&mut interval.into_schedule(), 10),
+        vec![
+            utc(2024, 2, 29, 23, 0),
+            utc(2024, 2, 29, 23, 20),
+            utc(2024, 2, 29, 23, 40)
+        ]
+    );
+}
+
+#[test]
+fn test_unbounded_skips_the_past() {
+    let clock = ManualClock::new(utc(2025, 1, 1, 0, 30) + TimeDelta::seconds(30));
+    let interval: RepeatingInterval = "R/2020-01-01T00:00:00Z/PT1M".parse().unwrap();
+    assert_eq!(
+        take(&mut interval.into_schedule_with_clock(&clock), 2),
+        vec![utc(2025, 1, 1, 0, 31), utc(2025, 1, 1, 0, 32)]
+    );
+    let interval: RepeatingInterval = "R/2020-01-01T00:00:00Z/2020-01-01T01:00:00Z"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        interval.into_schedule_with_clock(&clock).next(),
+        Some(utc(2025, 1, 1, 1, 0))
+    );
+    let interval: RepeatingInterval = "R/2020-01-31T00:00:00Z/P1M".parse().unwrap();
+    assert_eq!(
+        interval.into_schedule_with_clock(&clock).next(),
+        Some(utc(2025, 1, 31, 0, 0))
+    );
+}
+
+#[test]
+fn test_months() {
+    // the day of the month is clamped, it doesn't drift
+    let interval: RepeatingInterval = "R4/2025-01-31T09:00:00Z/P1M".parse().unwrap();
+    assert_eq!(
+        take(&mut interval.into_schedule(), 10),
+        vec![
+            utc(2025, 1, 31, 9, 0),
+            utc(2025, 2, 28, 9, 0),
+            utc(2025, 3, 31, 9, 0),
+            utc(2025, 4, 30, 9, 0)
+        ]
+    );
+    // months and days together
+    let interval: RepeatingInterval = "R3/2025-01-01T00:00:00Z/P1M1D".parse().unwrap();
+    assert_eq!(
+        take(&mut interval.into_schedule(), 10),
+        vec![
+            utc(2025, 1, 1, 0, 0),
+            utc(2025, 2, 2, 0, 0),
+            utc(2025, 3, 3, 0, 0)
+        ]
+    );
+}
+
+#[test]
+fn test_backward() {
+    // the last interval ends at `end`
+    let interval: RepeatingInterval = "R3/PT1H/2025-01-01T00:00:00Z".parse().unwrap();
+    assert_eq!(
+        take(&mut interval.into_schedule(), 10),
+        vec![
+            utc(2024, 12, 31, 21, 0),
+            utc(2024, 12, 31, 22, 0),
+            utc(2024, 12, 31, 23, 0)
+        ]
+    );
+    let interval: RepeatingInterval = "R3/P1M/2025-03-31T00:00:00Z".parse().unwrap();
+    assert_eq!(
+        take(&mut interval.into_schedule(), 10),
+        vec![
+            utc(2024, 12, 31, 0, 0),
+            utc(2025, 1, 31, 0, 0),
+            utc(2025, 2, 28, 0, 0)
+        ]
+    );
+
+    // without a count, from now until `end`
+    let end = Utc::now() + TimeDelta::minutes(5 * 60 + 30);
+    let interval: RepeatingInterval = format!("R/PT1H/{}", end.to_rfc3339()).parse().unwrap();
+    let times = take(&mut interval.into_schedule(), 10);
+    assert_eq!(times.len(), 5);
+    assert!(times[0] >= Utc::now() - TimeDelta::seconds(1));
+    assert_eq!(*times.last().unwrap(), end - TimeDelta::hours(1));
+
+    // an end in the past never runs
+    let interval: RepeatingInterval = "R/P1M/2025-01-01T00:00:00Z".parse().unwrap();
+    assert_eq!(interval.into_schedule().next(), None);
+}
+
+#[test]
+fn test_duration_schedule() {
+    let duration: IsoDuration = "PT10M".parse().unwrap();
+    let before = Utc::now();
+    let times = take(&mut duration.into_schedule(), 3);
+    assert!(times[0] >= before);
+    assert_eq!(times[1] - times[0], TimeDelta::minutes(10));
+    assert_eq!(times[2] - times[1], TimeDelta::minutes(10));
+}
+
+#[test]
+fn test_take() {
+    let start = Utc::now() + TimeDelta::hours(1);
+    let mut schedule = Period::new(TimeDelta::minutes(1), start).take(3);
+    assert_eq!(schedule.remaining(), 3);
+    schedule.forward_to(start + TimeDelta::seconds(90));
+    assert_eq!(schedule.remaining(), 1);
+    assert_eq!(schedule.next(), Some(start + TimeDelta::minutes(2)));
+    assert_eq!(schedule.next(), None);
+}
@@ -0,0 +1,270 @@
+use chrono::{DateTime, TimeDelta};
+use chrono_tz::{America::New_York, Tz};
+use tsuki_scheduler::prelude::*;
+
+mod common;
+use common::take;
+
+fn parse(text: &str) -> RRule {
+    text.parse().expect("valid rrule")
+}
+
+/// local times in new york
+fn ny(times: &[&str]) -> Vec<Dtu> {
+    times
+        .iter()
+        .map(|time| {
+            chrono::NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M")
+                .expect("valid time")
+                .and_local_timezone(New_York)
+                .single()
+                .expect("not ambiguous")
+                .to_utc()
+        })
+        .collect()
+}
+
+const NY_START: &str = "DTSTART;TZID=America/New_York:19970902T090000";
+
+#[test]
+fn test_daily_count() {
+    let mut schedule = parse(&format!("{NY_START}\nRRULE:FREQ=DAILY;COUNT=10"));
+    let runs = take(&mut schedule, 20);
+    assert_eq!(runs.len(), 10);
+    assert_eq!(runs[0], ny(&["1997-09-02 09:00"])[0]);
+    assert_eq!(runs[9], ny(&["1997-09-11 09:00"])[0]);
+}
+
+#[test]
+fn test_weekly_until() {
+    let mut schedule = parse(&format!(
+        "{NY_START}\nRRULE:FREQ=WEEKLY;UNTIL=19971007T000000Z;WKST=SU;BYDAY=TU,TH"
+    ));
+    assert_eq!(
+        take(&mut schedule, 20),
+        ny(&[
+            "1997-09-02 09:00",
+            "1997-09-04 09:00",
+            "1997-09-09 09:00",
+            "1997-09-11 09:00",
+            "1997-09-16 09:00",
+            "1997-09-18 09:00",
+            "1997-09-23 09:00",
+            "1997-09-25 09:00",
+            "1997-09-30 09:00",
+            "1997-10-02 09:00",
+        ])
+    );
+}
+
+#[test]
+fn test_monthly_nth_weekday() {
+    let mut schedule = parse(
+        "DTSTART;TZID=America/New_York:19970905T090000\nRRULE:FREQ=MONTHLY;COUNT=10;BYDAY=1FR",
+    );
+    assert_eq!(
+        take(&mut schedule, 20),
+        ny(&[
+            "1997-09-05 09:00",
+            "1997-10-03 09:00",
+            "1997-11-07 09:00",
+            "1997-12-05 09:00",
+            "1998-01-02 09:00",
+            "1998-02-06 09:00",
+            "1998-03-06 09:00",
+            "1998-04-03 09:00",
+            "1998-05-01 09:00",
+            "1998-06-05 09:00",
+        ])
+    );
+
+    let mut schedule =
+        parse("DTSTART;TZID=America/New_York:19970928T090000\nRRULE:FREQ=MONTHLY;BYMONTHDAY=-3");
+    assert_eq!(
+        take(&mut schedule, 6),
+        ny(&[
+            "1997-09-28 09:00",
+            "1997-10-29 09:00",
+            "1997-11-28 09:00",
+            "1997-12-29 09:00",
+            "1998-01-29 09:00",
+            "1998-02-26 09:00",
+        ])
+    );
+}
+
+#[test]
+fn test_yearly_rules() {
+    let mut schedule =
+        parse("DTSTART;TZID=America/New_York:19970519T090000\nRRULE:FREQ=YEARLY;BYDAY=20MO");
+    assert_eq!(
+        take(&mut schedule, 3),
+        ny(&["1997-05-19 09:00", "1998-05-18 09:00", "1999-05-17 09:00"])
+    );
+
+    let mut schedule = parse(
+        "DTSTART;TZID=America/New_York:19970512T090000\nRRULE:FREQ=YEARLY;BYWEEKNO=20;BYDAY=MO",
+    );
+    assert_eq!(
+        take(&mut schedule, 3),
+        ny(&["1997-05-12 09:00", "1998-05-11 09:00", "1999-05-17 09:00"])
+    );
+}
+
+#[test]
+fn test_set_pos_and_exdate() {
+    // the last work day of the month
+    let mut schedule = parse(
+        "DTSTART;TZID=America/New_York:19970929T090000\n\
+         RRULE:FREQ=MONTHLY;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-1",
+    );
+    assert_eq!(
+        take(&mut schedule, 4),
+        ny(&[
+            "1997-09-29 09:00",
+            "1997-09-30 09:00",
+            "1997-10-31 09:00",
+            "1997-11-28 09:00",
+        ])
+    );
+
+    // every friday the 13th, DTSTART is excluded
+    let mut schedule = parse(&format!(
+        "{NY_START}\nEXDATE;TZID=America/New_York:19970902T090000\n\
+         RRULE:FREQ=MONTHLY;BYDAY=FR;BYMONTHDAY=13"
+    ));
+    assert_eq!(
+        take(&mut schedule, 5),
+        ny(&[
+            "1998-02-13 09:00",
+            "1998-03-13 09:00",
+            "1998-11-13 09:00",
+            "1999-08-13 09:00",
+            "2000-10-13 09:00",
+        ])
+    );
+}
+
+#[test]
+fn test_sub_daily_and_rdate() {
+    let mut schedule = parse(&format!(
+        "{NY_START}\n\
+         RRULE:FREQ=MINUTELY;INTERVAL=20;BYHOUR=9,10,11,12,13,14,15,16;BYDAY=MO,TU,WE,TH,FR\n\
+         RDATE;TZID=America/New_York:19970902T093000"
+    ));
+    assert_eq!(
+        take(&mut schedule, 5),
+        ny(&[
+            "1997-09-02 09:00",
+            "1997-09-02 09:20",
+            "1997-09-02 09:30",
+            "1997-09-02 09:40",
+            "1997-09-02 10:00",
+        ])
+    );
+    // friday 16:40 is followed by monday 09:00
+    let friday = ny(&["1997-09-05 16:40"])[0];
+    schedule.forward_to(friday - TimeDelta::seconds(1));
+    assert_eq!(
+        take(&mut schedule, 2),
+        ny(&["1997-09-05 16:40", "1997-09-08 09:00"])
+    );
+}
+
+#[test]
+fn test_forward_to_jumps() {
+    let mut jumped = parse(&format!(
+        "{NY_START}\nRRULE:FREQ=MONTHLY;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-1"
+    ));
+    let mut walked = jumped.clone();
+    let target = ny(&["2097-06-15 00:00"])[0];
+    jumped.forward_to(target);
+    while walked.peek_next().is_some_and(|next| next <= target) {
+        walked.next();
+    }
+    assert_eq!(take(&mut jumped, 3), take(&mut walked, 3));
+    assert_eq!(jumped.peek_next(), walked.peek_next());
+}
+
+#[test]
+fn test_never_matching_rule_is_exhausted() {
+    let mut schedule = parse(
+        "DTSTART:20250101T000000Z\nEXDATE:20250101T000000Z\n\
+         RRULE:FREQ=YEARLY;BYMONTH=2;BYMONTHDAY=30",
+    );
+    assert_eq!(schedule.next(), None);
+}
+
+#[test]
+fn test_never_matching_sub_daily_rule_is_exhausted_quickly() {
+    let start = std::time::Instant::now();
+    let mut schedule = parse(
+        "DTSTART:20250101T000000Z\nEXDATE:20250101T000000Z\nRRULE:FREQ=MINUTELY;INTERVAL=2;BYMINUTE=1",
+    );
+    assert_eq!(schedule.next(), None);
+    let mut schedule = parse(
+        "DTSTART:20250101T000000Z\nEXDATE:20250101T000000Z\nRRULE:FREQ=SECONDLY;INTERVAL=2;BYHOUR=3;BYSECOND=1",
+    );
+    assert_eq!(schedule.next(), None);
+    assert!(start.elapsed() < std::time::Duration::from_secs(5));
+    // whole hours are skipped
+    let mut schedule = parse("DTSTART:20250101T000000Z\nRRULE:FREQ=SECONDLY;BYHOUR=23;BYMINUTE=59");
+    assert_eq!(
+        take(&mut schedule, 2)[1],
+        "2025-01-01T23:59:00Z".parse::<Dtu>().expect("valid time")
+    );
+}
+
+#[test]
+fn test_round_trip() {
+    let text = "DTSTART;TZID=America/New_York:19970902T090000\n\
+                RRULE:FREQ=MONTHLY;INTERVAL=2;COUNT=10;BYDAY=1SU,-1SU\n\
+                EXDATE:19971102T140000Z";
+    let schedule = parse(text);
+    assert_eq!(schedule.to_string(), text);
+    assert_eq!(schedule.dtstart().timezone(), Tz::America__New_York);
+
+    let rule: RecurrenceRule = "FREQ=WEEKLY;UNTIL=19971224T000000Z;WKST=SU;BYDAY=TU,TH"
+        .parse()
+        .expect("valid rule");
+    assert_eq!(
+        rule.to_string(),
+        "FREQ=WEEKLY;UNTIL=19971224T000000Z;BYDAY=TU,TH;WKST=SU"
+    );
+
+    assert_eq!(
+        "RRULE:FREQ=DAILY".parse::<RRule>().err(),
+        Some(RRuleError::MissingDtStart)
+    );
+    assert!(
+        "FREQ=DAILY;COUNT=1;UNTIL=19971224"
+            .parse::<RecurrenceRule>()
+            .is_err()
+    );
+    assert!(
+        "DTSTART;TZID=Mars/Olympus:19970902T090000"
+            .parse::<RRule>()
+            .is_err()
+    );
+}
+
+#[test]
+fn test_as_time_set() {
+    // only on the second tuesday of each month, checked every hour
+    let second_tuesday = parse(&format!("{NY_START}\nRRULE:FREQ=MONTHLY;BYDAY=2TU"));
+    let start = ny(&["1997-09-01 00:00"])[0];
+    let mut schedule = Iter::new((0..24 * 120).map(move |hour| start + TimeDelta::hours(hour)))
+        .filtered_in(second_tuesday);
+    let runs: Vec<DateTime<_>> = std::iter::from_fn(|| schedule.next()).collect();
+    // DTSTART is always part of the set
+    assert_eq!(
+        runs,
+        ny(&[
+            "1997-09-02 09:00",
+            "1997-09-09 09:00",
+            "1997-10-14 09:00",
+            "1997-11-11 09:00",
+            "1997-12-09 09:00",
+        ])
+    );
+}
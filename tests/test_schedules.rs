@@ -189,3 +189,129 @@ pub fn test_throttling_boundary_is_inclusive() {
         assert_eq!(schedule.next(), Some(day_0 + delta * i));
     }
 }
+
+#[test]
+fn test_backoff() {
+    let from = DateTime::parse_from_rfc3339("2025-01-01T00:00:00Z")
+        .expect("invalid")
+        .to_utc();
+    let seconds = |s: i64| from + TimeDelta::seconds(s);
+
+    let mut exponential = Backoff::exponential(TimeDelta::seconds(1), 3.0, from)
+        .with_cap(TimeDelta::seconds(10))
+        .with_max_elapsed(TimeDelta::seconds(40));
+    let points: Vec<_> = std::iter::from_fn(|| exponential.next()).collect();
+    assert_eq!(
+        points,
+        [1, 4, 13, 23, 33].map(seconds),
+        "1, 3, 9, then capped at 10"
+    );
+    assert_eq!(exponential.attempt(), 5);
+
+    let mut fibonacci = Backoff::fibonacci(TimeDelta::seconds(1), from).take(6);
+    let points: Vec<_> = std::iter::from_fn(|| fibonacci.next()).collect();
+    assert_eq!(points, [1, 2, 4, 7, 12, 20].map(seconds));
+
+    // restart after a success
+    let mut retry = Backoff::exponential(TimeDelta::seconds(1), 2.0, from);
+    retry.next();
+    retry.next();
+    retry.restart(seconds(100));
+    assert_eq!(retry.next(), Some(seconds(101)));
+    assert_eq!(retry.next(), Some(seconds(103)));
+}
+
+#[test]
+fn test_backoff_forward_to() {
+    let from = DateTime::parse_from_rfc3339("2025-01-01T00:00:00Z")
+        .expect("invalid")
+        .to_utc();
+    let backoffs = [
+        Backoff::exponential(TimeDelta::seconds(1), 2.0, from),
+        Backoff::exponential(TimeDelta::seconds(1), 1.5, from).with_cap(TimeDelta::minutes(1)),
+        Backoff::exponential(TimeDelta::seconds(7), 1.0, from),
+        Backoff::fibonacci(TimeDelta::milliseconds(300), from).with_cap(TimeDelta::hours(1)),
+        Backoff::exponential(TimeDelta::seconds(1), 1.0001, from),
+    ];
+    for backoff in backoffs {
+        for offset in [0, 1, 2, 3, 59, 60, 61, 1000, 86_400, 86_401] {
+            let dtu = from + TimeDelta::seconds(offset);
+            let mut forwarded = backoff.clone();
+            forwarded.forward_to(dtu);
+            let mut stepped = backoff.clone();
+            while stepped.peek_next().is_some_and(|next| next < dtu) {
+                stepped.next();
+            }
+            assert_eq!(
+                forwarded.peek_next(),
+                stepped.peek_next(),
+                "{backoff:?} {dtu}"
+            );
+            assert_eq!(forwarded.attempt(), stepped.attempt(), "{backoff:?} {dtu}");
+            assert_eq!(forwarded.next(), stepped.next(), "{backoff:?} {dtu}");
+        }
+    }
+    // a year of capped retries is skipped at once
+    let mut capped =
+        Backoff::exponential(TimeDelta::milliseconds(1), 2.0, from).with_cap(TimeDelta::seconds(1));
+    capped.forward_to(from + TimeDelta::days(365));
+    assert!(capped.attempt() > 365 * 86_400 - 10);
+    assert!(capped.peek_next() >= Some(from + TimeDelta::days(365)));
+    // intervals too short for the multiplier to add a nanosecond never grow
+    let mut stalled = Backoff::exponential(TimeDelta::nanoseconds(10), 1.0001, from);
+    stalled.forward_to(from + TimeDelta::days(365));
+    assert_eq!(stalled.attempt(), 365 * 86_400 * 100_000_000 - 1);
+    assert_eq!(stalled.peek_next(), Some(from + TimeDelta::days(365)));
+}
+
+#[test]
+fn test_backoff_jitter() {
+    let from = DateTime::parse_from_rfc3339("2025-01-01T00:00:00Z")
+        .expect("invalid")
+        .to_utc();
+    let exact =
+        Backoff::exponential(TimeDelta::seconds(1), 2.0, from).with_cap(TimeDelta::minutes(1));
+    let intervals = |mut backoff: Backoff| {
+        let mut last = from;
+        (0..20)
+            .map(|_| {
+                let next = backoff.next().expect("infinite");
+                let interval = next - last;
+                last = next;
+                interval
+            })
+            .collect::<Vec<_>>()
+    };
+    let exact_intervals = intervals(exact.clone());
+    for jitter in [Jitter::Full, Jitter::Equal, Jitter::Decorrelated] {
+        let jittered = exact.clone().with_jitter(jitter).with_seed(42);
+        let jittered_intervals = intervals(jittered.clone());
+        // reproducible
+        assert_eq!(jittered_intervals, intervals(jittered.clone()));
+        assert_ne!(jittered_intervals, exact_intervals);
+        assert_ne!(
+            jittered_intervals,
+            intervals(jittered.clone().with_seed(7)),
+            "{jitter:?}"
+        );
+        for (jittered, exact) in jittered_intervals.iter().zip(&exact_intervals) {
+            match jitter {
+                Jitter::Full => assert!(*jittered <= *exact),
+                Jitter::Equal => assert!(*jittered >= *exact / 2 && *jittered <= *exact),
+                _ => assert!(
+                    *jittered >= TimeDelta::seconds(1) && *jittered <= TimeDelta::minutes(1)
+                ),
+            }
+        }
+        // two backoffs from the same time point draw different jitters
+        let unseeded = || {
+            let backoff = Backoff::exponential(TimeDelta::seconds(1), 2.0, from);
+            intervals(backoff.with_jitter(jitter))
+        };
+        assert_ne!(unseeded(), unseeded(), "{jitter:?}");
+        let mut forwarded = jittered;
+        let dtu = from + TimeDelta::hours(1);
+        forwarded.forward_to(dtu);
+        assert!(forwarded.peek_next() >= Some(dtu));
+    }
+}
@@ -0,0 +1,137 @@
+use chrono::TimeDelta;
+use tsuki_scheduler::prelude::*;
+
+mod common;
+use common::{every, utc};
+
+#[test]
+fn test_simulate_log() {
+    let (hourly, quarterly, once) = (TaskUid::new(1), TaskUid::new(2), TaskUid::new(3));
+    let start = utc(2025, 1, 1, 0, 0);
+    let scheduler = || {
+        let mut scheduler = Scheduler::new(Local::new());
+        scheduler.add_task(
+            hourly,
+            Task::local(every(TimeDelta::hours(1), start), || panic!("never runs")),
+        );
+        scheduler.add_task(
+            quarterly,
+            Task::local(every(TimeDelta::minutes(15), start), || {}),
+        );
+        scheduler.add_task(once, Task::local(utc(2025, 1, 1, 1, 30), || {}));
+        scheduler
+    };
+
+    let log = scheduler().simulate(utc(2025, 1, 1, 0, 30), utc(2025, 1, 1, 2, 0));
+    assert_eq!(
+        log.times_of(hourly),
+        [utc(2025, 1, 1, 1, 0), utc(2025, 1, 1, 2, 0)]
+    );
+    assert_eq!(log.count_of(quarterly), 7);
+    assert_eq!(log.times_of(once), [utc(2025, 1, 1, 1, 30)]);
+    assert_eq!(log.len(), 10);
+    // ordered by time, then by task uid
+    assert!(
+        log.runs()
+            .windows(2)
+            .all(|pair| { (pair[0].time(), pair[0].key()) <= (pair[1].time(), pair[1].key()) })
+    );
+    assert_eq!(log.runs()[2].key(), hourly);
+    assert_eq!(log.runs()[3].key(), quarterly);
+
+    // the runs before `from` are skipped
+    let log = scheduler().simulate(utc(2025, 1, 1, 2, 30), utc(2025, 1, 1, 3, 0));
+    assert_eq!(log.times_of(hourly), [utc(2025, 1, 1, 3, 0)]);
+    assert_eq!(log.count_of(quarterly), 3);
+    assert_eq!(log.count_of(once), 0);
+}
+
+#[test]
+fn test_simulate_far_from_start() {
+    let id = TaskUid::new(1);
+    let mut scheduler = Scheduler::new(Local::new());
+    // billions of runs before `from`, skipped at once
+    let start = utc(1970, 1, 1, 0, 0);
+    let schedule = Period::new_with_clock(TimeDelta::seconds(1), start, &ManualClock::new(start));
+    scheduler.add_task(id, Task::local(schedule, || {}));
+    let from = utc(2025, 1, 1, 0, 0);
+    let log = scheduler.simulate(from, from + TimeDelta::seconds(2));
+    assert_eq!(
+        log.times_of(id),
+        [
+            from,
+            from + TimeDelta::seconds(1),
+            from + TimeDelta::seconds(2)
+        ]
+    );
+}
+
+#[test]
+fn test_group_conflict() {
+    let (a, b, c) = (TaskUid::new(1), TaskUid::new(2), TaskUid::new(3));
+    let start = utc(2025, 1, 1, 0, 0);
+    let mut scheduler = Scheduler::new(Local::new());
+    scheduler.add_task(a, Task::local(every(TimeDelta::hours(1), start), || {}));
+    scheduler.add_task(
+        b,
+        Task::local(
+            every(TimeDelta::hours(1), start + TimeDelta::minutes(30)),
+            || {},
+        ),
+    );
+    scheduler.add_task(
+        c,
+        Task::local(
+            every(TimeDelta::hours(1), start + TimeDelta::minutes(2)),
+            || {},
+        ),
+    );
+    let log = scheduler.simulate(start, start + TimeDelta::days(1));
+
+    // the runs of the same task are never a conflict
+    assert!(
+        log.group([a])
+            .conflict_within(TimeDelta::hours(2))
+            .is_none()
+    );
+    assert!(
+        log.group([a, b])
+            .conflict_within(TimeDelta::minutes(5))
+            .is_none()
+    );
+    let (first, second) = log
+        .conflict_within(TimeDelta::minutes(5))
+        .expect("a and c are 2 minutes apart");
+    assert_eq!((first.key(), second.key()), (a, c));
+    assert_eq!(first.time(), start);
+}
+
+#[test]
+fn test_replaced_task() {
+    let id = TaskUid::new(1);
+    let start = utc(2025, 1, 1, 0, 0);
+    let mut scheduler = Scheduler::new(Local::new());
+    let outcome = scheduler.add_task(id, Task::local(every(TimeDelta::hours(1), start), || {}));
+    assert_eq!(outcome, AddOutcome::Added { next_run: start });
+    // the first schedule doesn't run anymore
+    let outcome = scheduler.add_task(id, Task::local(every(TimeDelta::hours(2), start), || {}));
+    assert_eq!(outcome, AddOutcome::Replaced { next_run: start });
+    assert_eq!(
+        scheduler.add_task(TaskUid::new(2), Task::local(Never, || {})),
+        AddOutcome::Exhausted
+    );
+    assert_eq!(scheduler.next_run(id), Some(start));
+
+    // deleted then added again, only the new one runs
+    let other = TaskUid::new(3);
+    scheduler.add_task(other, Task::local(every(TimeDelta::hours(1), start), || {}));
+    scheduler.delete_task(other);
+    scheduler.add_task(other, Task::local(utc(2025, 1, 1, 3, 0), || {}));
+
+    let log = scheduler.simulate(start, utc(2025, 1, 1, 4, 0));
+    assert_eq!(
+        log.times_of(id),
+        [start, utc(2025, 1, 1, 2, 0), utc(2025, 1, 1, 4, 0)]
+    );
+    assert_eq!(log.times_of(other), [utc(2025, 1, 1, 3, 0)]);
+}
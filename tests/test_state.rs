@@ -0,0 +1,96 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use tsuki_scheduler::{TaskRun, prelude::*};
+
+#[derive(Default)]
+struct Counter {
+    hits: AtomicUsize,
+}
+
+struct Config {
+    step: usize,
+}
+
+#[test]
+fn test_state_extractor() {
+    let mut scheduler = Scheduler::new(Local::new())
+        .with_state(Counter::default())
+        .with_state(Config { step: 2 });
+    scheduler.add_task(
+        TaskUid::new(0),
+        Task::new(
+            [now(), now()],
+            |counter: State<Counter>, config: State<Config>| {
+                counter.hits.fetch_add(config.step, Ordering::SeqCst);
+            },
+        ),
+    );
+    scheduler.execute_by_now();
+    let counter = scheduler.state::<Counter>().expect("registered");
+    assert_eq!(counter.hits.load(Ordering::SeqCst), 4);
+
+    // replaced by type
+    scheduler.insert_state(Config { step: 10 });
+    assert_eq!(
+        scheduler.state::<Config>().map(|config| config.step),
+        Some(10)
+    );
+    assert!(scheduler.state::<String>().is_none());
+}
+
+#[test]
+fn test_optional_state() {
+    let seen = Arc::new(AtomicUsize::new(0));
+    let mut scheduler = Scheduler::new(Local::new());
+    scheduler.add_task(
+        TaskUid::new(0),
+        Task::new(now(), {
+            let seen = seen.clone();
+            move |config: Option<State<Config>>| {
+                assert!(config.is_none());
+                seen.fetch_add(1, Ordering::SeqCst);
+            }
+        }),
+    );
+    scheduler.execute_by_now();
+    assert_eq!(seen.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+#[should_panic(expected = "no state of type")]
+fn test_missing_state() {
+    let mut scheduler = Scheduler::new(Local::new());
+    scheduler.add_task(TaskUid::new(0), Task::new(now(), |_: State<Config>| {}));
+    scheduler.execute_by_now();
+}
+
+#[test]
+fn test_twelve_arguments() {
+    let mut scheduler = Scheduler::new(Local::new()).with_state(Counter::default());
+    scheduler.add_task(
+        TaskUid::new(0),
+        Task::new(
+            now(),
+            |counter: State<Counter>,
+             _: TaskUid,
+             _: Dtu,
+             _: TaskRun,
+             _: RunCount,
+             _: PreviousRun,
+             _: Dispatched,
+             _: Lateness,
+             _: NextPlanned,
+             _: Option<State<Config>>,
+             _: TaskUid,
+             _: Dtu| {
+                counter.hits.fetch_add(1, Ordering::SeqCst);
+            },
+        ),
+    );
+    scheduler.execute_by_now();
+    let counter = scheduler.state::<Counter>().expect("registered");
+    assert_eq!(counter.hits.load(Ordering::SeqCst), 1);
+}
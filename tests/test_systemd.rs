@@ -0,0 +1,218 @@
+use chrono::{TimeDelta, TimeZone, Utc};
+use chrono_tz::Europe::Paris;
+use tsuki_scheduler::prelude::*;
+
+mod common;
+use common::{take, utc};
+
+fn event(expr: &str) -> CalendarEvent {
+    expr.parse().expect("valid calendar event")
+}
+
+#[test]
+fn test_weekdays_in_time_zone() {
+    let mut schedule =
+        event("Mon..Fri *-*-* 09:00:00 Europe/Paris").schedule_after(utc(2025, 1, 3, 12, 0));
+    assert_eq!(
+        take(&mut schedule, 3),
+        vec![
+            utc(2025, 1, 6, 8, 0),
+            utc(2025, 1, 7, 8, 0),
+            utc(2025, 1, 8, 8, 0)
+        ]
+    );
+    // 09:00 in Paris is 07:00 UTC in summer
+    let mut schedule = event("Sat,Sun 09:00 Europe/Paris").schedule_after(utc(2025, 7, 1, 0, 0));
+    assert_eq!(
+        take(&mut schedule, 2),
+        vec![utc(2025, 7, 5, 7, 0), utc(2025, 7, 6, 7, 0)]
+    );
+}
+
+#[test]
+fn test_dates_and_repetitions() {
+    let start = utc(2025, 1, 1, 0, 0);
+    let mut schedule = event("*-*-01 00:00 UTC").schedule_after(start);
+    assert_eq!(
+        take(&mut schedule, 2),
+        vec![utc(2025, 2, 1, 0, 0), utc(2025, 3, 1, 0, 0)]
+    );
+
+    let mut schedule = event("*:0/15 UTC").schedule_after(start);
+    assert_eq!(
+        take(&mut schedule, 3),
+        vec![
+            utc(2025, 1, 1, 0, 15),
+            utc(2025, 1, 1, 0, 30),
+            utc(2025, 1, 1, 0, 45)
+        ]
+    );
+
+    let mut schedule = event("2026..2027-03-10..12 08:30:15 UTC").schedule_after(start);
+    assert_eq!(
+        take(&mut schedule, 4),
+        vec![
+            utc(2026, 3, 10, 8, 30) + TimeDelta::seconds(15),
+            utc(2026, 3, 11, 8, 30) + TimeDelta::seconds(15),
+            utc(2026, 3, 12, 8, 30) + TimeDelta::seconds(15),
+            utc(2027, 3, 10, 8, 30) + TimeDelta::seconds(15)
+        ]
+    );
+
+    // every other year, from 2024
+    let mut schedule = event("2024/2-06-01 UTC").schedule_after(start);
+    assert_eq!(
+        take(&mut schedule, 2),
+        vec![utc(2026, 6, 1, 0, 0), utc(2028, 6, 1, 0, 0)]
+    );
+}
+
+#[test]
+fn test_last_days() {
+    let start = utc(2024, 1, 15, 0, 0);
+    let mut last_day = event("*-*~01 UTC").schedule_after(start);
+    assert_eq!(
+        take(&mut last_day, 2),
+        vec![utc(2024, 1, 31, 0, 0), utc(2024, 2, 29, 0, 0)]
+    );
+    let mut third_last = event("*-02~03 UTC").schedule_after(start);
+    assert_eq!(third_last.next(), Some(utc(2024, 2, 27, 0, 0)));
+    // the last monday of may
+    let mut last_monday = event("Mon *-05~07/1 UTC").schedule_after(start);
+    assert_eq!(
+        take(&mut last_monday, 2),
+        vec![utc(2024, 5, 27, 0, 0), utc(2025, 5, 26, 0, 0)]
+    );
+}
+
+#[test]
+fn test_shorthands() {
+    let start = utc(2025, 5, 17, 10, 30) + TimeDelta::seconds(30);
+    let next = |expr: &str| event(expr).next_after(start);
+    assert_eq!(next("minutely UTC"), Some(utc(2025, 5, 17, 10, 31)));
+    assert_eq!(next("hourly UTC"), Some(utc(2025, 5, 17, 11, 0)));
+    assert_eq!(next("daily UTC"), Some(utc(2025, 5, 18, 0, 0)));
+    assert_eq!(next("weekly UTC"), Some(utc(2025, 5, 19, 0, 0)));
+    assert_eq!(next("monthly UTC"), Some(utc(2025, 6, 1, 0, 0)));
+    assert_eq!(next("quarterly UTC"), Some(utc(2025, 7, 1, 0, 0)));
+    assert_eq!(next("semiannually UTC"), Some(utc(2025, 7, 1, 0, 0)));
+    assert_eq!(next("yearly UTC"), Some(utc(2026, 1, 1, 0, 0)));
+    assert_eq!(event("daily").time_zone(), None);
+    assert_eq!(event("daily Europe/Paris").time_zone(), Some(Paris));
+}
+
+#[test]
+fn test_time_set() {
+    let set = event("Mon..Fri 09..17:00 UTC");
+    assert!(set.contains(utc(2025, 1, 6, 9, 0)));
+    assert!(!set.contains(utc(2025, 1, 6, 9, 0) + TimeDelta::seconds(1)));
+    assert!(!set.contains(utc(2025, 1, 4, 9, 0)));
+    assert_eq!(
+        set.lower_bound(utc(2025, 1, 3, 17, 0) + TimeDelta::seconds(1)),
+        LowerBound::At(utc(2025, 1, 6, 9, 0))
+    );
+    // the 30th of february never comes
+    assert_eq!(
+        event("*-02-30 UTC").lower_bound(utc(2025, 1, 1, 0, 0)),
+        LowerBound::Never
+    );
+
+    // forward_to jumps like the time set
+    let mut schedule = set.clone().schedule_after(utc(2025, 1, 1, 0, 0));
+    schedule.forward_to(utc(2025, 1, 3, 17, 0));
+    assert_eq!(schedule.next(), Some(utc(2025, 1, 6, 9, 0)));
+}
+
+#[test]
+fn test_dst() {
+    // 02:30 doesn't exist on the 30th of march in Paris, it is moved to 03:30
+    let mut schedule = event("*-*-* 02:30 Europe/Paris").schedule_after(utc(2025, 3, 29, 12, 0));
+    let expected = Paris
+        .with_ymd_and_hms(2025, 3, 30, 3, 30, 0)
+        .single()
+        .expect("valid time");
+    assert_eq!(schedule.next(), Some(expected.to_utc()));
+    // 02:30 happens twice on the 26th of october, it runs once
+    let mut schedule = event("*-*-* 02:30 Europe/Paris").schedule_after(utc(2025, 10, 25, 12, 0));
+    assert_eq!(
+        take(&mut schedule, 2),
+        vec![utc(2025, 10, 26, 0, 30), utc(2025, 10, 27, 1, 30)]
+    );
+}
+
+#[test]
+fn test_invalid_events() {
+    for expr in [
+        "",
+        "*-13-01",
+        "Funday",
+        "*-*-* 25:00",
+        "*-*-* 10:00 Mars/Olympus",
+        "*-*-* 10:00 11:00",
+        "*-*-*/0",
+    ] {
+        assert!(expr.parse::<CalendarEvent>().is_err(), "{expr}");
+    }
+    assert_eq!(
+        "*-*-* 10:00 Mars/Olympus".parse::<CalendarEvent>(),
+        Err(SystemdError::UnknownTimeZone("Mars/Olympus".to_string()))
+    );
+}
+
+#[test]
+fn test_time_spans() {
+    let span = |text: &str| {
+        text.parse::<TimeSpan>()
+            .expect("valid time span")
+            .as_delta()
+    };
+    assert_eq!(span("1h 30min"), TimeDelta::minutes(90));
+    assert_eq!(span("1h30min"), TimeDelta::minutes(90));
+    assert_eq!(span("2d"), TimeDelta::days(2));
+    assert_eq!(span("45"), TimeDelta::seconds(45));
+    assert_eq!(span("1.5s 300ms"), TimeDelta::milliseconds(1800));
+    assert_eq!(
+        span("1w 1M 1y"),
+        TimeDelta::seconds(604_800 + 2_629_800 + 31_557_600)
+    );
+    assert!("1 fortnight".parse::<TimeSpan>().is_err());
+    assert!("".parse::<TimeSpan>().is_err());
+    // a zero span can't be the interval of a schedule
+    assert_eq!(
+        "0".parse::<TimeSpan>(),
+        Err(SystemdError::InvalidTimeSpan("0".to_string()))
+    );
+    assert!("0s 0ms".parse::<TimeSpan>().is_err());
+    assert_eq!(
+        "90min".parse::<TimeSpan>().expect("valid").to_string(),
+        "1h 30min"
+    );
+
+    // like OnUnitActiveSec=, the first run is one span after the start
+    let start = Utc::now();
+    let mut schedule = "15min"
+        .parse::<TimeSpan>()
+        .expect("valid")
+        .schedule_from(start);
+    assert_eq!(
+        take(&mut schedule, 2),
+        vec![
+            start + TimeDelta::minutes(15),
+            start + TimeDelta::minutes(30)
+        ]
+    );
+}
+
+#[test]
+fn test_virtual_clock() {
+    let clock = ManualClock::new(utc(2000, 1, 1, 0, 0));
+    let mut schedule = "1h"
+        .parse::<TimeSpan>()
+        .expect("valid")
+        .into_schedule_with_clock(&clock);
+    assert_eq!(schedule.next(), Some(utc(2000, 1, 1, 1, 0)));
+    let mut schedule = OnCalendar::from_expr_with_clock("*-*-01 00:00 UTC", &clock).expect("valid");
+    assert_eq!(schedule.next(), Some(utc(2000, 2, 1, 0, 0)));
+    let mut schedule = event("daily UTC").schedule_with_clock(&clock);
+    assert_eq!(schedule.next(), Some(utc(2000, 1, 2, 0, 0)));
+}
@@ -40,3 +40,137 @@ fn test_thread_schedule() {
     assert_eq!(task_0_run_count.load(Ordering::SeqCst), 1);
     assert_eq!(task_1_run_count.load(Ordering::SeqCst), 2);
 }
+
+#[test]
+fn test_keyed_handles() {
+    use std::sync::mpsc;
+    use std::time::Duration;
+    let mut scheduler = Scheduler::new(Thread::new()).with_handle_manager(KeyedHandles::new());
+    let (short, long) = (TaskUid::new(0), TaskUid::new(1));
+    let (release, wait) = mpsc::channel::<()>();
+    let wait = std::sync::Arc::new(std::sync::Mutex::new(wait));
+    scheduler.add_task(short, Task::thread([now()], || {}));
+    scheduler.add_task(
+        long,
+        Task::thread([now()], move || {
+            let _ = wait.lock().unwrap().recv();
+        }),
+    );
+    scheduler.execute_by_now();
+    assert_eq!(scheduler.handle_manager.count(long), 1);
+    std::thread::sleep(Duration::from_millis(50));
+    scheduler.handle_manager.prune();
+    assert_eq!(scheduler.handle_manager.count(short), 0);
+    assert_eq!(scheduler.handle_manager.count(long), 1);
+
+    // threads can't be aborted, the default policy keeps them tracked
+    scheduler.delete_task(long);
+    assert_eq!(scheduler.handle_manager.keys().collect::<Vec<_>>(), [long]);
+    release.send(()).unwrap();
+    let (run, handle) = scheduler.handle_manager.take(long).remove(0);
+    assert_eq!(run.key(), long);
+    handle.unwrap().join().unwrap();
+}
+
+#[test]
+fn test_join_is_woken_by_the_thread() {
+    use std::sync::Arc;
+    use std::task::{Context, Wake, Waker};
+    use std::time::Duration;
+    struct Unpark(std::thread::Thread);
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+    let mut scheduler = Scheduler::new(Thread::new()).with_handle_manager(KeyedHandles::new());
+    let id = TaskUid::new(0);
+    scheduler.add_task(
+        id,
+        Task::thread([now()], || std::thread::sleep(Duration::from_millis(200))),
+    );
+    scheduler.execute_by_now();
+    let waker = Waker::from(Arc::new(Unpark(std::thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut polls = 0;
+    {
+        let mut join = std::pin::pin!(scheduler.handle_manager.join(id));
+        while join.as_mut().poll(&mut cx).is_pending() {
+            polls += 1;
+            std::thread::park();
+        }
+    }
+    // the join sleeps until the thread finishes instead of spinning
+    assert!(polls < 5, "{polls} polls");
+    assert_eq!(scheduler.handle_manager.count(id), 0);
+}
+
+#[test]
+fn test_thread_feedback() {
+    use chrono::TimeDelta;
+    use std::time::Duration;
+    let clock = ManualClock::new(now());
+    let start = clock.now();
+    let mut scheduler = Scheduler::new(Thread::new()).with_clock(clock.clone());
+    let id = TaskUid::new(0);
+    scheduler.add_task(
+        id,
+        Task::thread_feedback([start], |_| {
+            std::thread::sleep(Duration::from_millis(50));
+            NextRun::In(TimeDelta::hours(1))
+        }),
+    );
+    scheduler.execute_by_now();
+    // the default schedule is exhausted, the task waits for the thread
+    assert_eq!(scheduler.next_run(id), None);
+    assert!(scheduler.list().is_empty());
+    std::thread::sleep(Duration::from_millis(200));
+    scheduler.execute_by_now();
+    assert_eq!(scheduler.next_run(id), Some(start + TimeDelta::hours(1)));
+    assert_eq!(scheduler.list().len(), 1);
+}
+
+#[test]
+fn test_thread_feedback_ends_with_its_default_schedule() {
+    use std::time::Duration;
+    let mut scheduler = Scheduler::new(Thread::new());
+    let id = TaskUid::new(0);
+    scheduler.add_task(
+        id,
+        Task::thread_feedback([now()], |_| {
+            std::thread::sleep(Duration::from_millis(50));
+            NextRun::Default
+        }),
+    );
+    scheduler.execute_by_now();
+    assert_eq!(scheduler.next_run(id), None);
+    std::thread::sleep(Duration::from_millis(200));
+    scheduler.execute_by_now();
+    // nothing left to wait for, the task is removed
+    assert!(scheduler.delete_task(id).is_none());
+}
+
+#[test]
+fn test_trigger_now_overlaps_running_runs() {
+    use std::sync::{Arc, Barrier};
+    let mut scheduler = Scheduler::new(Thread::new()).with_handle_manager(KeyedHandles::new());
+    let id = TaskUid::new(0);
+    let barrier = Arc::new(Barrier::new(3));
+    scheduler.add_task(
+        id,
+        Task::thread(now() + chrono::TimeDelta::hours(1), {
+            let barrier = barrier.clone();
+            move || {
+                barrier.wait();
+            }
+        }),
+    );
+    // no overlap policy, both runs are running together
+    scheduler.trigger_now(id).expect("the task exists");
+    scheduler.trigger_now(id).expect("the task exists");
+    assert_eq!(scheduler.handle_manager.count(id), 2);
+    barrier.wait();
+    for (_, handle) in scheduler.handle_manager.take(id) {
+        handle.unwrap().join().unwrap();
+    }
+}
@@ -37,3 +37,83 @@ async fn test_tokio_schedule() {
     assert_eq!(task_0_run_count.load(Ordering::SeqCst), 1);
     assert_eq!(task_1_run_count.load(Ordering::SeqCst), 2);
 }
+
+#[tokio::test]
+async fn test_join_set_manager() {
+    use std::time::Duration;
+    let mut scheduler = Scheduler::new(Tokio).with_handle_manager(JoinSetManager::new());
+    let (ok, panics, slow) = (TaskUid::new(0), TaskUid::new(1), TaskUid::new(2));
+    let start = now();
+    scheduler.add_task(ok, Task::new_async([start, start], || async {}));
+    scheduler.add_task(
+        panics,
+        Task::new_async([start, start + chrono::TimeDelta::hours(1)], || async {
+            panic!("oops")
+        }),
+    );
+    scheduler.add_task(
+        slow,
+        Task::new_async([start, start], || async {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+        }),
+    );
+    scheduler.execute_by_now();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let manager = &mut scheduler.handle_manager;
+    manager.reap();
+    assert_eq!(manager.stats(ok).succeeded, 2);
+    assert_eq!(manager.running(ok), 0);
+    assert_eq!(manager.stats(panics).panicked, 1);
+    assert_eq!(manager.running(slow), 2);
+    assert_eq!(manager.stats(slow).finished(), 0);
+    let panicked = manager.take_panics();
+    assert_eq!(panicked.len(), 1);
+    assert_eq!(panicked[0].0.key(), panics);
+    assert!(manager.take_panics().is_empty());
+    scheduler.trigger_now(panics).expect("the task exists");
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    scheduler.handle_manager.reap();
+    assert_eq!(scheduler.handle_manager.stats(panics).panicked, 2);
+
+    // deleting a task aborts its runs and forgets it
+    scheduler.delete_task(panics);
+    scheduler.delete_task(slow);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let manager = &mut scheduler.handle_manager;
+    manager.reap();
+    assert_eq!(manager.running(slow), 0);
+    assert_eq!(manager.stats(slow), RunStats::default());
+    assert_eq!(manager.stats(panics), RunStats::default());
+    assert!(manager.take_panics().is_empty());
+    assert_eq!(manager.stats(ok).succeeded, 2);
+}
+
+#[tokio::test]
+async fn test_keyed_handles_on_delete() {
+    use std::time::Duration;
+    let long = || {
+        Task::new_async([now()], || async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        })
+    };
+    let id = TaskUid::new(0);
+
+    // the runs are awaited
+    let mut scheduler = Scheduler::new(Tokio).with_handle_manager(KeyedHandles::new());
+    scheduler.add_task(id, long());
+    scheduler.execute_by_now();
+    scheduler.delete_task(id);
+    assert_eq!(scheduler.handle_manager.count(id), 1);
+    let start = std::time::Instant::now();
+    scheduler.handle_manager.join(id).await;
+    assert!(start.elapsed() >= Duration::from_millis(80));
+    assert_eq!(scheduler.handle_manager.count(id), 0);
+
+    // the runs are dropped
+    let handles = KeyedHandles::new().with_on_delete(OnDelete::Detach);
+    let mut scheduler = Scheduler::new(Tokio).with_handle_manager(handles);
+    scheduler.add_task(id, long());
+    scheduler.execute_by_now();
+    scheduler.delete_task(id);
+    assert_eq!(scheduler.handle_manager.count(id), 0);
+}
@@ -0,0 +1,131 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use chrono::TimeDelta;
+use tsuki_scheduler::{prelude::*, timeset::Range};
+
+mod common;
+use common::{at, every};
+
+fn counting(runs: &Arc<AtomicUsize>) -> impl Fn() + Send + Clone + 'static {
+    let runs = runs.clone();
+    move || {
+        runs.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn test_trigger_alongside_cadence() {
+    let clock = ManualClock::new(at(0, 0));
+    let mut scheduler = Scheduler::new(Local::new()).with_clock(clock.clone());
+    let runs = Arc::new(AtomicUsize::new(0));
+    let trigger = Trigger::new();
+    let handle = trigger.handle();
+    let id = TaskUid::new(0);
+    scheduler.add_task(
+        id,
+        Task::local(
+            every(TimeDelta::hours(1), at(1, 0)).or(trigger),
+            counting(&runs),
+        ),
+    );
+    assert_eq!(scheduler.next_run(id), Some(at(1, 0)));
+
+    clock.set(at(0, 10));
+    handle.fire_at(clock.now());
+    scheduler.execute_by_now();
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+    // the planned run is kept
+    assert_eq!(scheduler.next_run(id), Some(at(1, 0)));
+
+    // a later push waits for its turn
+    handle.fire_at(at(1, 30));
+    scheduler.execute_by_now();
+    assert_eq!(scheduler.next_run(id), Some(at(1, 0)));
+    clock.set(at(1, 0));
+    scheduler.execute_by_now();
+    assert_eq!(scheduler.next_run(id), Some(at(1, 30)));
+    clock.set(at(1, 30));
+    scheduler.execute_by_now();
+    assert_eq!(runs.load(Ordering::SeqCst), 3);
+    assert_eq!(scheduler.next_run(id), Some(at(2, 0)));
+}
+
+#[test]
+fn test_trigger_waits() {
+    let clock = ManualClock::new(at(0, 0));
+    let mut scheduler = Scheduler::new(Local::new()).with_clock(clock.clone());
+    let runs = Arc::new(AtomicUsize::new(0));
+    let trigger = Trigger::new();
+    let handle = trigger.handle();
+    let id = TaskUid::new(0);
+    let outcome = scheduler.add_task(id, Task::local(trigger, counting(&runs)));
+    assert_eq!(outcome, AddOutcome::Waiting { replaced: false });
+    assert_eq!(scheduler.next_run(id), None);
+    assert!(scheduler.list().is_empty());
+    assert_eq!(scheduler.next_deadline(), None);
+
+    for fired in 1..=3 {
+        handle.fire_at(clock.now());
+        scheduler.execute_by_now();
+        assert_eq!(runs.load(Ordering::SeqCst), fired);
+        // waiting again, not removed
+        assert_eq!(scheduler.next_run(id), None);
+    }
+    scheduler.delete_task(id);
+    handle.fire_at(clock.now());
+    scheduler.execute_by_now();
+    assert_eq!(runs.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn test_trigger_throttled_and_filtered() {
+    let clock = ManualClock::new(at(0, 0));
+    let mut scheduler = Scheduler::new(Local::new()).with_clock(clock.clone());
+    let runs = Arc::new(AtomicUsize::new(0));
+    let trigger = Trigger::new();
+    let handle = trigger.handle();
+    let id = TaskUid::new(0);
+    // at most once every 10 minutes, never after 12:00
+    let schedule = trigger
+        .throttling(TimeDelta::minutes(10))
+        .filtered_in(Range::before(at(12, 0)));
+    scheduler.add_task(id, Task::local(schedule, counting(&runs)));
+
+    handle.fire_at(at(0, 0));
+    handle.fire_at(at(0, 5));
+    scheduler.execute_by_now();
+    clock.set(at(0, 5));
+    scheduler.execute_by_now();
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+    clock.set(at(0, 10));
+    handle.fire_at(clock.now());
+    scheduler.execute_by_now();
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+
+    clock.set(at(13, 0));
+    handle.fire_at(clock.now());
+    scheduler.execute_by_now();
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn test_fire_by_the_scheduler_clock() {
+    let clock = ManualClock::new(at(0, 0));
+    let mut scheduler = Scheduler::new(Local::new()).with_clock(clock.clone());
+    let trigger = Trigger::new();
+    let handle = trigger.handle();
+    let id = TaskUid::new(0);
+    scheduler.add_task(
+        id,
+        Task::local(every(TimeDelta::hours(1), at(1, 0)).or(trigger), || {}),
+    );
+    clock.set(at(0, 10));
+    handle.fire();
+    // take the pushed time point without running it
+    scheduler.execute(at(0, 0));
+    assert_eq!(scheduler.next_run(id), Some(at(0, 10)));
+}